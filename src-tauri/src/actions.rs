@@ -0,0 +1,559 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::cpuset::CpuSet;
+use crate::process::threads;
+use crate::process::trim;
+use crate::process::ProcessController;
+use crate::topology::PlacementContext;
+
+type Result<T> = std::result::Result<T, String>;
+
+// --- 参数类型 ---
+
+/// 亲和性目标，写在配置里的形式：
+///
+/// - `"0-3,8,10-11"`：显式列出的逻辑核心
+/// - `"last"`、`"last-2"`：最后 N 个逻辑核心
+/// - `"every-other"`：每隔一个逻辑核心（0、2、4…）
+/// - `"all"`：不限制
+/// - `"e-cores"`：能效核心（仅大小核架构），也可以写 `"efficiency"`
+/// - `"ccd0"`、`"ccd1"`：共享同一块末级缓存的核心组，从 0 开始编号
+/// - `"smt-sibling"`：不与游戏共享物理核心的 SMT 副线程
+/// - `"least-loaded"`、`"least-loaded-2"`：当前负载最低的 N 个逻辑核心
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Affinity {
+    /// 最后 N 个逻辑核心
+    Last(usize),
+    EveryOther,
+    /// 不限制，恢复到全部核心
+    All,
+    Efficiency,
+    Ccd(usize),
+    SmtSibling,
+    LeastLoaded(usize),
+    List(CpuSet),
+}
+
+impl Affinity {
+    /// 按拓扑和当前负载选出逻辑核心
+    pub fn select(&self, ctx: &PlacementContext) -> Result<CpuSet> {
+        let all: Vec<usize> = ctx.topology.cpus().iter().map(|c| c.id).collect();
+        let count = all.len();
+        let check_count = |n: usize| {
+            if n == 0 || n > count { Err(format!("核心数量 {} 超出范围 (1-{})", n, count)) } else { Ok(n) }
+        };
+        let cpus: CpuSet = match self {
+            Affinity::All => all.into_iter().collect(),
+            Affinity::Last(n) => all[count - check_count(*n)?..].iter().copied().collect(),
+            Affinity::EveryOther => all.into_iter().step_by(2).collect(),
+            Affinity::Efficiency => {
+                let cpus = ctx.topology.efficiency_cpus();
+                if cpus.is_empty() {
+                    return Err("当前 CPU 没有能效核心".to_string());
+                }
+                cpus
+            }
+            Affinity::Ccd(index) => {
+                let groups = ctx.topology.llc_groups();
+                let group = groups
+                    .get(*index)
+                    .ok_or_else(|| format!("CCD {} 不存在（本机共 {} 个）", index, groups.len()))?;
+                group.iter().copied().collect()
+            }
+            Affinity::SmtSibling => {
+                let sibling = ctx.smt_sibling().ok_or_else(|| {
+                    if ctx.topology.cores().iter().any(|core| core.len() > 1) {
+                        "所有开启 SMT 的核心都被游戏占用".to_string()
+                    } else {
+                        "当前 CPU 没有开启 SMT".to_string()
+                    }
+                })?;
+                CpuSet::from_iter([sibling])
+            }
+            Affinity::LeastLoaded(n) => ctx.by_load().into_iter().take(check_count(*n)?).collect(),
+            Affinity::List(list) => {
+                if let Some(missing) = list.iter().find(|&id| ctx.topology.get(id).is_none()) {
+                    return Err(format!("逻辑核心 {} 不存在（本机为 {}）", missing, ctx.topology.all()));
+                }
+                list.clone()
+            }
+        };
+        if cpus.is_empty() {
+            return Err(format!("亲和性 {} 没有选中任何核心", self));
+        }
+        Ok(cpus)
+    }
+}
+
+impl FromStr for Affinity {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let count = |prefix: &str, other: &str| {
+            other.strip_prefix(prefix).and_then(|n| n.parse().ok()).filter(|&n: &usize| n > 0)
+        };
+        match s.trim() {
+            "all" => Ok(Affinity::All),
+            "last" => Ok(Affinity::Last(1)),
+            "every-other" => Ok(Affinity::EveryOther),
+            "e-cores" | "efficiency" => Ok(Affinity::Efficiency),
+            "smt-sibling" => Ok(Affinity::SmtSibling),
+            "least-loaded" => Ok(Affinity::LeastLoaded(1)),
+            other if other.starts_with(|c: char| c.is_ascii_digit()) => {
+                let list: CpuSet = other.parse()?;
+                if list.is_empty() {
+                    return Err(format!("无法识别的亲和性: {}", other));
+                }
+                Ok(Affinity::List(list))
+            }
+            other => count("last-", other)
+                .map(Affinity::Last)
+                .or_else(|| other.strip_prefix("ccd").and_then(|n| n.parse().ok()).map(Affinity::Ccd))
+                .or_else(|| count("least-loaded-", other).map(Affinity::LeastLoaded))
+                .ok_or_else(|| format!("无法识别的亲和性: {}", other)),
+        }
+    }
+}
+
+impl fmt::Display for Affinity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Affinity::All => write!(f, "all"),
+            Affinity::Last(1) => write!(f, "last"),
+            Affinity::Last(n) => write!(f, "last-{}", n),
+            Affinity::EveryOther => write!(f, "every-other"),
+            Affinity::Efficiency => write!(f, "e-cores"),
+            Affinity::Ccd(index) => write!(f, "ccd{}", index),
+            Affinity::SmtSibling => write!(f, "smt-sibling"),
+            Affinity::LeastLoaded(1) => write!(f, "least-loaded"),
+            Affinity::LeastLoaded(n) => write!(f, "least-loaded-{}", n),
+            Affinity::List(list) => write!(f, "{}", list),
+        }
+    }
+}
+
+impl Serialize for Affinity {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Affinity {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        String::deserialize(deserializer)?.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CpuPriority {
+    Idle,
+    /// 恢复降级前记录的优先级
+    Normal,
+}
+
+/// 对应 IO_PRIORITY_HINT
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum IoPriority {
+    VeryLow,
+    Low,
+    Normal,
+}
+
+impl IoPriority {
+    pub fn hint(self) -> u32 {
+        match self {
+            IoPriority::VeryLow => 0,
+            IoPriority::Low => 1,
+            IoPriority::Normal => 2,
+        }
+    }
+}
+
+/// 对应 MEMORY_PRIORITY_*
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MemoryPriority {
+    VeryLow,
+    Low,
+    Medium,
+    BelowNormal,
+    Normal,
+}
+
+impl MemoryPriority {
+    pub fn level(self) -> u32 {
+        match self {
+            MemoryPriority::VeryLow => 1,
+            MemoryPriority::Low => 2,
+            MemoryPriority::Medium => 3,
+            MemoryPriority::BelowNormal => 4,
+            MemoryPriority::Normal => 5,
+        }
+    }
+}
+
+/// 线程级规则：CPU 占用超过阈值的线程单独降到空闲优先级
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ThreadRule {
+    /// 阈值，100 表示占满一个逻辑核心
+    #[serde(deserialize_with = "deserialize_percent")]
+    pub above_percent: f32,
+    /// 同时把热点线程绑到这些核心
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub affinity: Option<Affinity>,
+}
+
+// 反序列化和 `ActionSet::validate` 都会拒绝 NaN，阈值不会是 NaN
+impl Eq for ThreadRule {}
+
+impl ThreadRule {
+    pub fn validate(&self) -> Result<()> {
+        if !(0.0..=100.0).contains(&self.above_percent) {
+            return Err(format!("线程阈值 {} 超出范围 (0-100)", self.above_percent));
+        }
+        Ok(())
+    }
+}
+
+/// TOML 可以写 `nan`，前端和控制接口也可能传来任意数值，读入时就挡掉
+fn deserialize_percent<'de, D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<f32, D::Error> {
+    let value = f32::deserialize(deserializer)?;
+    let rule = ThreadRule { above_percent: value, affinity: None };
+    rule.validate().map_err(serde::de::Error::custom)?;
+    Ok(value)
+}
+
+// --- 动作集合 ---
+
+/// 动作类别，前端的开关按类别启用或停用规则里的动作
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ActionKind {
+    Affinity,
+    Priority,
+    Eco,
+    Io,
+    Memory,
+}
+
+impl ActionKind {
+    pub const ALL: [ActionKind; 5] = [
+        ActionKind::Affinity,
+        ActionKind::Priority,
+        ActionKind::Eco,
+        ActionKind::Io,
+        ActionKind::Memory,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ActionKind::Affinity => "CPU 亲和性",
+            ActionKind::Priority => "优先级",
+            ActionKind::Eco => "效率模式",
+            ActionKind::Io => "I/O 优先级",
+            ActionKind::Memory => "内存优先级",
+        }
+    }
+}
+
+impl FromStr for ActionKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim() {
+            "affinity" => Ok(ActionKind::Affinity),
+            "priority" => Ok(ActionKind::Priority),
+            "eco" => Ok(ActionKind::Eco),
+            "io" => Ok(ActionKind::Io),
+            "memory" => Ok(ActionKind::Memory),
+            other => Err(format!("无法识别的动作类别: {}", other)),
+        }
+    }
+}
+
+/// 一条规则要执行的动作，未设置的项保持进程原样
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ActionSet {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub affinity: Option<Affinity>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<CpuPriority>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub io: Option<IoPriority>,
+    /// 效率模式：true 开启，false 交还给系统
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub eco: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memory: Option<MemoryPriority>,
+    /// 同时修剪工作集（受 `TrimPolicy` 阈值和频率限制）
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub trim: bool,
+    /// 需要两次执行才能算出线程占用，第一次只采样
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub threads: Option<ThreadRule>,
+}
+
+/// 一次施加动作的结果
+#[derive(Debug, Default)]
+pub struct ActionReport {
+    pub errors: Vec<String>,
+    /// 成功设置的进程亲和性
+    pub cpus: Option<CpuSet>,
+    pub reclaimed_bytes: u64,
+    pub demoted_threads: usize,
+}
+
+impl ActionSet {
+    pub fn is_empty(&self) -> bool {
+        *self == ActionSet::default()
+    }
+
+    /// 只保留指定类别的动作；内存类别同时控制修剪，优先级类别同时控制线程规则
+    pub fn filtered(&self, kinds: &[ActionKind]) -> ActionSet {
+        let keep = |kind| kinds.contains(&kind);
+        ActionSet {
+            affinity: self.affinity.clone().filter(|_| keep(ActionKind::Affinity)),
+            priority: self.priority.filter(|_| keep(ActionKind::Priority)),
+            io: self.io.filter(|_| keep(ActionKind::Io)),
+            eco: self.eco.filter(|_| keep(ActionKind::Eco)),
+            memory: self.memory.filter(|_| keep(ActionKind::Memory)),
+            trim: self.trim && keep(ActionKind::Memory),
+            threads: self.threads.clone().filter(|_| keep(ActionKind::Priority)),
+        }
+    }
+
+    /// 检查线程阈值的范围，以及规则里的亲和性在本机上能否选出核心，例如列出的核心是否存在
+    pub fn validate(&self, ctx: &PlacementContext) -> Result<()> {
+        if let Some(rule) = &self.threads {
+            rule.validate()?;
+        }
+        let affinities = self.affinity.iter().chain(self.threads.iter().filter_map(|rule| rule.affinity.as_ref()));
+        for affinity in affinities {
+            affinity.select(ctx).map_err(|e| format!("亲和性 {} 无效: {}", affinity, e))?;
+        }
+        Ok(())
+    }
+
+    pub fn apply(&self, ctl: &dyn ProcessController, pid: u32, ctx: &PlacementContext) -> ActionReport {
+        let mut report = ActionReport::default();
+        if let Some(affinity) = &self.affinity {
+            match affinity.select(ctx).and_then(|cpus| ctl.set_cpu_affinity(pid, &cpus).map(|()| cpus)) {
+                Ok(cpus) => report.cpus = Some(cpus),
+                Err(e) => report.errors.push(e),
+            }
+        }
+
+        let mut check = |result: Result<()>| {
+            if let Err(e) = result { report.errors.push(e); }
+        };
+        match self.priority {
+            Some(CpuPriority::Idle) => check(ctl.set_idle_priority(pid)),
+            Some(CpuPriority::Normal) => check(ctl.restore_priority(pid)),
+            None => {}
+        }
+        match self.eco {
+            Some(true) => check(ctl.set_efficiency_mode(pid)),
+            Some(false) => check(ctl.clear_efficiency_mode(pid)),
+            None => {}
+        }
+        if let Some(io) = self.io {
+            check(ctl.set_io_priority(pid, io.hint()));
+        }
+        if let Some(memory) = self.memory.filter(|_| ctl.supports_memory_priority()) {
+            check(ctl.set_memory_priority(pid, memory.level()));
+        }
+        if self.trim {
+            match trim::trimmer().trim(ctl, pid) {
+                Ok(outcome) => report.reclaimed_bytes += outcome.reclaimed(),
+                Err(e) => report.errors.push(e),
+            }
+        }
+        if let Some(rule) = &self.threads {
+            let cpus = match rule.affinity.as_ref().map(|a| a.select(ctx)).transpose() {
+                Ok(cpus) => cpus,
+                Err(e) => { report.errors.push(e); return report; }
+            };
+            match threads::monitor().demote_hot(ctl, pid, rule.above_percent, cpus.as_ref()) {
+                Ok(outcome) => report.demoted_threads += outcome.demoted.len(),
+                Err(e) => report.errors.push(e),
+            }
+        }
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process::mock::{Call, MockController};
+    use crate::topology::{LogicalCpu, Topology};
+
+    /// 8 个逻辑核心：0-3 是两个带 SMT 的性能核心 (0,1)、(2,3)，共享缓存 0；4-7 是能效核心，共享缓存 1
+    fn hybrid() -> Topology {
+        Topology::new(
+            (0..8)
+                .map(|id| LogicalCpu {
+                    id,
+                    package: 0,
+                    core: if id < 4 { (id / 2 * 2) as u32 } else { id as u32 },
+                    efficiency_class: u8::from(id < 4),
+                    llc: Some(u32::from(id >= 4)),
+                })
+                .collect(),
+        )
+    }
+
+    fn cpus(ids: &[usize]) -> CpuSet {
+        ids.iter().copied().collect()
+    }
+
+    #[test]
+    fn affinity_round_trips_through_strings() {
+        for text in ["all", "last", "last-2", "every-other", "e-cores", "ccd1", "smt-sibling", "least-loaded", "least-loaded-3", "0-3,8"] {
+            let affinity: Affinity = text.parse().unwrap();
+            assert_eq!(affinity.to_string(), text);
+        }
+        assert_eq!("efficiency".parse::<Affinity>().unwrap(), Affinity::Efficiency);
+        assert_eq!(" last-4 ".parse::<Affinity>().unwrap(), Affinity::Last(4));
+    }
+
+    #[test]
+    fn affinity_rejects_unknown_and_empty() {
+        for text in ["", "last-0", "last-x", "least-loaded-0", "ccdx", "fastest", "0-", "99999999999"] {
+            assert!(text.parse::<Affinity>().is_err(), "{:?} 不应被接受", text);
+        }
+    }
+
+    #[test]
+    fn affinity_selects_from_topology() {
+        let topology = hybrid();
+        let ctx = PlacementContext::new(&topology);
+        let select = |text: &str| text.parse::<Affinity>().unwrap().select(&ctx);
+        assert_eq!(select("all").unwrap(), topology.all());
+        assert_eq!(select("last-2").unwrap(), cpus(&[6, 7]));
+        assert_eq!(select("every-other").unwrap(), cpus(&[0, 2, 4, 6]));
+        assert_eq!(select("e-cores").unwrap(), cpus(&[4, 5, 6, 7]));
+        assert_eq!(select("ccd0").unwrap(), cpus(&[0, 1, 2, 3]));
+        assert_eq!(select("1,5").unwrap(), cpus(&[1, 5]));
+        assert!(select("last-9").is_err());
+        assert!(select("ccd2").is_err());
+        assert!(select("8").is_err());
+    }
+
+    #[test]
+    fn affinity_follows_load_and_game_placement() {
+        let topology = hybrid();
+        let load = vec![90.0, 10.0, 0.0, 0.0, 50.0, 5.0, 50.0, 50.0];
+        let ctx = PlacementContext::new(&topology).with_load(load);
+        assert_eq!(Affinity::LeastLoaded(2).select(&ctx).unwrap(), cpus(&[2, 3]));
+        assert_eq!(Affinity::SmtSibling.select(&ctx).unwrap(), cpus(&[3]));
+        let ctx = ctx.with_game_cpus(cpus(&[0, 2]));
+        assert!(Affinity::SmtSibling.select(&ctx).unwrap_err().contains("游戏占用"));
+
+        let flat = Topology::flat(4);
+        let ctx = PlacementContext::new(&flat);
+        assert!(Affinity::Efficiency.select(&ctx).is_err());
+        assert!(Affinity::SmtSibling.select(&ctx).unwrap_err().contains("没有开启 SMT"));
+        assert!(Affinity::Ccd(0).select(&ctx).is_err());
+    }
+
+    #[test]
+    fn filtered_keeps_only_selected_kinds() {
+        let actions = ActionSet {
+            affinity: Some(Affinity::Last(1)),
+            priority: Some(CpuPriority::Idle),
+            io: Some(IoPriority::VeryLow),
+            eco: Some(true),
+            memory: Some(MemoryPriority::Low),
+            trim: true,
+            threads: Some(ThreadRule { above_percent: 50.0, affinity: None }),
+        };
+        let filtered = actions.filtered(&[ActionKind::Affinity, ActionKind::Io]);
+        assert_eq!(filtered.affinity, Some(Affinity::Last(1)));
+        assert_eq!(filtered.io, Some(IoPriority::VeryLow));
+        assert_eq!(filtered.priority, None);
+        assert_eq!(filtered.eco, None);
+        assert_eq!(filtered.memory, None);
+        assert!(!filtered.trim);
+        assert_eq!(filtered.threads, None);
+        assert!(actions.filtered(&[]).is_empty());
+    }
+
+    #[test]
+    fn action_set_reads_from_toml() {
+        let actions: ActionSet = toml::from_str(
+            r#"
+            affinity = "last-2"
+            priority = "idle"
+            io = "very-low"
+            eco = false
+            trim = true
+            "#,
+        )
+        .unwrap();
+        assert_eq!(actions.affinity, Some(Affinity::Last(2)));
+        assert_eq!(actions.io, Some(IoPriority::VeryLow));
+        assert_eq!(actions.eco, Some(false));
+        assert!(actions.trim && actions.memory.is_none());
+        assert!(toml::from_str::<ActionSet>(r#"io = "lowest""#).is_err());
+    }
+
+    #[test]
+    fn thread_threshold_must_be_a_percentage() {
+        let parse = |value: &str| toml::from_str::<ThreadRule>(&format!("above_percent = {}", value));
+        assert_eq!(parse("35.5").unwrap().above_percent, 35.5);
+        assert!(parse("nan").is_err());
+        assert!(parse("-1.0").is_err());
+        assert!(parse("100.5").is_err());
+
+        let topology = Topology::flat(2);
+        let actions = ActionSet {
+            threads: Some(ThreadRule { above_percent: f32::NAN, affinity: None }),
+            ..Default::default()
+        };
+        assert!(actions.validate(&PlacementContext::new(&topology)).is_err());
+    }
+
+    #[test]
+    fn apply_calls_controller_in_order() {
+        let ctl = MockController::new();
+        let topology = Topology::flat(4);
+        let actions = ActionSet {
+            affinity: Some(Affinity::Last(1)),
+            priority: Some(CpuPriority::Idle),
+            io: Some(IoPriority::Low),
+            eco: Some(true),
+            memory: Some(MemoryPriority::VeryLow),
+            ..Default::default()
+        };
+        let report = actions.apply(&ctl, 42, &PlacementContext::new(&topology));
+        assert!(report.errors.is_empty(), "{:?}", report.errors);
+        assert_eq!(report.cpus, Some(cpus(&[3])));
+        assert_eq!(
+            ctl.calls(),
+            vec![
+                Call::CpuAffinity { pid: 42, cpus: cpus(&[3]) },
+                Call::IdlePriority { pid: 42 },
+                Call::EfficiencyMode { pid: 42 },
+                Call::IoPriority { pid: 42, priority: 1 },
+                Call::MemoryPriority { pid: 42, priority: 1 },
+            ]
+        );
+    }
+
+    #[test]
+    fn apply_collects_errors() {
+        let ctl = MockController::new();
+        ctl.fail_pid(7);
+        let topology = Topology::flat(2);
+        let actions = ActionSet { priority: Some(CpuPriority::Idle), eco: Some(false), ..Default::default() };
+        let report = actions.apply(&ctl, 7, &PlacementContext::new(&topology));
+        assert_eq!(report.errors.len(), 2);
+        assert!(ctl.calls().is_empty());
+    }
+}
@@ -0,0 +1,601 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use serde::Serialize;
+
+use crate::hidden_command;
+use crate::transaction::Transaction;
+
+type Result<T> = std::result::Result<T, String>;
+
+const APP_NAME: &str = "PitayaBox";
+
+/// 一种开机自启的实现方式
+pub trait AutostartBackend: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// 当前环境能否使用该方式（例如没有 systemd 的发行版）
+    fn is_available(&self) -> bool {
+        true
+    }
+
+    fn enable(&self, exe: &Path) -> Result<()>;
+
+    /// 删除自启项，返回之前是否存在
+    fn disable(&self) -> Result<bool>;
+
+    /// 已登记的启动命令（可执行文件路径），未登记时为 `None`
+    fn query(&self) -> Result<Option<PathBuf>>;
+}
+
+/// 某个后端中找到的自启项
+#[derive(Debug, Clone, Serialize)]
+pub struct AutostartEntry {
+    pub backend: &'static str,
+    pub command: String,
+    /// 是否指向当前正在运行的可执行文件；为 false 说明是旧版本或旧安装位置留下的
+    pub current: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AutostartStatus {
+    /// 首选后端已登记且指向当前程序
+    pub enabled: bool,
+    pub backend: &'static str,
+    pub entries: Vec<AutostartEntry>,
+}
+
+// --- 命令辅助 ---
+
+fn run(cmd: &mut Command, what: &str) -> Result<()> {
+    let output = cmd.output().map_err(|e| format!("{}失败: {}", what, e))?;
+    if output.status.success() {
+        return Ok(());
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    Err(format!("{}失败: {}", what, stderr.trim()))
+}
+
+fn current_exe() -> Result<PathBuf> {
+    std::env::current_exe().map_err(|e| format!("无法获取程序路径: {}", e))
+}
+
+fn same_path(a: &Path, b: &Path) -> bool {
+    if cfg!(windows) {
+        a.as_os_str().eq_ignore_ascii_case(b.as_os_str())
+    } else {
+        a == b
+    }
+}
+
+// --- Windows：计划任务 ---
+
+/// 以最高权限在登录时启动的计划任务，免去每次开机弹出 UAC
+#[cfg(windows)]
+pub struct ScheduledTask;
+
+#[cfg(windows)]
+impl ScheduledTask {
+    const TASK_NAME: &'static str = "PitayaBoxAutoStart";
+}
+
+#[cfg(windows)]
+impl AutostartBackend for ScheduledTask {
+    fn name(&self) -> &'static str {
+        "scheduled-task"
+    }
+
+    fn enable(&self, exe: &Path) -> Result<()> {
+        // 路径可能带空格，/tr 需要自带引号
+        let action = format!("\"{}\"", exe.display());
+        run(
+            hidden_command("schtasks")
+                .args(["/create", "/tn", Self::TASK_NAME, "/sc", "onlogon", "/rl", "highest", "/f", "/tr"])
+                .arg(action),
+            "创建计划任务",
+        )
+    }
+
+    fn disable(&self) -> Result<bool> {
+        if self.query()?.is_none() {
+            return Ok(false);
+        }
+        run(hidden_command("schtasks").args(["/delete", "/tn", Self::TASK_NAME, "/f"]), "删除计划任务")?;
+        Ok(true)
+    }
+
+    fn query(&self) -> Result<Option<PathBuf>> {
+        let output = hidden_command("schtasks")
+            .args(["/query", "/tn", Self::TASK_NAME, "/xml"])
+            .output()
+            .map_err(|e| format!("查询计划任务失败: {}", e))?;
+        // 任务不存在时 schtasks 返回非零
+        if !output.status.success() {
+            return Ok(None);
+        }
+        let xml = decode_task_xml(&output.stdout);
+        let command = xml
+            .split_once("<Command>")
+            .and_then(|(_, rest)| rest.split_once("</Command>"))
+            .map(|(cmd, _)| unescape_xml(cmd.trim()).trim_matches('"').to_string())
+            .unwrap_or_default();
+        Ok(Some(PathBuf::from(command)))
+    }
+}
+
+/// `schtasks /xml` 按任务文件原样输出，通常是带 BOM 的 UTF-16LE；没有 BOM 时按 UTF-8 处理
+#[cfg(any(windows, test))]
+fn decode_task_xml(bytes: &[u8]) -> String {
+    fn utf16(bytes: &[u8], from: fn([u8; 2]) -> u16) -> String {
+        let units: Vec<u16> = bytes.chunks_exact(2).map(|c| from([c[0], c[1]])).collect();
+        String::from_utf16_lossy(&units)
+    }
+    match bytes {
+        [0xFF, 0xFE, rest @ ..] => utf16(rest, u16::from_le_bytes),
+        [0xFE, 0xFF, rest @ ..] => utf16(rest, u16::from_be_bytes),
+        [0xEF, 0xBB, 0xBF, rest @ ..] => String::from_utf8_lossy(rest).into_owned(),
+        // 无 BOM 的 UTF-16LE：ASCII 字符的高字节全是 0
+        [_, 0, ..] => utf16(bytes, u16::from_le_bytes),
+        _ => String::from_utf8_lossy(bytes).into_owned(),
+    }
+}
+
+/// 还原 XML 文本里的实体，路径中的 `&`、引号等会被写成 `&amp;`、`&quot;`
+#[cfg(any(windows, test))]
+fn unescape_xml(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let Some(end) = rest.find(';') else { break };
+        let entity = &rest[1..end];
+        let decoded = match entity {
+            "amp" => Some('&'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            _ => entity
+                .strip_prefix("#x")
+                .or_else(|| entity.strip_prefix("#X"))
+                .map(|hex| u32::from_str_radix(hex, 16))
+                .or_else(|| entity.strip_prefix('#').map(str::parse))
+                .and_then(|code| code.ok())
+                .and_then(char::from_u32),
+        };
+        match decoded {
+            Some(c) => {
+                out.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+// --- Windows：HKCU Run ---
+
+/// 旧版 `registry::set_autostart` 使用的 Run 键，普通权限启动
+#[cfg(windows)]
+pub struct RunKey;
+
+#[cfg(windows)]
+impl RunKey {
+    const PATH: &'static str = r"Software\Microsoft\Windows\CurrentVersion\Run";
+}
+
+#[cfg(windows)]
+impl AutostartBackend for RunKey {
+    fn name(&self) -> &'static str {
+        "run-key"
+    }
+
+    fn enable(&self, exe: &Path) -> Result<()> {
+        use winreg::enums::HKEY_CURRENT_USER;
+        let hkcu = winreg::RegKey::predef(HKEY_CURRENT_USER);
+        let (key, _) = hkcu.create_subkey(Self::PATH).map_err(|e| format!("打开 Run 键失败: {}", e))?;
+        key.set_value(APP_NAME, &format!("\"{}\"", exe.display()))
+            .map_err(|e| format!("写入 Run 键失败: {}", e))
+    }
+
+    fn disable(&self) -> Result<bool> {
+        use winreg::enums::{HKEY_CURRENT_USER, KEY_ALL_ACCESS};
+        let hkcu = winreg::RegKey::predef(HKEY_CURRENT_USER);
+        let Ok(key) = hkcu.open_subkey_with_flags(Self::PATH, KEY_ALL_ACCESS) else {
+            return Ok(false);
+        };
+        match key.delete_value(APP_NAME) {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(format!("删除 Run 键失败: {}", e)),
+        }
+    }
+
+    fn query(&self) -> Result<Option<PathBuf>> {
+        use winreg::enums::HKEY_CURRENT_USER;
+        let hkcu = winreg::RegKey::predef(HKEY_CURRENT_USER);
+        let Ok(key) = hkcu.open_subkey(Self::PATH) else {
+            return Ok(None);
+        };
+        Ok(key.get_value::<String, _>(APP_NAME).ok().map(|v| PathBuf::from(v.trim().trim_matches('"'))))
+    }
+}
+
+// --- Linux：XDG autostart ---
+
+fn xdg_config_home() -> PathBuf {
+    if let Some(dir) = std::env::var_os("XDG_CONFIG_HOME") {
+        return PathBuf::from(dir);
+    }
+    std::env::var_os("HOME")
+        .map(|home| PathBuf::from(home).join(".config"))
+        .unwrap_or_else(std::env::temp_dir)
+}
+
+/// 按 Desktop Entry 规范给 Exec 的参数加引号
+fn quote_exec(path: &Path) -> String {
+    let raw = path.to_string_lossy();
+    if !raw.contains(|c: char| c.is_whitespace() || "\"'\\`$;&|<>()".contains(c)) {
+        return raw.into_owned();
+    }
+    let mut quoted = String::from("\"");
+    for c in raw.chars() {
+        if matches!(c, '"' | '`' | '$' | '\\') {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
+}
+
+fn unquote_exec(value: &str) -> String {
+    let value = value.trim();
+    let Some(inner) = value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) else {
+        return value.to_string();
+    };
+    let mut out = String::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => out.extend(chars.next()),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// 读取 ini 风格文件中某个键的值
+fn read_key(path: &Path, key: &str) -> Result<Option<String>> {
+    match std::fs::read_to_string(path) {
+        Ok(content) => Ok(content
+            .lines()
+            .find_map(|line| line.strip_prefix(key).and_then(|v| v.strip_prefix('=')))
+            .map(unquote_exec)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(format!("读取 {} 失败: {}", path.display(), e)),
+    }
+}
+
+fn remove_file(path: &Path) -> Result<bool> {
+    match std::fs::remove_file(path) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(format!("删除 {} 失败: {}", path.display(), e)),
+    }
+}
+
+/// `~/.config/autostart/pitayabox.desktop`，由桌面环境在登录时启动
+pub struct XdgAutostart {
+    dir: PathBuf,
+}
+
+impl XdgAutostart {
+    pub const FILE_NAME: &'static str = "pitayabox.desktop";
+
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    fn path(&self) -> PathBuf {
+        self.dir.join(Self::FILE_NAME)
+    }
+}
+
+impl Default for XdgAutostart {
+    fn default() -> Self {
+        Self::new(xdg_config_home().join("autostart"))
+    }
+}
+
+impl AutostartBackend for XdgAutostart {
+    fn name(&self) -> &'static str {
+        "xdg"
+    }
+
+    fn enable(&self, exe: &Path) -> Result<()> {
+        let content = format!(
+            "[Desktop Entry]\nType=Application\nName={}\nExec={}\nTerminal=false\nX-GNOME-Autostart-enabled=true\n",
+            APP_NAME,
+            quote_exec(exe)
+        );
+        let path = self.path();
+        crate::paths::write_atomic(&path, &content).map_err(|e| format!("写入 {} 失败: {}", path.display(), e))
+    }
+
+    fn disable(&self) -> Result<bool> {
+        remove_file(&self.path())
+    }
+
+    fn query(&self) -> Result<Option<PathBuf>> {
+        Ok(read_key(&self.path(), "Exec")?.map(PathBuf::from))
+    }
+}
+
+// --- Linux：systemd --user ---
+
+/// `~/.config/systemd/user/pitayabox.service`，挂在图形会话上
+pub struct SystemdUser {
+    dir: PathBuf,
+}
+
+impl SystemdUser {
+    pub const UNIT: &'static str = "pitayabox.service";
+
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    fn path(&self) -> PathBuf {
+        self.dir.join(Self::UNIT)
+    }
+
+    fn systemctl(&self, args: &[&str], what: &str) -> Result<()> {
+        run(hidden_command("systemctl").arg("--user").args(args), what)
+    }
+}
+
+impl Default for SystemdUser {
+    fn default() -> Self {
+        Self::new(xdg_config_home().join("systemd").join("user"))
+    }
+}
+
+impl AutostartBackend for SystemdUser {
+    fn name(&self) -> &'static str {
+        "systemd-user"
+    }
+
+    fn is_available(&self) -> bool {
+        hidden_command("systemctl")
+            .args(["--user", "show-environment"])
+            .output()
+            .is_ok_and(|o| o.status.success())
+    }
+
+    fn enable(&self, exe: &Path) -> Result<()> {
+        let content = format!(
+            "[Unit]\nDescription={}\nPartOf=graphical-session.target\nAfter=graphical-session.target\n\n\
+             [Service]\nExecStart={}\nRestart=no\n\n\
+             [Install]\nWantedBy=graphical-session.target\n",
+            APP_NAME,
+            quote_exec(exe)
+        );
+        let path = self.path();
+        crate::paths::write_atomic(&path, &content).map_err(|e| format!("写入 {} 失败: {}", path.display(), e))?;
+        self.systemctl(&["daemon-reload"], "重新加载 systemd")?;
+        self.systemctl(&["enable", Self::UNIT], "启用 systemd 单元")
+    }
+
+    fn disable(&self) -> Result<bool> {
+        if !self.path().exists() {
+            return Ok(false);
+        }
+        // 单元文件删除前先解除链接，否则会留下悬空的 wants 链接
+        let _ = self.systemctl(&["disable", Self::UNIT], "停用 systemd 单元");
+        let removed = remove_file(&self.path())?;
+        let _ = self.systemctl(&["daemon-reload"], "重新加载 systemd");
+        Ok(removed)
+    }
+
+    fn query(&self) -> Result<Option<PathBuf>> {
+        Ok(read_key(&self.path(), "ExecStart")?.map(PathBuf::from))
+    }
+}
+
+// --- 统一入口 ---
+
+/// 管理所有自启后端：只在首选后端登记，其余后端中的条目视为旧版本遗留
+pub struct Autostart {
+    backends: Vec<Box<dyn AutostartBackend>>,
+}
+
+impl Autostart {
+    /// 第一个后端为首选
+    pub fn new(backends: Vec<Box<dyn AutostartBackend>>) -> Self {
+        Self { backends }
+    }
+
+    fn backend(&self, name: Option<&str>) -> Result<&dyn AutostartBackend> {
+        let Some(name) = name else {
+            let found = self.backends.iter().find(|b| b.is_available());
+            return found.map(|b| b.as_ref()).ok_or_else(|| "当前系统没有可用的自启方式".to_string());
+        };
+        let backend = self.find(name).ok_or_else(|| format!("不支持的自启方式: {}", name))?;
+        // 提前拒绝，免得到 enable 时才在半途失败
+        if !backend.is_available() {
+            return Err(format!("自启方式 {} 在当前系统上不可用", name));
+        }
+        Ok(backend)
+    }
+
+    /// 按名称查找后端，不检查是否可用
+    pub fn find(&self, name: &str) -> Option<&dyn AutostartBackend> {
+        self.backends.iter().find(|b| b.name() == name).map(|b| b.as_ref())
+    }
+
+    /// 当前登记在册的所有自启项
+    pub fn entries(&self) -> Vec<AutostartEntry> {
+        let exe = current_exe().ok();
+        self.backends
+            .iter()
+            .filter_map(|b| {
+                let command = b.query().ok()??;
+                let current = exe.as_deref().is_some_and(|exe| same_path(exe, &command));
+                Some(AutostartEntry { backend: b.name(), command: command.display().to_string(), current })
+            })
+            .collect()
+    }
+
+    pub fn status(&self) -> Result<AutostartStatus> {
+        let backend = self.backend(None)?.name();
+        let entries = self.entries();
+        let enabled = entries.iter().any(|e| e.backend == backend && e.current);
+        Ok(AutostartStatus { enabled, backend, entries })
+    }
+
+    /// 在指定（或首选）后端登记当前程序，并清理其它后端中的旧条目；任何一步失败都由事务整体回滚
+    pub fn enable(&self, name: Option<&str>, tx: &mut Transaction) -> Result<AutostartStatus> {
+        let exe = current_exe()?;
+        let backend = self.backend(name)?;
+        tx.set_autostart(backend, Some(&exe))?;
+        for other in self.backends.iter().filter(|b| b.name() != backend.name()) {
+            tx.set_autostart(other.as_ref(), None)?;
+        }
+        let entries = self.entries();
+        let enabled = entries.iter().any(|e| e.backend == backend.name() && e.current);
+        if !enabled {
+            return Err(format!("{} 自启项写入后未能读回", backend.name()));
+        }
+        Ok(AutostartStatus { enabled, backend: backend.name(), entries })
+    }
+
+    /// 删除所有后端中的自启项，返回实际删除的后端名
+    pub fn disable(&self, tx: &mut Transaction) -> Result<Vec<&'static str>> {
+        let mut removed = Vec::new();
+        for backend in &self.backends {
+            if tx.set_autostart(backend.as_ref(), None)? {
+                removed.push(backend.name());
+            }
+        }
+        Ok(removed)
+    }
+}
+
+impl Default for Autostart {
+    #[cfg(windows)]
+    fn default() -> Self {
+        Self::new(vec![Box::new(ScheduledTask), Box::new(RunKey)])
+    }
+
+    #[cfg(not(windows))]
+    fn default() -> Self {
+        Self::new(vec![Box::new(XdgAutostart::default()), Box::new(SystemdUser::default())])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utf16le(text: &str, bom: bool) -> Vec<u8> {
+        let mut bytes = if bom { vec![0xFF, 0xFE] } else { Vec::new() };
+        bytes.extend(text.encode_utf16().flat_map(u16::to_le_bytes));
+        bytes
+    }
+
+    const TASK_XML: &str = "<?xml version=\"1.0\" encoding=\"UTF-16\"?>\r\n<Task><Actions><Exec>\
+        <Command>\"C:\\Program Files\\R&amp;D 游戏\\PitayaBox.exe\"</Command></Exec></Actions></Task>";
+
+    #[test]
+    fn decodes_task_xml_by_bom() {
+        assert_eq!(decode_task_xml(&utf16le(TASK_XML, true)), TASK_XML);
+        assert_eq!(decode_task_xml(&utf16le(TASK_XML, false)), TASK_XML);
+
+        let mut be = vec![0xFE, 0xFF];
+        be.extend(TASK_XML.encode_utf16().flat_map(u16::to_be_bytes));
+        assert_eq!(decode_task_xml(&be), TASK_XML);
+
+        let mut utf8 = vec![0xEF, 0xBB, 0xBF];
+        utf8.extend(TASK_XML.as_bytes());
+        assert_eq!(decode_task_xml(&utf8), TASK_XML);
+        assert_eq!(decode_task_xml(TASK_XML.as_bytes()), TASK_XML);
+        assert_eq!(decode_task_xml(&[]), "");
+    }
+
+    #[test]
+    fn unescapes_xml_entities() {
+        assert_eq!(unescape_xml("R&amp;D &quot;x&quot; &apos;y&apos; &lt;z&gt;"), "R&D \"x\" 'y' <z>");
+        assert_eq!(unescape_xml("&#65;&#x42;&#X43;&#x6E38;"), "ABC游");
+        // 不认识的实体和落单的 & 原样保留
+        assert_eq!(unescape_xml("a & b &bogus; &#xZZ; &amp"), "a & b &bogus; &#xZZ; &amp");
+    }
+
+    struct Fake {
+        name: &'static str,
+        available: bool,
+    }
+
+    impl AutostartBackend for Fake {
+        fn name(&self) -> &'static str {
+            self.name
+        }
+
+        fn is_available(&self) -> bool {
+            self.available
+        }
+
+        fn enable(&self, _exe: &Path) -> Result<()> {
+            Ok(())
+        }
+
+        fn disable(&self) -> Result<bool> {
+            Ok(false)
+        }
+
+        fn query(&self) -> Result<Option<PathBuf>> {
+            Ok(None)
+        }
+    }
+
+    #[test]
+    fn backend_must_be_available() {
+        let autostart = Autostart::new(vec![
+            Box::new(Fake { name: "missing", available: false }),
+            Box::new(Fake { name: "present", available: true }),
+        ]);
+        assert_eq!(autostart.backend(None).unwrap().name(), "present");
+        assert_eq!(autostart.backend(Some("present")).unwrap().name(), "present");
+        let error = |name| autostart.backend(Some(name)).err().unwrap_or_default();
+        assert!(error("missing").contains("不可用"));
+        assert!(error("other").contains("不支持"));
+        assert!(autostart.find("missing").is_some());
+        assert!(Autostart::new(Vec::new()).backend(None).is_err());
+    }
+
+    #[test]
+    fn exec_quoting_round_trips() {
+        for path in ["/usr/bin/pitayabox", "/opt/Pitaya Box/pitayabox", "/tmp/a\"b$c`d\\e"] {
+            assert_eq!(unquote_exec(&quote_exec(Path::new(path))), path);
+        }
+        assert_eq!(quote_exec(Path::new("/usr/bin/pitayabox")), "/usr/bin/pitayabox");
+    }
+
+    #[test]
+    fn xdg_entry_round_trips() {
+        let dir = crate::paths::ScratchDir::new("xdg");
+        let backend = XdgAutostart::new(dir.join("autostart"));
+        assert_eq!(backend.query().unwrap(), None);
+        let exe = Path::new("/opt/Pitaya Box/pitayabox");
+        backend.enable(exe).unwrap();
+        assert_eq!(backend.query().unwrap().as_deref(), Some(exe));
+        assert!(backend.disable().unwrap());
+        assert!(!backend.disable().unwrap());
+    }
+}
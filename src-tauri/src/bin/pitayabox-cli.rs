@@ -0,0 +1,5 @@
+// src-tauri/src/bin/pitayabox-cli.rs
+// 无界面的命令行入口，子命令都在 lib.rs 的 cli 模块中实现
+fn main() {
+    std::process::exit(pitayabox::cli::main())
+}
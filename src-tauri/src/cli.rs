@@ -0,0 +1,472 @@
+use std::io::Write;
+
+use serde::Serialize;
+use serde_json::json;
+use sysinfo::{Pid, System, Users};
+
+use crate::actions::ActionKind;
+use crate::autostart::{Autostart, AutostartStatus};
+use crate::cpuset::CpuSet;
+use crate::ipc;
+use crate::journal::{self, JournalEntry};
+use crate::plan;
+use crate::policy::{self, PerfOptions};
+use crate::process;
+use crate::profiles::{self, ProfileStatus};
+use crate::targets;
+use crate::transaction::{self, Record};
+use crate::tree;
+use crate::watch::{self, WatchEvent, WatchSettings};
+
+type Result<T> = std::result::Result<T, String>;
+
+const USAGE: &str = "用法: pitayabox-cli [--json] <命令> [选项]
+
+命令:
+  list                          列出命中目标规则的进程
+  apply [选项]                  按目标规则限制进程，先列出计划再执行
+      --only <类别,...>         只执行这些动作类别: affinity, priority, eco, io, memory
+      --profile <id>            改为写入游戏方案的持久化设置，可重复
+      --dry-run                 只列出计划，不执行
+  reset [--all | --profile <id>]
+                                撤销目标规则的限制；--all 按状态日志恢复所有进程，
+                                --profile 删除游戏方案写入的设置
+  status                        被修改的进程与持久化设置
+  watch [--only <类别,...>] [--interval <秒>]
+                                在前台检测游戏并维持限制，同时开放控制接口；
+                                收到 Ctrl+C 或 SIGTERM 时恢复所有进程后退出
+
+选项:
+  --json                        以 JSON 输出；watch 每个事件一行
+  -h, --help                    显示帮助
+  -V, --version                 显示版本";
+
+// --- 参数解析 ---
+
+#[derive(Debug)]
+enum Command {
+    List,
+    Apply { kinds: Vec<ActionKind>, profiles: Vec<String>, dry_run: bool },
+    Reset { all: bool, profiles: Vec<String> },
+    Status,
+    Watch { kinds: Vec<ActionKind>, interval_secs: Option<u64> },
+    Help,
+    Version,
+}
+
+struct Args {
+    json: bool,
+    command: Command,
+}
+
+fn parse(args: impl IntoIterator<Item = String>) -> Result<Args> {
+    let mut json = false;
+    let mut rest = Vec::new();
+    for arg in args {
+        match arg.as_str() {
+            "--json" => json = true,
+            "-h" | "--help" => return Ok(Args { json, command: Command::Help }),
+            "-V" | "--version" => return Ok(Args { json, command: Command::Version }),
+            _ => rest.push(arg),
+        }
+    }
+    let mut rest = rest.into_iter();
+    let Some(name) = rest.next() else {
+        return Ok(Args { json, command: Command::Help });
+    };
+
+    let mut kinds = ActionKind::ALL.to_vec();
+    let mut profiles = Vec::new();
+    let mut dry_run = false;
+    let mut all = false;
+    let mut interval_secs = None;
+    while let Some(option) = rest.next() {
+        let mut value = || rest.next().ok_or_else(|| format!("{} 缺少参数", option));
+        match (name.as_str(), option.as_str()) {
+            ("apply" | "watch", "--only") => {
+                kinds = value()?.split(',').filter(|s| !s.trim().is_empty()).map(str::parse).collect::<Result<_>>()?;
+            }
+            ("apply" | "reset", "--profile") => profiles.push(value()?),
+            ("apply", "--dry-run") => dry_run = true,
+            ("reset", "--all") => all = true,
+            ("watch", "--interval") => {
+                let secs = value()?;
+                interval_secs = Some(secs.parse().map_err(|_| format!("无效的间隔: {}", secs))?);
+            }
+            _ => return Err(format!("{} 不支持选项 {}", name, option)),
+        }
+    }
+    let command = match name.as_str() {
+        "list" => Command::List,
+        "apply" => Command::Apply { kinds, profiles, dry_run },
+        "reset" if all && !profiles.is_empty() => return Err("--all 与 --profile 不能同时使用".to_string()),
+        "reset" => Command::Reset { all, profiles },
+        "status" => Command::Status,
+        "watch" => Command::Watch { kinds, interval_secs },
+        other => return Err(format!("未知命令: {}", other)),
+    };
+    Ok(Args { json, command })
+}
+
+// --- 输出 ---
+
+/// 命令的结果：`--json` 时输出 `value`，否则输出 `text`
+struct Output {
+    value: serde_json::Value,
+    text: String,
+}
+
+impl Output {
+    fn new(value: &impl Serialize, text: impl Into<String>) -> Self {
+        Self { value: serde_json::to_value(value).unwrap_or_default(), text: text.into() }
+    }
+
+    fn message(message: String) -> Self {
+        Self { value: json!({ "message": message }), text: message }
+    }
+}
+
+/// 命令行入口，返回退出码：0 成功，1 执行失败，2 参数错误
+pub fn main() -> i32 {
+    let args = match parse(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{}\n运行 pitayabox-cli --help 查看用法", e);
+            return 2;
+        }
+    };
+    let json = args.json;
+    let result = match args.command {
+        Command::Help => Ok(Output::message(USAGE.to_string())),
+        Command::Version => Ok(Output::new(&json!({ "version": env!("CARGO_PKG_VERSION") }), env!("CARGO_PKG_VERSION"))),
+        Command::List => list(),
+        Command::Apply { kinds, profiles, dry_run } => apply(&kinds, &profiles, dry_run),
+        Command::Reset { all, profiles } => reset(all, &profiles),
+        Command::Status => status(),
+        Command::Watch { kinds, interval_secs } => watch(json, kinds, interval_secs),
+    };
+    match result {
+        Ok(output) if json => print(&serde_json::to_string_pretty(&output.value).unwrap_or_default()),
+        Ok(output) => print(&output.text),
+        Err(e) if json => {
+            print(&json!({ "error": e }).to_string());
+            return 1;
+        }
+        Err(e) => {
+            eprintln!("错误: {}", e);
+            return 1;
+        }
+    }
+    0
+}
+
+/// 输出被管道提前关闭（如接了 `head`）时不当作错误
+fn print(line: &str) {
+    let _ = writeln!(std::io::stdout(), "{}", line);
+}
+
+// --- 子命令 ---
+
+#[derive(Serialize)]
+struct Listed {
+    pid: u32,
+    /// 进程名
+    name: String,
+    /// 命中的规则名
+    rule: String,
+    inherited: bool,
+    cpu_usage: f32,
+    memory_mb: f64,
+}
+
+fn list() -> Result<Output> {
+    let mut sys = System::new_all();
+    std::thread::sleep(sysinfo::MINIMUM_CPU_UPDATE_INTERVAL);
+    sys.refresh_processes();
+    let users = Users::new_with_refreshed_list();
+    let config = targets::store().config();
+    let listed: Vec<Listed> = config
+        .resolve(&sys, &users, tree::lineage())
+        .into_iter()
+        .filter_map(|assignment| {
+            let process = sys.process(Pid::from_u32(assignment.pid))?;
+            Some(Listed {
+                pid: assignment.pid,
+                name: process.name().to_string(),
+                rule: config.targets[assignment.target].name.clone(),
+                inherited: assignment.inherited,
+                cpu_usage: process.cpu_usage(),
+                memory_mb: process.memory() as f64 / 1024.0 / 1024.0,
+            })
+        })
+        .collect();
+    let text = if listed.is_empty() {
+        "未发现命中规则的进程".to_string()
+    } else {
+        listed
+            .iter()
+            .map(|p| {
+                let inherited = if p.inherited { "（继承）" } else { "" };
+                format!("{:>7}  {:<20} {}{}  CPU {:.1}%  内存 {:.1} MB", p.pid, p.name, p.rule, inherited, p.cpu_usage, p.memory_mb)
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+    Ok(Output::new(&listed, text))
+}
+
+/// 没有 `--profile` 时限制进程，否则只写入方案的持久化设置；`--dry-run` 只输出计划
+fn apply(kinds: &[ActionKind], profiles: &[String], dry_run: bool) -> Result<Output> {
+    let plan = super::build_plan(kinds, profiles.is_empty(), profiles)?;
+    if dry_run {
+        let text = plan.summary.join("\n");
+        return Ok(Output::new(&plan, text));
+    }
+    let plan = plan::planner().insert(plan);
+    let status = super::apply_plan(plan.id)?;
+    let text = status.message.clone();
+    Ok(Output::new(&json!({ "plan": plan, "status": status }), text))
+}
+
+fn reset(all: bool, profiles: &[String]) -> Result<Output> {
+    if all {
+        return super::restore_all().map(Output::message);
+    }
+    if profiles.is_empty() {
+        return super::reset_processes().map(Output::message);
+    }
+    let messages = profiles.iter().map(|id| super::reset_profile(id.clone())).collect::<Result<Vec<_>>>()?;
+    Ok(Output::message(messages.join("\n")))
+}
+
+/// 一个目标进程当前的实际状态
+#[derive(Serialize)]
+struct ProcessState {
+    pid: u32,
+    rule: String,
+    /// 状态日志里有它的原始状态，即被本程序修改过
+    modified: bool,
+    cpus: Option<CpuSet>,
+    idle: Option<bool>,
+    /// 取值同 IO_PRIORITY_HINT
+    io: Option<u32>,
+    eco: Option<bool>,
+}
+
+#[derive(Serialize)]
+struct Status {
+    backend: &'static str,
+    processes: Vec<ProcessState>,
+    /// 状态日志中已退出的进程不会出现在 `processes` 里
+    journal: Vec<JournalEntry>,
+    policy_store: &'static str,
+    policy: Vec<(String, PerfOptions)>,
+    profiles: Vec<ProfileStatus>,
+    autostart: Option<AutostartStatus>,
+    /// 最近的事务，最新的在前
+    transactions: Vec<Record>,
+}
+
+fn status() -> Result<Output> {
+    const RECENT: usize = 10;
+    let ctl = process::controller();
+    let mut sys = System::new_all();
+    sys.refresh_processes();
+    let users = Users::new_with_refreshed_list();
+    let config = targets::store().config();
+    let journal = journal::journal().entries();
+    let processes: Vec<ProcessState> = config
+        .resolve(&sys, &users, tree::lineage())
+        .into_iter()
+        .map(|assignment| {
+            let pid = assignment.pid;
+            let start_time = sys.process(Pid::from_u32(pid)).map(|p| p.start_time());
+            ProcessState {
+                pid,
+                rule: config.targets[assignment.target].name.clone(),
+                modified: journal.iter().any(|e| e.pid == pid && Some(e.start_time) == start_time),
+                cpus: ctl.cpu_affinity(pid).ok(),
+                idle: ctl.is_idle_priority(pid).ok(),
+                io: ctl.io_priority(pid).ok(),
+                eco: ctl.efficiency_mode(pid).ok(),
+            }
+        })
+        .collect();
+    let store = policy::store();
+    let catalog = profiles::catalog();
+    let status = Status {
+        backend: ctl.name(),
+        processes,
+        journal,
+        policy_store: store.name(),
+        policy: store.list()?,
+        profiles: catalog.profiles.iter().map(|p| ProfileStatus { profile: p.clone(), applied: p.is_applied(store) }).collect(),
+        autostart: Autostart::default().status().ok(),
+        transactions: transaction::history().records().into_iter().take(RECENT).collect(),
+    };
+    let text = status_text(&status);
+    Ok(Output::new(&status, text))
+}
+
+fn status_text(status: &Status) -> String {
+    fn or_dash<T: ToString>(value: &Option<T>) -> String {
+        value.as_ref().map_or_else(|| "-".to_string(), T::to_string)
+    }
+    let mut lines = vec![format!("后端: {}", status.backend), "目标进程:".to_string()];
+    if status.processes.is_empty() {
+        lines.push("  （无）".to_string());
+    }
+    for p in &status.processes {
+        lines.push(format!(
+            "  {:>7}  {:<20} 核心 {}  空闲 {}  I/O {}  效率模式 {}{}",
+            p.pid,
+            p.rule,
+            or_dash(&p.cpus),
+            or_dash(&p.idle),
+            or_dash(&p.io),
+            or_dash(&p.eco),
+            if p.modified { "  [已修改]" } else { "" }
+        ));
+    }
+    lines.push(format!("状态日志: {} 个进程", status.journal.len()));
+    lines.push(format!("持久化优先级 ({}):", status.policy_store));
+    if status.policy.is_empty() {
+        lines.push("  （无）".to_string());
+    }
+    for (exe_name, options) in &status.policy {
+        lines.push(format!(
+            "  {:<24} CPU {}  I/O {}  内存 {}",
+            exe_name,
+            or_dash(&options.cpu_priority),
+            or_dash(&options.io_priority),
+            or_dash(&options.page_priority)
+        ));
+    }
+    lines.push("游戏方案:".to_string());
+    for p in &status.profiles {
+        lines.push(format!("  {:<16} {}{}", p.profile.id, p.profile.name, if p.applied { "  [已生效]" } else { "" }));
+    }
+    match &status.autostart {
+        Some(autostart) => lines.push(format!("自启: {} ({})", if autostart.enabled { "已开启" } else { "未开启" }, autostart.backend)),
+        None => lines.push("自启: 未知".to_string()),
+    }
+    lines.push("最近的事务:".to_string());
+    if status.transactions.is_empty() {
+        lines.push("  （无）".to_string());
+    }
+    for record in &status.transactions {
+        lines.push(format!("  #{:<4} {:?}  {}", record.id, record.state, record.description));
+    }
+    lines.join("\n")
+}
+
+/// 前台运行自动检测，不会正常返回；收到退出信号时恢复所有进程再退出
+fn watch(json: bool, kinds: Vec<ActionKind>, interval_secs: Option<u64>) -> Result<Output> {
+    let defaults = WatchSettings::default();
+    watch::watcher().configure(WatchSettings {
+        enabled: true,
+        kinds,
+        interval_secs: interval_secs.unwrap_or(defaults.interval_secs),
+    });
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .map_err(|e| format!("创建信号监听失败: {}", e))?;
+    std::thread::spawn(move || {
+        runtime.block_on(shutdown_signal());
+        let report = super::restore_all_processes(process::controller());
+        eprintln!("{}", report.message());
+        std::process::exit(if report.errors.is_empty() { 0 } else { 1 });
+    });
+    ipc::spawn();
+    eprintln!("正在检测游戏（{}），控制接口 {}，按 Ctrl+C 退出", process::controller().name(), ipc::address());
+    super::watch_loop(&|event| {
+        if json {
+            print(&serde_json::to_string(&event).unwrap_or_default());
+            return;
+        }
+        match event {
+            WatchEvent::GameStarted { name, pids, .. } => print(&format!("检测到 {} 启动 (PID: {:?})", name, pids)),
+            WatchEvent::GameExited { name, .. } => print(&format!("{} 已退出", name)),
+            WatchEvent::ProfileApplied { message, .. }
+            | WatchEvent::CompanionsRestricted { message, .. }
+            | WatchEvent::CompanionsReleased { message, .. }
+            | WatchEvent::DriftCorrected { message, .. } => print(&message),
+        }
+    })
+}
+
+/// Ctrl+C；Unix 上还包括服务管理器发来的 SIGTERM
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {}
+                    _ = terminate.recv() => {}
+                }
+            }
+            Err(_) => { let _ = tokio::signal::ctrl_c().await; }
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = tokio::signal::ctrl_c().await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Result<Args> {
+        parse(line.split_whitespace().map(str::to_string))
+    }
+
+    #[test]
+    fn json_is_accepted_anywhere() {
+        for line in ["--json status", "status --json"] {
+            let parsed = args(line).unwrap();
+            assert!(parsed.json && matches!(parsed.command, Command::Status));
+        }
+        let parsed = args("apply --only io --json --dry-run").unwrap();
+        assert!(parsed.json);
+        assert!(matches!(parsed.command, Command::Apply { dry_run: true, .. }));
+        assert!(!args("list").unwrap().json);
+        assert!(matches!(args("").unwrap().command, Command::Help));
+        assert!(matches!(args("apply --version").unwrap().command, Command::Version));
+    }
+
+    #[test]
+    fn only_selects_action_kinds() {
+        let Command::Apply { kinds, profiles, dry_run } = args("apply --only io,affinity, --profile a --profile b").unwrap().command else {
+            panic!("应解析为 apply");
+        };
+        assert_eq!(kinds, vec![ActionKind::Io, ActionKind::Affinity]);
+        assert_eq!(profiles, vec!["a".to_string(), "b".to_string()]);
+        assert!(!dry_run);
+
+        let Command::Watch { kinds, interval_secs } = args("watch --interval 5").unwrap().command else {
+            panic!("应解析为 watch");
+        };
+        assert_eq!(kinds, ActionKind::ALL.to_vec());
+        assert_eq!(interval_secs, Some(5));
+        assert!(args("apply --only turbo").is_err());
+    }
+
+    #[test]
+    fn reset_rejects_all_with_profile() {
+        assert!(matches!(args("reset --all").unwrap().command, Command::Reset { all: true, .. }));
+        assert_eq!(args("reset --all --profile a").err().unwrap(), "--all 与 --profile 不能同时使用");
+    }
+
+    #[test]
+    fn rejects_unknown_input_and_missing_values() {
+        assert_eq!(args("launch").err().unwrap(), "未知命令: launch");
+        assert_eq!(args("list --all").err().unwrap(), "list 不支持选项 --all");
+        assert_eq!(args("apply --interval 5").err().unwrap(), "apply 不支持选项 --interval");
+        assert_eq!(args("apply --only").err().unwrap(), "--only 缺少参数");
+        assert_eq!(args("reset --profile").err().unwrap(), "--profile 缺少参数");
+        assert_eq!(args("watch --interval soon").err().unwrap(), "无效的间隔: soon");
+    }
+}
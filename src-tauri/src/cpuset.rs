@@ -0,0 +1,199 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::topology::parse_cpu_list;
+
+type Result<T> = std::result::Result<T, String>;
+
+/// 逻辑处理器编号的上限，与内核 `NR_CPUS` 的最大配置相同；挡住 `0-4000000000` 这类输入
+pub const MAX_CPUS: usize = 8192;
+
+/// 任意宽度的逻辑处理器集合，按 64 位一个字存放
+///
+/// 第 `n` 个字对应 Windows 的第 `n` 个处理器组，也与 Linux 内核的 CPU 位图布局一致。
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct CpuSet {
+    words: Vec<u64>,
+}
+
+impl CpuSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `0..count` 全部置位
+    pub fn full(count: usize) -> Self {
+        (0..count).collect()
+    }
+
+    pub fn from_words(words: Vec<u64>) -> Self {
+        let mut set = Self { words };
+        set.trim();
+        set
+    }
+
+    pub fn words(&self) -> &[u64] {
+        &self.words
+    }
+
+    pub fn insert(&mut self, cpu: usize) -> Result<()> {
+        if cpu >= MAX_CPUS {
+            return Err(format!("逻辑核心编号 {} 超出上限 {}", cpu, MAX_CPUS - 1));
+        }
+        self.set(cpu);
+        Ok(())
+    }
+
+    fn set(&mut self, cpu: usize) {
+        let (word, bit) = (cpu / 64, cpu % 64);
+        if self.words.len() <= word {
+            self.words.resize(word + 1, 0);
+        }
+        self.words[word] |= 1u64 << bit;
+    }
+
+    pub fn remove(&mut self, cpu: usize) {
+        if let Some(word) = self.words.get_mut(cpu / 64) {
+            *word &= !(1u64 << (cpu % 64));
+        }
+        self.trim();
+    }
+
+    pub fn contains(&self, cpu: usize) -> bool {
+        self.words.get(cpu / 64).is_some_and(|word| word & (1u64 << (cpu % 64)) != 0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.words.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    /// 升序遍历
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.words
+            .iter()
+            .enumerate()
+            .flat_map(|(index, &word)| (0..64).filter(move |bit| word & (1u64 << bit) != 0).map(move |bit| index * 64 + bit))
+    }
+
+    pub fn first(&self) -> Option<usize> {
+        self.iter().next()
+    }
+
+    pub fn last(&self) -> Option<usize> {
+        let (index, word) = self.words.iter().enumerate().rev().find(|(_, &w)| w != 0)?;
+        Some(index * 64 + 63 - word.leading_zeros() as usize)
+    }
+
+    pub fn union(&self, other: &CpuSet) -> CpuSet {
+        let len = self.words.len().max(other.words.len());
+        let word = |set: &CpuSet, i: usize| set.words.get(i).copied().unwrap_or(0);
+        Self::from_words((0..len).map(|i| word(self, i) | word(other, i)).collect())
+    }
+
+    pub fn intersection(&self, other: &CpuSet) -> CpuSet {
+        Self::from_words(self.words.iter().zip(&other.words).map(|(a, b)| a & b).collect())
+    }
+
+    pub fn is_subset(&self, other: &CpuSet) -> bool {
+        self.intersection(other) == *self
+    }
+
+    /// 按 64 个一组拆开，返回 `(组号, 组内掩码)`，跳过空组
+    pub fn groups(&self) -> impl Iterator<Item = (u16, u64)> + '_ {
+        self.words.iter().enumerate().filter(|(_, &w)| w != 0).map(|(group, &w)| (group as u16, w))
+    }
+
+    /// 去掉末尾的空字，保证相等比较与宽度无关
+    fn trim(&mut self) {
+        while self.words.last() == Some(&0) {
+            self.words.pop();
+        }
+    }
+}
+
+/// 编号来自拓扑或内核位图，不会超过 `MAX_CPUS`，超出的直接丢弃；外部输入走 `FromStr`
+impl FromIterator<usize> for CpuSet {
+    fn from_iter<I: IntoIterator<Item = usize>>(iter: I) -> Self {
+        let mut set = CpuSet::new();
+        for cpu in iter.into_iter().filter(|&cpu| cpu < MAX_CPUS) {
+            set.set(cpu);
+        }
+        set
+    }
+}
+
+/// 内核 CPU 列表格式，例如 `0-3,8`
+impl fmt::Display for CpuSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut ranges: Vec<(usize, usize)> = Vec::new();
+        for cpu in self.iter() {
+            match ranges.last_mut() {
+                Some((_, end)) if *end + 1 == cpu => *end = cpu,
+                _ => ranges.push((cpu, cpu)),
+            }
+        }
+        let parts: Vec<String> = ranges
+            .into_iter()
+            .map(|(start, end)| if start == end { start.to_string() } else { format!("{}-{}", start, end) })
+            .collect();
+        write!(f, "{}", parts.join(","))
+    }
+}
+
+impl FromStr for CpuSet {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(parse_cpu_list(s)?.into_iter().collect())
+    }
+}
+
+impl Serialize for CpuSet {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for CpuSet {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        String::deserialize(deserializer)?.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_and_displays_ranges() {
+        let set: CpuSet = "0-3,8,10-11".parse().unwrap();
+        assert_eq!(set.len(), 7);
+        assert_eq!(set.to_string(), "0-3,8,10-11");
+        assert_eq!(set.first(), Some(0));
+        assert_eq!(set.last(), Some(11));
+        assert!(set.contains(8) && !set.contains(9));
+    }
+
+    #[test]
+    fn spans_processor_groups() {
+        let set: CpuSet = "63-64,130".parse().unwrap();
+        assert_eq!(set.groups().collect::<Vec<_>>(), vec![(0, 1 << 63), (1, 1), (2, 1 << 2)]);
+    }
+
+    #[test]
+    fn rejects_ids_beyond_limit() {
+        let mut set = CpuSet::new();
+        assert!(set.insert(MAX_CPUS - 1).is_ok());
+        assert!(set.insert(MAX_CPUS).is_err());
+        assert!(format!("0,{}", usize::MAX).parse::<CpuSet>().is_err());
+        // 从迭代器收集时直接丢弃，不会为巨大的编号分配内存
+        let set: CpuSet = [1, usize::MAX].into_iter().collect();
+        assert_eq!(set.iter().collect::<Vec<_>>(), vec![1]);
+    }
+}
@@ -0,0 +1,274 @@
+use std::collections::BTreeMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+
+use crate::actions::{ActionKind, ActionSet};
+use crate::cpuset::CpuSet;
+use crate::process::ProcessController;
+
+/// 施加动作后读回的进程状态；没有施加或读不出来的项为 `None`
+///
+/// 以读回值而不是规则本身作为期望状态，平台的退回方案（如 I/O 空闲类被拒后改用
+/// best-effort 7）就不会被误判为漂移。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Observed {
+    pub cpus: Option<CpuSet>,
+    pub idle: Option<bool>,
+    pub io: Option<u32>,
+    pub eco: Option<bool>,
+}
+
+impl Observed {
+    /// 只读取 `actions` 涉及的项
+    pub fn read(ctl: &dyn ProcessController, pid: u32, actions: &ActionSet) -> Self {
+        Self {
+            cpus: actions.affinity.as_ref().and_then(|_| ctl.cpu_affinity(pid).ok()),
+            idle: actions.priority.and_then(|_| ctl.is_idle_priority(pid).ok()),
+            io: actions.io.and_then(|_| ctl.io_priority(pid).ok()),
+            eco: actions.eco.and_then(|_| ctl.efficiency_mode(pid).ok()),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        *self == Observed::default()
+    }
+
+    /// 与期望状态不一致的类别；任意一边缺失的项不参与比较
+    pub fn drifted(&self, current: &Observed) -> Vec<ActionKind> {
+        fn differs<T: PartialEq>(expected: &Option<T>, current: &Option<T>) -> bool {
+            matches!((expected, current), (Some(a), Some(b)) if a != b)
+        }
+        let mut kinds = Vec::new();
+        if differs(&self.cpus, &current.cpus) { kinds.push(ActionKind::Affinity); }
+        if differs(&self.idle, &current.idle) { kinds.push(ActionKind::Priority); }
+        if differs(&self.io, &current.io) { kinds.push(ActionKind::Io); }
+        if differs(&self.eco, &current.eco) { kinds.push(ActionKind::Eco); }
+        kinds
+    }
+
+    /// 按期望状态重新施加 `kinds` 里的项
+    fn enforce(&self, ctl: &dyn ProcessController, pid: u32, kinds: &[ActionKind]) -> Vec<String> {
+        let mut errors = Vec::new();
+        for kind in kinds {
+            let result = match (kind, self) {
+                (ActionKind::Affinity, Observed { cpus: Some(cpus), .. }) => ctl.set_cpu_affinity(pid, cpus),
+                (ActionKind::Priority, Observed { idle: Some(true), .. }) => ctl.set_idle_priority(pid),
+                (ActionKind::Priority, Observed { idle: Some(false), .. }) => ctl.restore_priority(pid),
+                (ActionKind::Io, Observed { io: Some(io), .. }) => ctl.set_io_priority(pid, *io),
+                (ActionKind::Eco, Observed { eco: Some(true), .. }) => ctl.set_efficiency_mode(pid),
+                (ActionKind::Eco, Observed { eco: Some(false), .. }) => ctl.clear_efficiency_mode(pid),
+                _ => Ok(()),
+            };
+            if let Err(e) = result { errors.push(e); }
+        }
+        errors
+    }
+}
+
+/// 一次读回发现并纠正的漂移
+#[derive(Debug, Clone, Serialize)]
+pub struct Correction {
+    pub pid: u32,
+    pub name: String,
+    pub kinds: Vec<ActionKind>,
+    /// 该进程累计的漂移次数（含本次）
+    pub drifts: u32,
+    /// 重新施加失败的原因
+    pub errors: Vec<String>,
+}
+
+impl Correction {
+    pub fn message(&self) -> String {
+        let kinds: Vec<&str> = self.kinds.iter().map(|k| k.label()).collect();
+        let mut message =
+            format!("{} (PID: {}) 的{}被改回，已重新施加（第 {} 次）", self.name, self.pid, kinds.join("、"), self.drifts);
+        if !self.errors.is_empty() {
+            message = format!("{}，失败: {}", message, self.errors.join("; "));
+        }
+        message
+    }
+}
+
+/// `get_drift_status` 返回的单个进程记录
+#[derive(Debug, Clone, Serialize)]
+pub struct DriftStatus {
+    pub pid: u32,
+    /// 命中的规则名
+    pub name: String,
+    pub drifts: u32,
+    /// 最近一次漂移涉及的类别
+    pub last_kinds: Vec<ActionKind>,
+    /// 最近一次漂移的时间（Unix 秒）
+    pub last_at: Option<u64>,
+}
+
+struct Enforced {
+    name: String,
+    /// 记录时进程的启动时间，用来识别 PID 被复用
+    start_time: u64,
+    expected: Observed,
+    drifts: u32,
+    last_kinds: Vec<ActionKind>,
+    last_at: Option<u64>,
+}
+
+/// 记住每个被限制进程的期望状态，定期读回比较
+#[derive(Default)]
+pub struct DriftTracker {
+    enforced: Mutex<BTreeMap<u32, Enforced>>,
+}
+
+impl DriftTracker {
+    /// 在施加 `actions` 之后调用；同一进程按同一规则重复施加时保留漂移计数
+    pub fn record(&self, ctl: &dyn ProcessController, pid: u32, start_time: u64, name: &str, actions: &ActionSet) {
+        let expected = Observed::read(ctl, pid, actions);
+        let mut enforced = self.enforced.lock().unwrap();
+        if expected.is_empty() {
+            enforced.remove(&pid);
+            return;
+        }
+        match enforced.get_mut(&pid) {
+            Some(entry) if entry.name == name && entry.start_time == start_time => entry.expected = expected,
+            _ => {
+                let entry = Enforced { name: name.to_string(), start_time, expected, drifts: 0, last_kinds: Vec::new(), last_at: None };
+                enforced.insert(pid, entry);
+            }
+        }
+    }
+
+    pub fn forget(&self, pid: u32) {
+        self.enforced.lock().unwrap().remove(&pid);
+    }
+
+    /// 丢掉已经退出或 PID 被复用的进程；`start_time` 返回进程当前的启动时间，进程不存在时为 `None`
+    pub fn retain(&self, start_time: impl Fn(u32) -> Option<u64>) {
+        self.enforced.lock().unwrap().retain(|&pid, entry| start_time(pid) == Some(entry.start_time));
+    }
+
+    pub fn is_tracked(&self, pid: u32, start_time: u64) -> bool {
+        self.enforced.lock().unwrap().get(&pid).is_some_and(|entry| entry.start_time == start_time)
+    }
+
+    /// 读回所有记录的进程，与期望不一致时重新施加并计数
+    pub fn check(&self, ctl: &dyn ProcessController) -> Vec<Correction> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).ok();
+        let mut corrections = Vec::new();
+        for (&pid, entry) in self.enforced.lock().unwrap().iter_mut() {
+            let expected = &entry.expected;
+            let current = Observed {
+                cpus: expected.cpus.as_ref().and_then(|_| ctl.cpu_affinity(pid).ok()),
+                idle: expected.idle.and_then(|_| ctl.is_idle_priority(pid).ok()),
+                io: expected.io.and_then(|_| ctl.io_priority(pid).ok()),
+                eco: expected.eco.and_then(|_| ctl.efficiency_mode(pid).ok()),
+            };
+            let kinds = expected.drifted(&current);
+            if kinds.is_empty() {
+                continue;
+            }
+            let errors = expected.enforce(ctl, pid, &kinds);
+            entry.drifts += 1;
+            entry.last_kinds = kinds.clone();
+            entry.last_at = now;
+            corrections.push(Correction { pid, name: entry.name.clone(), kinds, drifts: entry.drifts, errors });
+        }
+        corrections
+    }
+
+    pub fn status(&self) -> Vec<DriftStatus> {
+        self.enforced
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(&pid, entry)| DriftStatus {
+                pid,
+                name: entry.name.clone(),
+                drifts: entry.drifts,
+                last_kinds: entry.last_kinds.clone(),
+                last_at: entry.last_at,
+            })
+            .collect()
+    }
+}
+
+/// 进程内共享的漂移记录
+pub fn tracker() -> &'static DriftTracker {
+    static TRACKER: OnceLock<DriftTracker> = OnceLock::new();
+    TRACKER.get_or_init(DriftTracker::default)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process::mock::{Call, MockController};
+
+    fn idle_actions() -> ActionSet {
+        toml::from_str("priority = \"idle\"\nio = \"very-low\"").unwrap()
+    }
+
+    /// 施加 `idle_actions` 并记录期望状态
+    fn enforce(ctl: &MockController, tracker: &DriftTracker, pid: u32, start_time: u64) {
+        ctl.set_idle_priority(pid).unwrap();
+        ctl.set_io_priority(pid, 0).unwrap();
+        tracker.record(ctl, pid, start_time, "game", &idle_actions());
+    }
+
+    #[test]
+    fn drift_is_detected_and_reapplied() {
+        let ctl = MockController::new();
+        let tracker = DriftTracker::default();
+        enforce(&ctl, &tracker, 1, 100);
+        assert!(tracker.check(&ctl).is_empty());
+
+        // 进程自己把优先级改了回去
+        ctl.restore_priority(1).unwrap();
+        ctl.set_io_priority(1, 2).unwrap();
+        let calls = ctl.calls().len();
+        let corrections = tracker.check(&ctl);
+        assert_eq!(corrections.len(), 1);
+        assert_eq!(corrections[0].kinds, vec![ActionKind::Priority, ActionKind::Io]);
+        assert_eq!(corrections[0].drifts, 1);
+        assert!(corrections[0].errors.is_empty());
+        assert_eq!(ctl.calls()[calls..], [Call::IdlePriority { pid: 1 }, Call::IoPriority { pid: 1, priority: 0 }]);
+        assert!(tracker.check(&ctl).is_empty());
+
+        ctl.restore_priority(1).unwrap();
+        assert_eq!(tracker.check(&ctl)[0].drifts, 2);
+        let status = tracker.status();
+        assert_eq!((status[0].pid, status[0].drifts), (1, 2));
+        assert_eq!(status[0].last_kinds, vec![ActionKind::Priority]);
+        assert!(status[0].last_at.is_some());
+    }
+
+    #[test]
+    fn reused_pid_is_not_corrected() {
+        let ctl = MockController::new();
+        let tracker = DriftTracker::default();
+        enforce(&ctl, &tracker, 2, 100);
+        assert!(tracker.is_tracked(2, 100));
+
+        // PID 2 已经是另一个进程，它的正常优先级不算漂移
+        tracker.retain(|pid| (pid == 2).then_some(200));
+        assert!(!tracker.is_tracked(2, 100));
+        ctl.restore_priority(2).unwrap();
+        let calls = ctl.calls().len();
+        assert!(tracker.check(&ctl).is_empty());
+        assert_eq!(ctl.calls().len(), calls);
+    }
+
+    #[test]
+    fn new_process_instance_resets_drift_count() {
+        let ctl = MockController::new();
+        let tracker = DriftTracker::default();
+        enforce(&ctl, &tracker, 3, 100);
+        ctl.restore_priority(3).unwrap();
+        assert_eq!(tracker.check(&ctl)[0].drifts, 1);
+
+        enforce(&ctl, &tracker, 3, 100);
+        assert_eq!(tracker.status()[0].drifts, 1);
+        enforce(&ctl, &tracker, 3, 300);
+        assert_eq!(tracker.status()[0].drifts, 0);
+        assert!(tracker.is_tracked(3, 300));
+    }
+}
@@ -0,0 +1,386 @@
+use std::collections::BTreeSet;
+use std::sync::OnceLock;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::sync::broadcast;
+
+use crate::watch;
+
+#[cfg(unix)]
+mod unix;
+#[cfg(windows)]
+mod pipe;
+
+type Result<T> = std::result::Result<T, String>;
+
+// 本地控制接口：Linux 上为 Unix 域套接字，Windows 上为命名管道，每行一个 JSON-RPC 2.0 消息。
+//
+// - 方法与 Tauri 命令同名，参数按名称传递，参数名与 Rust 函数参数相同（snake_case）
+// - `hello` 协商协议版本并列出所有方法和事件主题
+// - `subscribe` / `unsubscribe` 按连接订阅事件，事件以 `event` 通知推送
+// - 谁能连接由文件系统权限（Windows 上为管道的 DACL）决定，连上之后不再另做认证
+
+/// 协议版本；只增加方法和字段时不变，不兼容的修改才递增
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// 可以订阅的事件主题
+pub const TOPICS: [&str; 1] = [watch::EVENT];
+
+// --- 错误 ---
+
+/// JSON-RPC 的 error 对象
+#[derive(Debug, Clone, Serialize)]
+pub struct RpcError {
+    pub code: i32,
+    pub message: String,
+}
+
+impl RpcError {
+    pub const PARSE_ERROR: i32 = -32700;
+    pub const INVALID_REQUEST: i32 = -32600;
+    pub const METHOD_NOT_FOUND: i32 = -32601;
+    pub const INVALID_PARAMS: i32 = -32602;
+    pub const INTERNAL_ERROR: i32 = -32603;
+    /// 命令本身返回了错误，`message` 为命令的错误信息
+    pub const COMMAND_FAILED: i32 = -32000;
+    /// 客户端要求的协议版本比服务端新
+    pub const UNSUPPORTED_VERSION: i32 = -32001;
+
+    fn new(code: i32, message: impl Into<String>) -> Self {
+        Self { code, message: message.into() }
+    }
+}
+
+type Reply = std::result::Result<Value, RpcError>;
+
+fn reply(value: impl Serialize) -> Reply {
+    serde_json::to_value(value).map_err(|e| RpcError::new(RpcError::INTERNAL_ERROR, format!("序列化结果失败: {}", e)))
+}
+
+fn done(result: Result<impl Serialize>) -> Reply {
+    result.map_err(|e| RpcError::new(RpcError::COMMAND_FAILED, e)).and_then(reply)
+}
+
+// --- 方法 ---
+
+/// 按名称传入的参数
+struct Params(Map<String, Value>);
+
+impl Params {
+    fn new(value: Value) -> std::result::Result<Self, RpcError> {
+        match value {
+            Value::Null => Ok(Params(Map::new())),
+            Value::Object(map) => Ok(Params(map)),
+            _ => Err(RpcError::new(RpcError::INVALID_PARAMS, "params 必须是按名称传参的对象")),
+        }
+    }
+
+    /// 没有传的参数按 `null` 处理，对应命令里的 `Option` 参数
+    fn get<T: DeserializeOwned>(&self, name: &str) -> std::result::Result<T, RpcError> {
+        let value = self.0.get(name).cloned().unwrap_or(Value::Null);
+        serde_json::from_value(value).map_err(|e| RpcError::new(RpcError::INVALID_PARAMS, format!("参数 {} 无效: {}", name, e)))
+    }
+}
+
+type Handler = fn(&Params) -> Reply;
+
+/// 对外开放的 Tauri 命令；只与界面有关的 `exit_app`、`open_github` 不在其中。都会阻塞，在 `spawn_blocking` 中调用
+const METHODS: &[(&str, Handler)] = &[
+    ("restrict_processes", |p| reply(super::restrict_processes(p.get("enabled")?))),
+    ("plan_restrictions", |p| done(super::plan_restrictions(p.get("enabled")?, p.get("profile_ids")?))),
+    ("apply_plan", |p| done(super::apply_plan(p.get("id")?))),
+    ("reset_processes", |_| done(super::reset_processes())),
+    ("restore_all", |_| done(super::restore_all())),
+    ("get_system_info", |_| reply(super::get_system_info())),
+    ("get_process_performance", |_| reply(super::get_process_performance())),
+    ("get_thread_usage", |p| done(super::get_thread_usage(p.get("pid")?))),
+    ("get_watch_status", |_| reply(super::get_watch_status())),
+    ("configure_watcher", |p| reply(super::configure_watcher(p.get("enabled")?, p.get("kinds")?))),
+    ("get_drift_status", |_| reply(super::get_drift_status())),
+    ("list_targets", |_| reply(super::list_targets())),
+    ("add_target", |p| done(super::add_target(p.get("name")?, p.get("matcher")?, p.get("actions")?, p.get("inherit")?))),
+    ("remove_target", |p| done(super::remove_target(p.get("name")?))),
+    ("enable_autostart", |p| done(super::enable_autostart(p.get("backend")?))),
+    ("disable_autostart", |_| done(super::disable_autostart())),
+    ("check_autostart", |_| reply(super::check_autostart())),
+    ("get_autostart_status", |_| done(super::get_autostart_status())),
+    ("lower_ace_priority", |_| done(super::lower_ace_priority())),
+    ("reset_ace_priority", |_| done(super::reset_ace_priority())),
+    ("check_registry_priority", |_| reply(super::check_registry_priority())),
+    ("list_profiles", |_| reply(super::list_profiles())),
+    ("apply_profile", |p| done(super::apply_profile(p.get("id")?))),
+    ("reset_profile", |p| done(super::reset_profile(p.get("id")?))),
+    ("list_transactions", |_| reply(super::list_transactions())),
+    ("revert_transaction", |p| done(super::revert_transaction(p.get("id")?))),
+];
+
+#[derive(Deserialize)]
+struct Request {
+    jsonrpc: String,
+    /// 没有 id 的是通知，执行但不回复
+    #[serde(default)]
+    id: Option<Value>,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+/// 单个连接的状态
+#[derive(Default)]
+struct Session {
+    topics: BTreeSet<&'static str>,
+}
+
+impl Session {
+    /// 协商版本：客户端声明自己使用的版本，比服务端新时拒绝
+    fn hello(&self, params: &Params) -> Reply {
+        let version: Option<u32> = params.get("version")?;
+        if let Some(version) = version.filter(|&v| v > PROTOCOL_VERSION) {
+            return Err(RpcError::new(
+                RpcError::UNSUPPORTED_VERSION,
+                format!("协议版本 {} 过新，当前仅支持 {}", version, PROTOCOL_VERSION),
+            ));
+        }
+        let methods: Vec<&str> = METHODS.iter().map(|(name, _)| *name).chain(["hello", "subscribe", "unsubscribe"]).collect();
+        Ok(json!({
+            "protocol": PROTOCOL_VERSION,
+            "app": env!("CARGO_PKG_VERSION"),
+            "methods": methods,
+            "topics": TOPICS,
+        }))
+    }
+
+    /// 返回订阅后的全部主题
+    fn subscribe(&mut self, params: &Params) -> Reply {
+        let topics: Vec<String> = params.get("topics")?;
+        let mut resolved = Vec::new();
+        for topic in &topics {
+            let known = TOPICS.iter().find(|t| **t == topic).ok_or_else(|| {
+                RpcError::new(RpcError::INVALID_PARAMS, format!("未知的事件主题: {}（可用: {}）", topic, TOPICS.join(", ")))
+            })?;
+            resolved.push(*known);
+        }
+        self.topics.extend(resolved);
+        reply(&self.topics)
+    }
+
+    /// 不带 `topics` 时取消全部订阅
+    fn unsubscribe(&mut self, params: &Params) -> Reply {
+        match params.get::<Option<Vec<String>>>("topics")? {
+            Some(topics) => self.topics.retain(|t| !topics.iter().any(|name| name == t)),
+            None => self.topics.clear(),
+        }
+        reply(&self.topics)
+    }
+
+    /// 处理一行输入；通知返回 `None`
+    async fn handle(&mut self, line: &str) -> Option<Value> {
+        let value: Value = match serde_json::from_str(line) {
+            Ok(value) => value,
+            Err(e) => return Some(response(Value::Null, Err(RpcError::new(RpcError::PARSE_ERROR, format!("无法解析 JSON: {}", e))))),
+        };
+        if value.is_array() {
+            return Some(response(Value::Null, Err(RpcError::new(RpcError::INVALID_REQUEST, "不支持批量请求"))));
+        }
+        let request = match serde_json::from_value::<Request>(value) {
+            Ok(request) if request.jsonrpc == "2.0" => request,
+            Ok(_) => return Some(response(Value::Null, Err(RpcError::new(RpcError::INVALID_REQUEST, "jsonrpc 必须为 \"2.0\"")))),
+            Err(e) => return Some(response(Value::Null, Err(RpcError::new(RpcError::INVALID_REQUEST, format!("请求格式错误: {}", e))))),
+        };
+        let result = match Params::new(request.params) {
+            Err(e) => Err(e),
+            Ok(params) => match request.method.as_str() {
+                "hello" => self.hello(&params),
+                "subscribe" => self.subscribe(&params),
+                "unsubscribe" => self.unsubscribe(&params),
+                method => match METHODS.iter().find(|(name, _)| *name == method) {
+                    Some(&(_, handler)) => tokio::task::spawn_blocking(move || handler(&params))
+                        .await
+                        .unwrap_or_else(|e| Err(RpcError::new(RpcError::INTERNAL_ERROR, format!("命令执行异常: {}", e)))),
+                    None => Err(RpcError::new(RpcError::METHOD_NOT_FOUND, format!("未知方法: {}", method))),
+                },
+            },
+        };
+        request.id.map(|id| response(id, result))
+    }
+}
+
+fn response(id: Value, result: Reply) -> Value {
+    match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(error) => json!({ "jsonrpc": "2.0", "id": id, "error": error }),
+    }
+}
+
+// --- 事件 ---
+
+#[derive(Clone)]
+struct Event {
+    topic: &'static str,
+    data: Value,
+}
+
+fn events() -> &'static broadcast::Sender<Event> {
+    static EVENTS: OnceLock<broadcast::Sender<Event>> = OnceLock::new();
+    // 慢的连接最多落后这么多条，再多就丢弃并通知它
+    EVENTS.get_or_init(|| broadcast::channel(256).0)
+}
+
+/// 推送给订阅了 `topic` 的连接；没有连接时直接丢弃
+pub fn publish(topic: &'static str, data: &impl Serialize) {
+    if let Ok(data) = serde_json::to_value(data) {
+        let _ = events().send(Event { topic, data });
+    }
+}
+
+// --- 连接 ---
+
+/// 服务一个连接直到对方断开：逐行读请求并按顺序回复，其间转发订阅的事件
+async fn serve(stream: impl AsyncRead + AsyncWrite) {
+    let (reader, mut writer) = tokio::io::split(stream);
+    let mut lines = BufReader::new(reader).lines();
+    let mut events = events().subscribe();
+    let mut session = Session::default();
+    loop {
+        let message = tokio::select! {
+            line = lines.next_line() => match line {
+                Ok(Some(line)) if line.trim().is_empty() => continue,
+                Ok(Some(line)) => match session.handle(&line).await {
+                    Some(message) => message,
+                    None => continue,
+                },
+                _ => return,
+            },
+            event = events.recv() => match event {
+                Ok(event) if session.topics.contains(event.topic) => json!({
+                    "jsonrpc": "2.0",
+                    "method": "event",
+                    "params": { "topic": event.topic, "data": event.data },
+                }),
+                Ok(_) => continue,
+                // 丢了事件的订阅者需要自己重新查询状态
+                Err(broadcast::error::RecvError::Lagged(count)) if !session.topics.is_empty() => json!({
+                    "jsonrpc": "2.0",
+                    "method": "events_lost",
+                    "params": { "count": count },
+                }),
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => return,
+            },
+        };
+        let mut line = message.to_string();
+        line.push('\n');
+        if writer.write_all(line.as_bytes()).await.is_err() {
+            return;
+        }
+    }
+}
+
+/// 控制接口的地址，用于日志和客户端
+pub fn address() -> String {
+    #[cfg(unix)]
+    return unix::address().display().to_string();
+    #[cfg(windows)]
+    return pipe::ADDRESS.to_string();
+    #[cfg(not(any(unix, windows)))]
+    return String::new();
+}
+
+#[cfg(unix)]
+async fn listen() -> Result<()> {
+    unix::listen().await
+}
+
+#[cfg(windows)]
+async fn listen() -> Result<()> {
+    pipe::listen().await
+}
+
+#[cfg(not(any(unix, windows)))]
+async fn listen() -> Result<()> {
+    Err("当前平台不支持控制接口".to_string())
+}
+
+/// 在后台线程上运行控制接口；地址已被另一个实例占用等原因无法启动时只打印原因
+pub fn spawn() {
+    std::thread::spawn(|| {
+        let runtime = match tokio::runtime::Builder::new_current_thread().enable_all().build() {
+            Ok(runtime) => runtime,
+            Err(e) => return eprintln!("控制接口未启动: {}", e),
+        };
+        if let Err(e) = runtime.block_on(listen()) {
+            eprintln!("控制接口未启动: {}", e);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error_code(reply: &Value) -> i64 {
+        reply["error"]["code"].as_i64().unwrap()
+    }
+
+    #[tokio::test]
+    async fn malformed_requests_get_errors() {
+        let mut session = Session::default();
+        let reply = session.handle("{ not json").await.unwrap();
+        assert_eq!(error_code(&reply), RpcError::PARSE_ERROR as i64);
+        assert_eq!(reply["id"], Value::Null);
+
+        let reply = session.handle(r#"{"jsonrpc":"1.0","id":1,"method":"hello"}"#).await.unwrap();
+        assert_eq!(error_code(&reply), RpcError::INVALID_REQUEST as i64);
+        let reply = session.handle(r#"[{"jsonrpc":"2.0","id":1,"method":"hello"}]"#).await.unwrap();
+        assert_eq!(error_code(&reply), RpcError::INVALID_REQUEST as i64);
+        let reply = session.handle(r#"{"jsonrpc":"2.0","id":1}"#).await.unwrap();
+        assert_eq!(error_code(&reply), RpcError::INVALID_REQUEST as i64);
+
+        let reply = session.handle(r#"{"jsonrpc":"2.0","id":2,"method":"launch"}"#).await.unwrap();
+        assert_eq!((error_code(&reply), reply["id"].as_i64()), (RpcError::METHOD_NOT_FOUND as i64, Some(2)));
+        let reply = session.handle(r#"{"jsonrpc":"2.0","id":3,"method":"hello","params":[1]}"#).await.unwrap();
+        assert_eq!(error_code(&reply), RpcError::INVALID_PARAMS as i64);
+    }
+
+    #[tokio::test]
+    async fn notifications_get_no_reply() {
+        let mut session = Session::default();
+        assert!(session.handle(r#"{"jsonrpc":"2.0","method":"subscribe","params":{"topics":["game-watch"]}}"#).await.is_none());
+        assert!(session.topics.contains(watch::EVENT));
+        // 出错的通知同样不回复
+        assert!(session.handle(r#"{"jsonrpc":"2.0","method":"launch"}"#).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn hello_negotiates_version() {
+        let mut session = Session::default();
+        let reply = session.handle(r#"{"jsonrpc":"2.0","id":"a","method":"hello","params":{"version":1}}"#).await.unwrap();
+        assert_eq!(reply["id"], "a");
+        assert_eq!(reply["result"]["protocol"], PROTOCOL_VERSION);
+        let methods = reply["result"]["methods"].as_array().unwrap();
+        assert!(methods.iter().any(|m| m == "subscribe") && methods.iter().any(|m| m == "apply_plan"));
+
+        assert!(session.hello(&Params::new(Value::Null).unwrap()).is_ok());
+        let newer = Params::new(json!({ "version": PROTOCOL_VERSION + 1 })).unwrap();
+        assert_eq!(session.hello(&newer).unwrap_err().code, RpcError::UNSUPPORTED_VERSION);
+    }
+
+    #[test]
+    fn subscriptions_are_per_session() {
+        let mut session = Session::default();
+        let topics = |names: Value| Params::new(json!({ "topics": names })).unwrap();
+        assert_eq!(session.subscribe(&topics(json!(["game-watch"]))).unwrap(), json!(["game-watch"]));
+
+        let error = session.subscribe(&topics(json!(["game-watch", "weather"]))).unwrap_err();
+        assert_eq!(error.code, RpcError::INVALID_PARAMS);
+        assert!(error.message.contains("weather"));
+        assert!(session.subscribe(&Params::new(Value::Null).unwrap()).is_err());
+
+        assert_eq!(session.unsubscribe(&topics(json!(["weather"]))).unwrap(), json!(["game-watch"]));
+        assert_eq!(session.unsubscribe(&Params::new(Value::Null).unwrap()).unwrap(), json!([]));
+        assert!(Session::default().topics.is_empty());
+    }
+}
@@ -0,0 +1,41 @@
+use std::ffi::c_void;
+
+use tokio::net::windows::named_pipe::{NamedPipeServer, ServerOptions};
+use windows::core::w;
+use windows::Win32::Security::Authorization::{ConvertStringSecurityDescriptorToSecurityDescriptorW, SDDL_REVISION_1};
+use windows::Win32::Security::{PSECURITY_DESCRIPTOR, SECURITY_ATTRIBUTES};
+
+use super::Result;
+
+pub const ADDRESS: &str = r"\\.\pipe\pitayabox-control";
+
+/// 每个连接用掉一个管道实例，连上之后马上创建下一个
+pub async fn listen() -> Result<()> {
+    // 只允许 SYSTEM、管理员和创建者连接；以管理员运行时创建者即管理员组，普通权限的进程连不上
+    let sddl = w!("D:P(A;;GA;;;SY)(A;;GA;;;BA)(A;;GA;;;OW)");
+    let mut descriptor = PSECURITY_DESCRIPTOR::default();
+    // 描述符在整个进程生命周期内复用，不释放
+    unsafe { ConvertStringSecurityDescriptorToSecurityDescriptorW(sddl, SDDL_REVISION_1, &mut descriptor, None) }
+        .map_err(|e| format!("创建管道权限失败: {}", e))?;
+    let mut attributes = SECURITY_ATTRIBUTES {
+        nLength: std::mem::size_of::<SECURITY_ATTRIBUTES>() as u32,
+        lpSecurityDescriptor: descriptor.0,
+        bInheritHandle: false.into(),
+    };
+    let mut create = |first: bool| -> std::io::Result<NamedPipeServer> {
+        unsafe {
+            ServerOptions::new()
+                .first_pipe_instance(first)
+                .reject_remote_clients(true)
+                .create_with_security_attributes_raw(ADDRESS, &mut attributes as *mut SECURITY_ATTRIBUTES as *mut c_void)
+        }
+    };
+
+    // 第一个实例独占名字：已有实例在运行，或者别的进程抢先创建了同名管道想冒充时都会失败
+    let mut server = create(true).map_err(|e| format!("{} 已被另一个实例使用: {}", ADDRESS, e))?;
+    loop {
+        server.connect().await.map_err(|e| format!("等待连接失败: {}", e))?;
+        let next = create(false).map_err(|e| format!("创建管道实例失败: {}", e))?;
+        tokio::spawn(super::serve(std::mem::replace(&mut server, next)));
+    }
+}
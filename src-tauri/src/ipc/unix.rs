@@ -0,0 +1,170 @@
+use std::fs;
+use std::os::unix::fs::{DirBuilderExt, FileTypeExt, MetadataExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use tokio::net::{UnixListener, UnixStream};
+
+use super::Result;
+use crate::paths;
+
+const SOCKET_NAME: &str = "control.sock";
+
+/// 默认为 `$XDG_RUNTIME_DIR/pitayabox/control.sock`，没有运行时目录时放在配置目录的 `run` 下；
+/// 设置 `PITAYABOX_CONTROL_SOCKET` 可以改写
+pub fn address() -> PathBuf {
+    if let Some(path) = std::env::var_os("PITAYABOX_CONTROL_SOCKET") {
+        return PathBuf::from(path);
+    }
+    private_dir().join(SOCKET_NAME)
+}
+
+/// 只放本程序套接字的目录，权限由这里决定
+fn private_dir() -> PathBuf {
+    match std::env::var_os("XDG_RUNTIME_DIR") {
+        Some(dir) => PathBuf::from(dir).join("pitayabox"),
+        None => paths::config_dir().join("run"),
+    }
+}
+
+/// 能连接的用户组：`PITAYABOX_CONTROL_GROUP` 写组名或 gid，不设置时只有本用户（和 root）能连接
+fn control_group() -> Result<Option<u32>> {
+    let Some(name) = std::env::var("PITAYABOX_CONTROL_GROUP").ok().filter(|s| !s.is_empty()) else {
+        return Ok(None);
+    };
+    if let Ok(gid) = name.parse() {
+        return Ok(Some(gid));
+    }
+    let groups = fs::read_to_string("/etc/group").map_err(|e| format!("读取 /etc/group 失败: {}", e))?;
+    groups
+        .lines()
+        .find_map(|line| {
+            let mut fields = line.split(':');
+            let group = fields.next()?;
+            let gid = fields.nth(1)?.parse().ok()?;
+            (group == name).then_some(gid)
+        })
+        .map(Some)
+        .ok_or_else(|| format!("找不到用户组 {}", name))
+}
+
+fn restrict(path: &Path, mode: u32, group: Option<u32>) -> Result<()> {
+    if let Some(gid) = group {
+        std::os::unix::fs::chown(path, None, Some(gid)).map_err(|e| format!("修改 {} 的属组失败: {}", path.display(), e))?;
+    }
+    fs::set_permissions(path, fs::Permissions::from_mode(mode)).map_err(|e| format!("修改 {} 的权限失败: {}", path.display(), e))
+}
+
+/// 能否连接完全由文件权限决定：连接 Unix 套接字需要对它有写权限
+pub async fn listen() -> Result<()> {
+    let path = address();
+    let group = control_group()?;
+    let (dir_mode, socket_mode) = if group.is_some() { (0o750, 0o660) } else { (0o700, 0o600) };
+
+    let dir = private_dir();
+    if path.parent() == Some(dir.as_path()) {
+        // 先收紧目录再创建套接字，bind 和 chmod 之间的空档里别人也进不来
+        fs::create_dir_all(&dir).map_err(|e| format!("创建 {} 失败: {}", dir.display(), e))?;
+        restrict(&dir, dir_mode, group)?;
+    }
+    if path.exists() {
+        if UnixStream::connect(&path).await.is_ok() {
+            return Err(format!("{} 已被另一个实例使用", path.display()));
+        }
+        // 上次没有正常退出留下的套接字文件
+        remove_stale(&path)?;
+    }
+    let listener = if path.parent() == Some(dir.as_path()) {
+        let listener = UnixListener::bind(&path).map_err(|e| format!("监听 {} 失败: {}", path.display(), e))?;
+        restrict(&path, socket_mode, group)?;
+        listener
+    } else {
+        bind_private(&path, socket_mode, group)?
+    };
+
+    // 文件描述符耗尽等错误会让 accept 立即再次失败，不退避就会空转刷屏
+    const MAX_BACKOFF: Duration = Duration::from_secs(5);
+    let mut backoff = Duration::from_millis(50);
+    loop {
+        match listener.accept().await {
+            Ok((stream, _)) => {
+                backoff = Duration::from_millis(50);
+                tokio::spawn(super::serve(stream));
+            }
+            Err(e) => {
+                eprintln!("控制接口接受连接失败: {}，{} 毫秒后重试", e, backoff.as_millis());
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(MAX_BACKOFF);
+            }
+        }
+    }
+}
+
+/// 只删除本用户留下的套接字；路径上是普通文件、符号链接或别人的套接字时报错，由用户确认后手动处理
+fn remove_stale(path: &Path) -> Result<()> {
+    let metadata = fs::symlink_metadata(path).map_err(|e| format!("读取 {} 失败: {}", path.display(), e))?;
+    if !metadata.file_type().is_socket() || Some(metadata.uid()) != current_uid() {
+        return Err(format!("{} 已存在且不是本用户留下的套接字，请确认后手动删除", path.display()));
+    }
+    fs::remove_file(path).map_err(|e| format!("删除残留的 {} 失败: {}", path.display(), e))
+}
+
+#[cfg(target_os = "linux")]
+fn current_uid() -> Option<u32> {
+    Some(unsafe { libc::geteuid() })
+}
+
+/// 其他平台没有引入 libc，认不出属主时一律不删
+#[cfg(not(target_os = "linux"))]
+fn current_uid() -> Option<u32> {
+    None
+}
+
+/// 用户指定的位置所在目录不归本程序管，直接 bind 的话在 chmod 之前按 umask 创建的套接字谁都能连。
+/// 改为在旁边一个只有本用户能进的临时目录里 bind 并收紧权限，再整体改名到目标位置
+fn bind_private(path: &Path, mode: u32, group: Option<u32>) -> Result<UnixListener> {
+    let parent = path.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    let name = path.file_name().ok_or_else(|| format!("无效的套接字路径: {}", path.display()))?;
+    let staging = parent.join(format!(".{}.{}", name.to_string_lossy(), std::process::id()));
+    // 残留的同名目录可能是别人预先放的，不复用
+    fs::DirBuilder::new()
+        .mode(0o700)
+        .create(&staging)
+        .map_err(|e| format!("创建 {} 失败: {}", staging.display(), e))?;
+    let staged = staging.join(name);
+    let result = UnixListener::bind(&staged)
+        .map_err(|e| format!("监听 {} 失败: {}", staged.display(), e))
+        .and_then(|listener| {
+            restrict(&staged, mode, group)?;
+            fs::rename(&staged, path).map_err(|e| format!("把套接字移到 {} 失败: {}", path.display(), e))?;
+            Ok(listener)
+        });
+    let _ = fs::remove_file(&staged);
+    let _ = fs::remove_dir(&staging);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn removes_only_own_sockets() {
+        let dir = paths::ScratchDir::new("ipc");
+        let socket = dir.join(SOCKET_NAME);
+        drop(std::os::unix::net::UnixListener::bind(&socket).unwrap());
+        remove_stale(&socket).unwrap();
+        assert!(!socket.exists());
+
+        let file = dir.join("notes.txt");
+        fs::write(&file, "keep").unwrap();
+        assert!(remove_stale(&file).is_err());
+        assert_eq!(fs::read_to_string(&file).unwrap(), "keep");
+
+        let link = dir.join("link.sock");
+        std::os::unix::fs::symlink(&file, &link).unwrap();
+        assert!(remove_stale(&link).is_err());
+        assert!(fs::symlink_metadata(&link).is_ok());
+        assert!(remove_stale(&dir.join("missing.sock")).is_err());
+    }
+}
@@ -0,0 +1,294 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::paths;
+use crate::process::{ProcessController, ProcessSnapshot};
+
+type Result<T> = std::result::Result<T, String>;
+
+/// 一个进程在第一次被修改前的状态
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub pid: u32,
+    /// 命中的规则名
+    pub name: String,
+    /// 进程启动时间（Unix 秒），用于识别 PID 被复用
+    pub start_time: u64,
+    /// 记录时间（Unix 秒）
+    pub taken_at: u64,
+    pub snapshot: ProcessSnapshot,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct JournalFile {
+    #[serde(default)]
+    version: u32,
+    #[serde(default)]
+    entries: Vec<JournalEntry>,
+}
+
+/// `restore_all` 的结果
+#[derive(Debug, Default, Serialize)]
+pub struct RestoreReport {
+    pub restored: Vec<u32>,
+    /// 已退出或 PID 已被复用，记录直接丢弃
+    pub gone: Vec<u32>,
+    /// 恢复失败的进程保留在日志里，下次再试
+    pub errors: Vec<String>,
+}
+
+impl RestoreReport {
+    pub fn message(&self) -> String {
+        let mut message = match self.restored.len() {
+            0 => "没有需要恢复的进程".to_string(),
+            n => format!("已恢复 {} 个进程的原始状态", n),
+        };
+        if !self.errors.is_empty() {
+            message = format!("{}（{} 项失败）\n{}", message, self.errors.len(), self.errors.join("\n"));
+        }
+        message
+    }
+}
+
+/// 状态日志：修改进程之前先记下它原来的样子并立即落盘，应用重启或崩溃后仍能撤销上一次留下的修改
+pub struct Journal {
+    path: PathBuf,
+    entries: Mutex<Vec<JournalEntry>>,
+}
+
+impl Journal {
+    pub const FILE_NAME: &'static str = "journal.json";
+    pub const VERSION: u32 = 1;
+
+    /// 文件无法读取（损坏或由更新的版本写入）时原样保留，之后的记录和恢复都会报错，而不是从空日志重新开始
+    pub fn open(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let entries = load(&path).map(|file| file.entries).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            Vec::new()
+        });
+        Self { path, entries: Mutex::new(entries) }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// 重新读取文件，其他进程（如命令行）写入的记录也能看到；读取失败时返回上次的内容
+    pub fn entries(&self) -> Vec<JournalEntry> {
+        let mut cached = self.entries.lock().unwrap();
+        match load(&self.path) {
+            Ok(file) => *cached = file.entries,
+            Err(e) => eprintln!("{}", e),
+        }
+        cached.clone()
+    }
+
+    /// 在文件锁内重新读取日志交给 `f` 修改，有变化时写回
+    ///
+    /// 界面和命令行各有一份内存中的日志，只按内存内容整体写回会丢掉对方的记录。
+    fn update<T>(&self, f: impl FnOnce(&mut Vec<JournalEntry>) -> Result<T>) -> Result<T> {
+        let mut cached = self.entries.lock().unwrap();
+        let _lock = paths::lock(&self.path).map_err(|e| format!("锁定状态日志失败 ({}): {}", self.path.display(), e))?;
+        let mut entries = load(&self.path)
+            .map_err(|e| format!("{}；为免覆盖其中保存的原始状态，修复或移走该文件之前不会修改进程", e))?
+            .entries;
+        let original = entries.clone();
+        let value = f(&mut entries)?;
+        if entries != original {
+            save(&self.path, &entries)?;
+        }
+        *cached = entries;
+        Ok(value)
+    }
+
+    /// 在第一次修改前调用；同一进程已有记录时保留最早的快照，PID 被复用时替换旧记录
+    pub fn capture(&self, ctl: &dyn ProcessController, pid: u32, name: &str, start_time: u64) -> Result<()> {
+        self.update(|entries| {
+            if entries.iter().any(|e| e.pid == pid && e.start_time == start_time) {
+                return Ok(());
+            }
+            let snapshot = ctl.snapshot(pid)?;
+            entries.retain(|e| e.pid != pid);
+            entries.push(JournalEntry { pid, name: name.to_string(), start_time, taken_at: now(), snapshot });
+            Ok(())
+        })
+    }
+
+    /// 恢复单个进程并删除记录；没有记录或 PID 已被复用时返回 false
+    pub fn restore(&self, ctl: &dyn ProcessController, pid: u32, start_time: u64) -> Result<bool> {
+        self.update(|entries| {
+            let Some(index) = entries.iter().position(|e| e.pid == pid) else {
+                return Ok(false);
+            };
+            let matched = entries[index].start_time == start_time;
+            if matched {
+                ctl.restore_snapshot(pid, &entries[index].snapshot)?;
+            }
+            entries.remove(index);
+            Ok(matched)
+        })
+    }
+
+    /// 恢复所有记录；`start_time` 返回进程当前的启动时间，进程不存在时为 `None`
+    pub fn restore_all(&self, ctl: &dyn ProcessController, start_time: impl Fn(u32) -> Option<u64>) -> RestoreReport {
+        let mut report = RestoreReport::default();
+        let result = self.update(|entries| {
+            entries.retain(|entry| {
+                if start_time(entry.pid) != Some(entry.start_time) {
+                    report.gone.push(entry.pid);
+                    return false;
+                }
+                match ctl.restore_snapshot(entry.pid, &entry.snapshot) {
+                    Ok(()) => {
+                        report.restored.push(entry.pid);
+                        false
+                    }
+                    Err(e) => {
+                        report.errors.push(format!("{} (PID: {}): {}", entry.name, entry.pid, e));
+                        true
+                    }
+                }
+            });
+            Ok(())
+        });
+        if let Err(e) = result {
+            report.errors.push(e);
+        }
+        report
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+fn load(path: &Path) -> Result<JournalFile> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(JournalFile::default()),
+        Err(e) => return Err(format!("读取状态日志失败 ({}): {}", path.display(), e)),
+    };
+    let file: JournalFile =
+        serde_json::from_str(&content).map_err(|e| format!("状态日志格式错误 ({}): {}", path.display(), e))?;
+    if file.version > Journal::VERSION {
+        return Err(format!("状态日志版本 {} 过新，当前仅支持 {}", file.version, Journal::VERSION));
+    }
+    Ok(file)
+}
+
+fn save(path: &Path, entries: &[JournalEntry]) -> Result<()> {
+    let file = JournalFile { version: Journal::VERSION, entries: entries.to_vec() };
+    let content = serde_json::to_string_pretty(&file).map_err(|e| format!("序列化状态日志失败: {}", e))?;
+    paths::write_atomic(path, &content).map_err(|e| format!("写入状态日志失败 ({}): {}", path.display(), e))
+}
+
+/// 进程内共享的状态日志，默认位于配置目录下的 `journal.json`
+pub fn journal() -> &'static Journal {
+    static JOURNAL: OnceLock<Journal> = OnceLock::new();
+    JOURNAL.get_or_init(|| Journal::open(paths::config_dir().join(Journal::FILE_NAME)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpuset::CpuSet;
+    use crate::process::mock::{Call, MockController};
+
+    /// 目录随返回的 `ScratchDir` 一起删除，测试期间要一直持有
+    fn journal() -> (paths::ScratchDir, Journal) {
+        let dir = paths::ScratchDir::new("journal");
+        let journal = Journal::open(dir.join(Journal::FILE_NAME));
+        (dir, journal)
+    }
+
+    fn cpus(ids: &[usize]) -> CpuSet {
+        ids.iter().copied().collect()
+    }
+
+    /// 记下原始状态后把进程限制到核心 3 并降为空闲
+    fn capture_and_restrict(journal: &Journal, ctl: &MockController, pid: u32, start_time: u64) {
+        ctl.set_cpu_affinity(pid, &cpus(&[0, 1, 2, 3])).unwrap();
+        journal.capture(ctl, pid, "game.exe", start_time).unwrap();
+        ctl.set_cpu_affinity(pid, &cpus(&[3])).unwrap();
+        ctl.set_idle_priority(pid).unwrap();
+        ctl.set_efficiency_mode(pid).unwrap();
+    }
+
+    #[test]
+    fn restore_brings_back_first_snapshot() {
+        let (_dir, journal) = journal();
+        let ctl = MockController::new();
+        capture_and_restrict(&journal, &ctl, 10, 1000);
+        // 已经受限后再记录不会覆盖最早的快照
+        journal.capture(&ctl, 10, "game.exe", 1000).unwrap();
+        assert_eq!(journal.entries().len(), 1);
+
+        assert!(journal.restore(&ctl, 10, 1000).unwrap());
+        assert_eq!(ctl.cpu_affinity(10).unwrap(), cpus(&[0, 1, 2, 3]));
+        assert!(!ctl.is_idle_priority(10).unwrap());
+        assert!(!ctl.efficiency_mode(10).unwrap());
+        assert!(journal.entries().is_empty());
+        assert!(!journal.restore(&ctl, 10, 1000).unwrap());
+    }
+
+    #[test]
+    fn reused_pid_is_dropped_without_restoring() {
+        let (_dir, journal) = journal();
+        let ctl = MockController::new();
+        capture_and_restrict(&journal, &ctl, 11, 1000);
+        assert!(!journal.restore(&ctl, 11, 2000).unwrap());
+        assert!(journal.entries().is_empty());
+        assert!(!ctl.calls().contains(&Call::RestoreSnapshot { pid: 11 }));
+
+        // 新进程用了同一个 PID 时替换旧记录
+        capture_and_restrict(&journal, &ctl, 12, 1000);
+        journal.capture(&ctl, 12, "other.exe", 3000).unwrap();
+        let entries = journal.entries();
+        assert_eq!(entries.len(), 1);
+        assert_eq!((entries[0].name.as_str(), entries[0].start_time), ("other.exe", 3000));
+    }
+
+    #[test]
+    fn entries_survive_reopen() {
+        let (_dir, journal) = journal();
+        let ctl = MockController::new();
+        capture_and_restrict(&journal, &ctl, 20, 1000);
+        let reopened = Journal::open(journal.path());
+        assert_eq!(reopened.entries(), journal.entries());
+        assert_eq!(reopened.entries()[0].snapshot.cpus, Some(cpus(&[0, 1, 2, 3])));
+    }
+
+    #[test]
+    fn restore_all_reports_each_outcome() {
+        let (_dir, journal) = journal();
+        let ctl = MockController::new();
+        for pid in [30, 31, 32] {
+            capture_and_restrict(&journal, &ctl, pid, 1000);
+        }
+        ctl.fail_pid(32);
+        let report = journal.restore_all(&ctl, |pid| (pid != 31).then_some(1000));
+        assert_eq!(report.restored, vec![30]);
+        assert_eq!(report.gone, vec![31]);
+        assert_eq!(report.errors.len(), 1);
+        // 失败的留着下次再试
+        assert_eq!(journal.entries().iter().map(|e| e.pid).collect::<Vec<_>>(), vec![32]);
+    }
+
+    #[test]
+    fn unreadable_file_is_left_untouched() {
+        let dir = paths::ScratchDir::new("journal");
+        let path = dir.join(Journal::FILE_NAME);
+        let content = r#"{ "version": 99, "entries": [] }"#;
+        fs::write(&path, content).unwrap();
+        let journal = Journal::open(&path);
+        let ctl = MockController::new();
+        assert!(journal.capture(&ctl, 40, "game.exe", 1000).is_err());
+        assert!(!journal.restore_all(&ctl, |_| Some(1000)).errors.is_empty());
+        assert_eq!(fs::read_to_string(&path).unwrap(), content);
+    }
+}
@@ -1,25 +1,51 @@
-use tauri::{Manager, Emitter, WindowEvent};
+use tauri::{Emitter, RunEvent, WindowEvent};
 use std::process::Command;
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+#[cfg(windows)]
 use std::os::windows::process::CommandExt;
-use sysinfo::System; 
-use winreg::enums::*;
-use winreg::RegKey;
-use std::mem;
-use std::ffi::c_void;
-use windows::Win32::Foundation::HANDLE; 
-use windows::Win32::System::Threading::{
-    OpenProcess, SetPriorityClass, SetProcessAffinityMask, SetProcessInformation,
-    PROCESS_ALL_ACCESS, IDLE_PRIORITY_CLASS, PROCESS_POWER_THROTTLING_STATE,
-    PROCESS_POWER_THROTTLING_CURRENT_VERSION, PROCESS_POWER_THROTTLING_EXECUTION_SPEED,
-    ProcessPowerThrottling,
-};
-use windows::Win32::System::ProcessStatus::EmptyWorkingSet;
+use sysinfo::{Pid, ProcessRefreshKind, System, Users};
+
+pub mod actions;
+pub mod autostart;
+pub mod cli;
+pub mod cpuset;
+pub mod drift;
+pub mod ipc;
+pub mod journal;
+pub mod matcher;
+pub mod paths;
+pub mod plan;
+pub mod policy;
+pub mod process;
+pub mod profiles;
+pub mod registry;
+pub mod targets;
+pub mod topology;
+pub mod transaction;
+pub mod tree;
+pub mod watch;
+
+use actions::{ActionKind, ActionSet};
+use cpuset::CpuSet;
+use matcher::{ProcessInfo, ProcessMatcher, ProcessName};
+use process::events::{self, PidDiff, ProcessEvent};
+use process::threads::{self, ThreadUsage};
+use process::trim;
+use process::ProcessController;
+use profiles::ProfileStatus;
+use targets::{Assignment, Target, TargetConfig};
+use topology::{PlacementContext, Topology};
+use watch::{Transition, WatchEvent, WatchSettings, WatchStatus};
 
 // --- 结构体 ---
 #[derive(serde::Serialize)]
 struct ProcessStatus {
-    target_core: usize,
+    /// 本次绑定到的核心；没有规则设置亲和性时为默认选择的核心
+    target_cores: CpuSet,
     sguard64_restricted: bool,
+    /// 本次工作集修剪回收的内存总量
+    reclaimed_bytes: u64,
     message: String,
 }
 
@@ -40,74 +66,393 @@ struct ProcessPerformance {
 }
 
 // --- 辅助函数 ---
-unsafe fn set_efficiency_mode(handle: HANDLE) -> bool {
-    let mut throttling_state = PROCESS_POWER_THROTTLING_STATE {
-        Version: PROCESS_POWER_THROTTLING_CURRENT_VERSION,
-        ControlMask: PROCESS_POWER_THROTTLING_EXECUTION_SPEED,
-        StateMask: PROCESS_POWER_THROTTLING_EXECUTION_SPEED,
+/// 构造一个不弹出控制台窗口的命令
+pub(crate) fn hidden_command(program: &str) -> Command {
+    #[allow(unused_mut)]
+    let mut cmd = Command::new(program);
+    #[cfg(windows)]
+    cmd.creation_flags(0x08000000);
+    cmd
+}
+
+/// ACE 的两个进程，持久化降权时必须同时生效
+const ACE_EXES: [&str; 2] = ["SGuard64.exe", "SGuardSvc64.exe"];
+
+/// 正在运行的游戏被限定使用的核心；游戏没有运行或没有被限定时为空
+fn game_cpus(ctl: &dyn ProcessController, sys: &System, topology: &Topology) -> CpuSet {
+    let games: Vec<&str> = profiles::catalog().profiles.iter().flat_map(|p| &p.games).map(|g| g.exe_name.as_str()).collect();
+    let cpus = sys
+        .processes()
+        .iter()
+        .filter(|(_, p)| games.iter().any(|g| ProcessName::new(p).is(g)))
+        .filter_map(|(pid, _)| ctl.cpu_affinity(pid.as_u32()).ok())
+        .fold(CpuSet::new(), |all, cpus| all.union(&cpus));
+    if topology.all().is_subset(&cpus) { CpuSet::new() } else { cpus }
+}
+
+/// 用当前的负载和游戏亲和性构造核心选择的上下文；`sys` 需要已经刷新过两次 CPU 占用
+fn placement_context<'a>(ctl: &dyn ProcessController, sys: &System, topology: &'a Topology) -> PlacementContext<'a> {
+    PlacementContext::new(topology)
+        .with_load(sys.cpus().iter().map(|cpu| cpu.cpu_usage()).collect())
+        .with_game_cpus(game_cpus(ctl, sys, topology))
+}
+
+/// 对所有目标进程（含 `inherit` 规则的后代）执行各自规则里的动作，`kinds` 之外的动作类别跳过；
+/// `only_new` 时跳过已在漂移记录中的进程。先生成计划再立即执行，与具体平台无关，可以配合 `MockController` 运行
+fn apply_restrictions(ctl: &dyn ProcessController, sys: &System, users: &Users, targets: &TargetConfig, kinds: &[ActionKind], topology: &Topology, only_new: bool) -> ProcessStatus {
+    let ctx = placement_context(ctl, sys, topology);
+    let assignments = targets.resolve(sys, users, tree::lineage());
+    let found = !assignments.is_empty();
+    let processes = plan::plan_processes(ctl, targets, assignments, |pid| start_time(sys, pid), kinds, &ctx, only_new);
+    let lines: Vec<String> = processes.iter().map(plan::ProcessPlan::describe).collect();
+    let outcome = plan::execute_processes(ctl, journal::journal(), &processes, topology, |pid| Some(start_time(sys, pid)));
+    process_status(found, &lines, outcome, plan::default_cores(&ctx))
+}
+
+/// 汇总执行结果；`lines` 为执行的计划，逐行附在消息后面
+fn process_status(found: bool, lines: &[String], outcome: plan::Outcome, default_cores: CpuSet) -> ProcessStatus {
+    let plan::Outcome { reclaimed_bytes, demoted_threads, cpus, errors } = outcome;
+    let mut message = match (found, reclaimed_bytes) {
+        (false, _) => "未发现 ACE 进程".to_string(),
+        (true, 0) => "ACE 限制已生效".to_string(),
+        (true, bytes) => format!("ACE 限制已生效，回收内存 {:.1} MB", bytes as f64 / 1024.0 / 1024.0),
     };
-    let result = SetProcessInformation(
-        handle,
-        ProcessPowerThrottling,
-        &mut throttling_state as *mut _ as *mut c_void,
-        mem::size_of::<PROCESS_POWER_THROTTLING_STATE>() as u32,
-    );
-    result.is_ok()
-}
-
-fn set_registry_priority(exe_name: &str, priority: u32) -> Result<String, String> {
-    let hk_lm = RegKey::predef(HKEY_LOCAL_MACHINE);
-    let path = format!("SOFTWARE\\Microsoft\\Windows NT\\CurrentVersion\\Image File Execution Options\\{}\\PerfOptions", exe_name);
-    let (key, _) = hk_lm.create_subkey(&path).map_err(|e| format!("权限不足: {}", e))?;
-    key.set_value("CpuPriorityClass", &priority).map_err(|e| format!("写入失败: {}", e))?;
-    Ok(format!("{} 优化已应用", exe_name))
-}
-
-fn reset_registry_priority(exe_name: &str) -> Result<String, String> {
-    let hk_lm = RegKey::predef(HKEY_LOCAL_MACHINE);
-    let parent_path = format!("SOFTWARE\\Microsoft\\Windows NT\\CurrentVersion\\Image File Execution Options\\{}", exe_name);
-    if let Ok(key) = hk_lm.open_subkey_with_flags(&parent_path, KEY_ALL_ACCESS) {
-        match key.delete_subkey("PerfOptions") {
-            Ok(_) => Ok(format!("{} 已恢复默认", exe_name)),
-            Err(e) => Err(format!("恢复失败: {}", e)),
+    if demoted_threads > 0 {
+        message = format!("{}，降级热点线程 {} 个", message, demoted_threads);
+    }
+    if !errors.is_empty() {
+        message = format!("{}（{} 项失败）", message, errors.len());
+    }
+    for line in lines.iter().chain(&errors) {
+        message = format!("{}\n{}", message, line);
+    }
+    let target_cores = if cpus.is_empty() { default_cores } else { cpus };
+    ProcessStatus { target_cores, sguard64_restricted: found, reclaimed_bytes, message }
+}
+
+fn start_time(sys: &System, pid: u32) -> u64 {
+    sys.process(Pid::from_u32(pid)).map(|p| p.start_time()).unwrap_or(0)
+}
+
+/// 撤销运行时限制：先恢复单独降级的线程，再按状态日志把进程恢复原样，继承来的后代一并恢复
+fn release_restrictions(ctl: &dyn ProcessController, sys: &System, users: &Users, targets: &TargetConfig) -> Vec<String> {
+    let mut errors = Vec::new();
+    for Assignment { pid, .. } in targets.resolve(sys, users, tree::lineage()) {
+        if let Err(e) = threads::monitor().restore_all(ctl, pid) { errors.push(e); }
+        match journal::journal().restore(ctl, pid, start_time(sys, pid)) {
+            Ok(true) => {}
+            // 没有快照时退回后端在本次运行中记住的状态
+            Ok(false) => {
+                if let Err(e) = ctl.restore_priority(pid) { errors.push(e); }
+                if let Err(e) = ctl.clear_efficiency_mode(pid) { errors.push(e); }
+            }
+            Err(e) => errors.push(e),
         }
-    } else {
-        Ok("无需恢复".to_string())
+        drift::tracker().forget(pid);
+        trim::trimmer().forget(pid);
+    }
+    for target in &targets.targets {
+        tree::lineage().forget_rule(&target.name);
     }
+    errors
 }
 
-// --- 核心命令 ---
-#[tauri::command]
-fn restrict_processes(enable_cpu_affinity: bool, enable_process_priority: bool, enable_efficiency_mode: bool, enable_io_priority: bool, enable_memory_priority: bool) -> ProcessStatus {
+/// 把状态日志里的所有进程恢复原样，不限于当前的目标规则；已退出的进程直接丢弃记录
+fn restore_all_processes(ctl: &dyn ProcessController) -> journal::RestoreReport {
+    let mut sys = System::new();
+    sys.refresh_processes_specifics(ProcessRefreshKind::new());
+    let alive = |pid: u32| sys.process(Pid::from_u32(pid)).map(|p| p.start_time());
+    for entry in journal::journal().entries() {
+        if alive(entry.pid) == Some(entry.start_time) {
+            let _ = threads::monitor().restore_all(ctl, entry.pid);
+        }
+        drift::tracker().forget(entry.pid);
+        trim::trimmer().forget(entry.pid);
+    }
+    journal::journal().restore_all(ctl, alive)
+}
+
+/// 读回所有被限制进程的实际状态，被目标自己改掉的项重新施加
+fn check_drift(ctl: &dyn ProcessController, emit: &dyn Fn(WatchEvent)) {
+    let mut sys = System::new();
+    sys.refresh_processes_specifics(ProcessRefreshKind::new());
+    drift::tracker().retain(|pid| sys.process(Pid::from_u32(pid)).map(|p| p.start_time()));
+    for correction in drift::tracker().check(ctl) {
+        let message = correction.message();
+        let drift::Correction { pid, name, kinds, drifts, .. } = correction;
+        emit(WatchEvent::DriftCorrected { pid, name, kinds, drifts, message });
+    }
+}
+
+/// 规则存在注册表（IFEO）里时由系统在进程启动时施加；其他存储要由这里给新进程补上
+fn enforce_policies(ctl: &dyn ProcessController) {
+    let store = policy::store();
+    if store.name() == "registry" {
+        return;
+    }
+    let mut sys = System::new();
+    sys.refresh_processes_specifics(ProcessRefreshKind::new());
+    let processes = sys.processes().iter().map(|(pid, p)| (pid.as_u32(), ProcessName::new(p), p.start_time()));
+    for e in policy::enforcer().enforce_new(store, ctl, processes) {
+        eprintln!("施加持久化规则失败: {}", e);
+    }
+}
+
+/// 自动检测的一次扫描：先纠正漂移、施加持久化规则，游戏启动时写入方案并限制伴随进程，运行期间给新进程施加，退出后撤销
+fn watch_tick(ctl: &dyn ProcessController, emit: &dyn Fn(WatchEvent)) {
+    check_drift(ctl, emit);
+    enforce_policies(ctl);
+    let settings = watch::watcher().settings();
+    if !settings.enabled && watch::watcher().detector().running().is_empty() {
+        return;
+    }
+    let catalog = profiles::catalog();
     let mut sys = System::new_all();
+    std::thread::sleep(sysinfo::MINIMUM_CPU_UPDATE_INTERVAL);
     sys.refresh_all();
-    let target_process_names = ["SGuard64.exe", "SGuardSvc64.exe"];
-    let cpu_count = sys.cpus().len();
-    let last_core_mask: usize = 1 << (cpu_count - 1); 
-    let mut found = false;
-
-    for process_name in target_process_names.iter() {
-        for (pid, process) in sys.processes() {
-            if process.name() == *process_name {
-                found = true;
-                unsafe {
-                    if let Ok(handle) = OpenProcess(PROCESS_ALL_ACCESS, false, pid.as_u32()) {
-                        if enable_cpu_affinity { let _ = SetProcessAffinityMask(handle, last_core_mask); }
-                        if enable_process_priority { let _ = SetPriorityClass(handle, IDLE_PRIORITY_CLASS); }
-                        if enable_efficiency_mode { let _ = set_efficiency_mode(handle); }
-                        if enable_memory_priority { let _ = EmptyWorkingSet(handle); }
-                        if enable_io_priority { } 
-                    }
+    let users = Users::new_with_refreshed_list();
+    let config = targets::store().config();
+
+    let mut detector = watch::watcher().detector();
+    let transitions = if settings.enabled {
+        detector.update(catalog, sys.processes().iter().map(|(pid, p)| (pid.as_u32(), ProcessName::new(p))))
+    } else {
+        detector.clear()
+    };
+    let running = detector.running();
+    drop(detector);
+
+    for transition in transitions {
+        match transition {
+            Transition::Started { profile: id, pids } => {
+                let Ok(profile) = catalog.get(&id) else { continue };
+                emit(WatchEvent::GameStarted { profile: id.clone(), name: profile.name.clone(), pids });
+                if !profile.is_applied(policy::store()) {
+                    let message = transaction::run(&format!("应用方案 {}", profile.name), |tx| profile.apply(tx))
+                        .unwrap_or_else(|e| format!("写入方案失败: {}", e));
+                    emit(WatchEvent::ProfileApplied { profile: id.clone(), message });
                 }
+                let companions = config.subset_for(&profile.companions);
+                let status = apply_restrictions(ctl, &sys, &users, &companions, &settings.kinds, topology::current(), false);
+                emit(WatchEvent::CompanionsRestricted { profile: id, message: status.message });
+            }
+            Transition::Exited { profile: id } => {
+                let Ok(profile) = catalog.get(&id) else { continue };
+                let errors = release_restrictions(ctl, &sys, &users, &config.subset_for(&profile.companions));
+                let message = if errors.is_empty() { "伴随进程限制已撤销".to_string() } else { errors.join("\n") };
+                emit(WatchEvent::CompanionsReleased { profile: id.clone(), message });
+                emit(WatchEvent::GameExited { profile: id, name: profile.name.clone() });
             }
         }
     }
-    ProcessStatus {
-        target_core: cpu_count - 1, sguard64_restricted: found,
-        message: if found { "ACE 限制已生效".to_string() } else { "未发现 ACE 进程".to_string() },
+
+    // 反作弊可能晚于游戏启动或中途重启，运行期间每轮给新出现的进程施加；已施加的由漂移检测维持
+    for id in running {
+        if let Ok(profile) = catalog.get(&id) {
+            apply_restrictions(ctl, &sys, &users, &config.subset_for(&profile.companions), &settings.kinds, topology::current(), true);
+        }
+    }
+}
+
+/// 判断新进程是否值得立刻扫描用的名单：自动检测开启时看目标规则和游戏，任何时候都看持久化规则
+///
+/// 每个 exec 都要判断一次，名单定期重建，而不是每次都读配置文件。
+struct Relevance {
+    built_at: Instant,
+    /// 自动检测关闭时为 `None`
+    targets: Option<TargetConfig>,
+    /// 游戏和持久化规则的可执行文件名
+    names: Vec<String>,
+}
+
+impl Relevance {
+    const TTL: Duration = Duration::from_secs(5);
+
+    fn build() -> Self {
+        let watching = watch::watcher().settings().enabled;
+        let mut names: Vec<String> = policy::store().list().unwrap_or_default().into_iter().map(|(name, _)| name).collect();
+        if watching {
+            names.extend(profiles::catalog().profiles.iter().flat_map(|p| &p.games).map(|g| g.exe_name.clone()));
+        }
+        Self { built_at: Instant::now(), targets: watching.then(|| targets::store().config()), names }
+    }
+
+    fn is_stale(&self) -> bool {
+        self.built_at.elapsed() >= Self::TTL
+    }
+
+    /// 只凭进程名粗筛：规则另有按路径、命令行等的条件时，名字对得上才需要细查
+    fn may_match(&self, name: &str) -> bool {
+        // 被截断的名字无法比较，交给细查
+        if name.len() >= events::COMM_MAX {
+            return true;
+        }
+        let targets = self.targets.iter().flat_map(|config| &config.targets);
+        targets.into_iter().any(|t| t.matcher.name.as_ref().is_none_or(|pattern| pattern.is_match(name)))
+            || self.is_known(ProcessName { name, ..Default::default() })
+    }
+
+    /// 是否为游戏或持久化规则里的可执行文件；进程名被截断时看路径和 `argv[0]`
+    fn is_known(&self, name: ProcessName) -> bool {
+        self.names.iter().any(|n| name.is(n))
+    }
+
+    /// 新进程本身或其父进程命中时才值得立刻扫描
+    ///
+    /// 先读 `/proc` 里的进程名粗筛，可能命中时才构造 `System` 刷新这两个进程细查。
+    fn is_relevant(&self, pid: u32) -> bool {
+        let quick: Option<Vec<String>> = [Some(pid), events::parent_pid(pid)].into_iter().flatten().map(events::process_name).collect();
+        if quick.is_some_and(|names| !names.iter().any(|name| self.may_match(name))) {
+            return false;
+        }
+        let mut sys = System::new();
+        let pid = Pid::from_u32(pid);
+        if !sys.refresh_process(pid) {
+            return false;
+        }
+        let parent = sys.process(pid).and_then(|p| p.parent());
+        if let Some(parent) = parent {
+            sys.refresh_process(parent);
+        }
+        let users = Users::new();
+        [Some(pid), parent].into_iter().flatten().filter_map(|pid| sys.process(pid)).any(|process| {
+            self.targets.as_ref().is_some_and(|config| config.is_target(&ProcessInfo::new(&sys, &users, process)))
+                || self.is_known(ProcessName::new(process))
+        })
+    }
+}
+
+/// 订阅进程事件，相关进程启动时唤醒检测线程；事件源出错时退回比对 PID 列表
+fn watch_events(wake: mpsc::Sender<()>) {
+    // 事件丢失说明负载很重，全量扫描本身也不便宜，间隔内只补扫一次
+    const LOST_COOLDOWN: Duration = Duration::from_secs(5);
+    let mut source = events::open();
+    let mut relevance = Relevance::build();
+    let mut last_lost: Option<Instant> = None;
+    loop {
+        match source.next(Duration::from_secs(60)) {
+            Ok(Some(ProcessEvent::Started { pid, .. })) => {
+                if relevance.is_stale() {
+                    relevance = Relevance::build();
+                }
+                if !relevance.is_relevant(pid) {
+                    continue;
+                }
+            }
+            // 丢了事件就不知道错过了什么，全量扫描一次
+            Ok(Some(ProcessEvent::Lost)) => {
+                if last_lost.is_some_and(|at| at.elapsed() < LOST_COOLDOWN) {
+                    continue;
+                }
+                last_lost = Some(Instant::now());
+            }
+            Ok(_) => continue,
+            Err(e) => {
+                eprintln!("进程事件源 {} 出错，改为轮询: {}", source.name(), e);
+                source = Box::new(PidDiff::new(PidDiff::DEFAULT_INTERVAL));
+                continue;
+            }
+        }
+        if wake.send(()).is_err() {
+            return;
+        }
+    }
+}
+
+/// 自动检测主循环，不会返回：定期全量扫描，相关进程启动时提前唤醒
+fn watch_loop(emit: &dyn Fn(WatchEvent)) -> ! {
+    // 控制接口的订阅者与界面收到同样的事件
+    let emit = |event: WatchEvent| {
+        ipc::publish(watch::EVENT, &event);
+        emit(event);
+    };
+    let (wake, woken) = mpsc::channel();
+    std::thread::spawn(move || watch_events(wake));
+    loop {
+        watch_tick(process::controller(), &emit);
+        // 一次启动可能带来多个相关进程的事件，合并成一次扫描
+        if woken.recv_timeout(watch::watcher().settings().interval()).is_ok() {
+            while woken.try_recv().is_ok() {}
+        }
     }
 }
 
+// --- 核心命令 ---
+/// `enabled` 为前端开关对应的动作类别，省略时执行规则里的全部动作
+#[tauri::command]
+fn restrict_processes(enabled: Option<Vec<ActionKind>>) -> ProcessStatus {
+    let mut sys = System::new_all();
+    // 占用率需要两次采样才能算出，按负载选核心时要用到
+    std::thread::sleep(sysinfo::MINIMUM_CPU_UPDATE_INTERVAL);
+    sys.refresh_all();
+    let kinds = enabled.unwrap_or_else(|| ActionKind::ALL.to_vec());
+    let users = Users::new_with_refreshed_list();
+    apply_restrictions(process::controller(), &sys, &users, &targets::store().config(), &kinds, topology::current(), false)
+}
+
+/// 生成计划：`rules` 为 false 时只包含 `profile_ids` 对应游戏方案的持久化设置
+fn build_plan(kinds: &[ActionKind], rules: bool, profile_ids: &[String]) -> Result<plan::Plan, String> {
+    let ctl = process::controller();
+    let mut sys = System::new_all();
+    std::thread::sleep(sysinfo::MINIMUM_CPU_UPDATE_INTERVAL);
+    sys.refresh_all();
+    let ctx = placement_context(ctl, &sys, topology::current());
+    let processes = if rules {
+        let users = Users::new_with_refreshed_list();
+        let config = targets::store().config();
+        let assignments = config.resolve(&sys, &users, tree::lineage());
+        plan::plan_processes(ctl, &config, assignments, |pid| start_time(&sys, pid), kinds, &ctx, false)
+    } else {
+        Vec::new()
+    };
+    let catalog = profiles::catalog();
+    let selected = profile_ids.iter().map(|id| catalog.get(id)).collect::<Result<Vec<_>, _>>()?;
+    let policy = plan::plan_profiles(&selected)?;
+    Ok(plan::Plan::new(kinds, processes, policy, plan::default_cores(&ctx)))
+}
+
+/// 生成一键优化的计划但不执行；`profile_ids` 为要一并写入的游戏方案
+#[tauri::command]
+fn plan_restrictions(enabled: Option<Vec<ActionKind>>, profile_ids: Option<Vec<String>>) -> Result<plan::Plan, String> {
+    let kinds = enabled.unwrap_or_else(|| ActionKind::ALL.to_vec());
+    let plan = build_plan(&kinds, true, &profile_ids.unwrap_or_default())?;
+    Ok(plan::planner().insert(plan))
+}
+
+/// 执行之前生成的计划：先写持久化设置，失败时整体回滚且不碰进程
+#[tauri::command]
+fn apply_plan(id: u64) -> Result<ProcessStatus, String> {
+    let plan = plan::planner().take(id)?;
+    plan::execute_policy(&plan)?;
+    let mut sys = System::new();
+    sys.refresh_processes_specifics(ProcessRefreshKind::new());
+    let alive = |pid: u32| sys.process(Pid::from_u32(pid)).map(|p| p.start_time());
+    let outcome = plan::execute_processes(process::controller(), journal::journal(), &plan.processes, topology::current(), alive);
+    if plan.processes.is_empty() && !plan.policy.is_empty() {
+        let mut status = process_status(false, &[], outcome, plan.default_cores);
+        status.message = format!("持久化设置已写入\n{}", plan.summary.join("\n"));
+        return Ok(status);
+    }
+    let lines = if plan.is_empty() { &[][..] } else { &plan.summary[..] };
+    Ok(process_status(!plan.processes.is_empty(), lines, outcome, plan.default_cores))
+}
+
+#[tauri::command]
+fn reset_processes() -> Result<String, String> {
+    let mut sys = System::new_all();
+    sys.refresh_processes();
+    let users = Users::new_with_refreshed_list();
+    let errors = release_restrictions(process::controller(), &sys, &users, &targets::store().config());
+    if errors.is_empty() { Ok("ACE 限制已撤销".to_string()) } else { Err(errors.join("\n")) }
+}
+
+/// 按状态日志恢复所有改过的进程，包括上一次运行留下的
+#[tauri::command]
+fn restore_all() -> Result<String, String> {
+    let report = restore_all_processes(process::controller());
+    if report.errors.is_empty() { Ok(report.message()) } else { Err(report.message()) }
+}
+
 #[tauri::command]
 fn get_system_info() -> SystemInfo {
     let mut sys = System::new_all();
@@ -123,73 +468,156 @@ fn get_system_info() -> SystemInfo {
 fn get_process_performance() -> Vec<ProcessPerformance> {
     let mut sys = System::new_all();
     sys.refresh_processes();
+    let users = Users::new_with_refreshed_list();
+    let config = targets::store().config();
     let mut list = Vec::new();
     for (pid, process) in sys.processes() {
         let name = process.name();
-        if name.contains("SGuard") || name.contains("Delta") || name.contains("VALORANT") {
+        if config.is_monitored(&ProcessInfo::new(&sys, &users, process)) {
             list.push(ProcessPerformance { pid: pid.as_u32(), name: name.to_string(), cpu_usage: process.cpu_usage(), memory_mb: process.memory() as f64 / 1024.0 / 1024.0 });
         }
     }
     list
 }
 
+/// 单个进程各线程的 CPU 占用；首次调用只建立基线
+#[tauri::command]
+fn get_thread_usage(pid: u32) -> Result<Vec<ThreadUsage>, String> {
+    threads::monitor().sample(process::controller(), pid)
+}
+
+/// 每个被限制进程的漂移次数
+#[tauri::command]
+fn get_drift_status() -> Vec<drift::DriftStatus> {
+    drift::tracker().status()
+}
+
 #[tauri::command]
-fn lower_ace_priority() -> String {
-    let _ = set_registry_priority("SGuard64.exe", 1);
-    let _ = set_registry_priority("SGuardSvc64.exe", 1);
-    "ACE 已降权".to_string()
+fn get_watch_status() -> WatchStatus {
+    watch::watcher().status()
 }
+/// 开关自动检测；关闭后下一轮扫描会撤销正在生效的限制
 #[tauri::command]
-fn reset_ace_priority() -> String {
-    let _ = reset_registry_priority("SGuard64.exe");
-    let _ = reset_registry_priority("SGuardSvc64.exe");
-    "ACE 已恢复".to_string()
+fn configure_watcher(enabled: bool, kinds: Option<Vec<ActionKind>>) -> WatchStatus {
+    let settings = watch::watcher().settings();
+    watch::watcher().configure(WatchSettings { enabled, kinds: kinds.unwrap_or(settings.kinds), ..settings });
+    watch::watcher().status()
+}
+
+#[tauri::command]
+fn list_targets() -> TargetConfig {
+    targets::store().config()
 }
 #[tauri::command]
-fn raise_delta_priority() -> String { let _ = set_registry_priority("DeltaForceClient.exe", 3); "优化已应用".to_string() }
+/// 不带 `matcher` 时按 `name` 做进程名通配
+fn add_target(name: String, matcher: Option<ProcessMatcher>, actions: Option<ActionSet>, inherit: Option<bool>) -> Result<Vec<Target>, String> {
+    let target = match matcher {
+        Some(matcher) => Target { name, matcher, actions: ActionSet::default(), inherit: false },
+        None => Target::new(name)?,
+    };
+    let target = target.with_actions(actions.unwrap_or_default()).with_inherit(inherit.unwrap_or(false));
+    target.actions.validate(&PlacementContext::new(topology::current()))?;
+    targets::store().add(target)
+}
 #[tauri::command]
-fn reset_delta_priority() -> String { let _ = reset_registry_priority("DeltaForceClient.exe"); "已恢复".to_string() }
+fn remove_target(name: String) -> Result<Vec<Target>, String> {
+    targets::store().remove(&name)
+}
+
+/// 通过策略存储持久化 CPU 优先级（Windows 为 IFEO 注册表，其他平台为规则文件），两项要么都写入要么都不写
 #[tauri::command]
-fn modify_valorant_registry_priority() -> String { let _ = set_registry_priority("VALORANT-Win64-Shipping.exe", 3); "优化已应用".to_string() }
+fn lower_ace_priority() -> Result<String, String> {
+    transaction::run("ACE 降权", |tx| {
+        for exe_name in ACE_EXES {
+            tx.update_policy(exe_name, &|options| options.cpu_priority = Some(1))?;
+        }
+        Ok("ACE 已降权".to_string())
+    })
+}
 #[tauri::command]
-fn reset_valorant_priority() -> String { let _ = reset_registry_priority("VALORANT-Win64-Shipping.exe"); "已恢复".to_string() }
+fn reset_ace_priority() -> Result<String, String> {
+    transaction::run("ACE 恢复默认", |tx| {
+        let mut removed = false;
+        for exe_name in ACE_EXES {
+            removed |= tx.remove_policy(exe_name)?;
+        }
+        Ok(if removed { "ACE 已恢复".to_string() } else { "无需恢复".to_string() })
+    })
+}
+#[tauri::command]
+fn list_profiles() -> Vec<ProfileStatus> {
+    let store = policy::store();
+    profiles::catalog()
+        .profiles
+        .iter()
+        .map(|p| ProfileStatus { profile: p.clone(), applied: p.is_applied(store) })
+        .collect()
+}
+#[tauri::command]
+fn apply_profile(id: String) -> Result<String, String> {
+    let profile = profiles::catalog().get(&id)?;
+    transaction::run(&format!("应用方案 {}", profile.name), |tx| profile.apply(tx))
+}
+#[tauri::command]
+fn reset_profile(id: String) -> Result<String, String> {
+    let profile = profiles::catalog().get(&id)?;
+    transaction::run(&format!("撤销方案 {}", profile.name), |tx| profile.reset(tx))
+}
+
+/// 持久化修改的事务历史，最新的在前
+#[tauri::command]
+fn list_transactions() -> Vec<transaction::Record> {
+    transaction::history().records()
+}
+#[tauri::command]
+fn revert_transaction(id: u64) -> Result<String, String> {
+    let record = transaction::revert(id)?;
+    Ok(format!("已撤销 #{} {}", record.id, record.description))
+}
 
 #[tauri::command]
 fn check_registry_priority() -> String {
-    let hk_lm = RegKey::predef(HKEY_LOCAL_MACHINE);
-    let path = "SOFTWARE\\Microsoft\\Windows NT\\CurrentVersion\\Image File Execution Options\\SGuard64.exe\\PerfOptions";
-    if let Ok(key) = hk_lm.open_subkey(path) {
-        if let Ok(v) = key.get_value::<u32, _>("CpuPriorityClass") {
-            if v == 1 { return "⚠️ 已降权".to_string(); }
-        }
+    match policy::store().get("SGuard64.exe") {
+        Ok(Some(options)) if options.cpu_priority == Some(1) => "⚠️ 已降权".to_string(),
+        _ => "✅ 默认".to_string(),
     }
-    "✅ 默认".to_string()
 }
 
 #[tauri::command]
-fn exit_app() { std::process::exit(0); }
+fn exit_app() {
+    restore_all_processes(process::controller());
+    std::process::exit(0);
+}
 
+/// `backend` 为空时使用当前平台的首选方式
 #[tauri::command]
-fn enable_autostart() -> Result<String, String> {
-    let exe_path = std::env::current_exe().map_err(|e| e.to_string())?;
-    let path_str = exe_path.to_str().unwrap();
-    let _ = Command::new("schtasks").args(&["/create", "/tn", "PitayaBoxAutoStart", "/tr", path_str, "/sc", "onlogon", "/rl", "highest", "/f"]).creation_flags(0x08000000).status();
-    Ok("自启已开启".to_string())
+fn enable_autostart(backend: Option<String>) -> Result<String, String> {
+    let status = transaction::run("开启自启", |tx| autostart::Autostart::default().enable(backend.as_deref(), tx))?;
+    Ok(format!("自启已开启 ({})", status.backend))
 }
 #[tauri::command]
 fn disable_autostart() -> Result<String, String> {
-    let _ = Command::new("schtasks").args(&["/delete", "/tn", "PitayaBoxAutoStart", "/f"]).creation_flags(0x08000000).status();
-    Ok("自启已关闭".to_string())
+    match transaction::run("关闭自启", |tx| autostart::Autostart::default().disable(tx))?.as_slice() {
+        [] => Ok("自启原本就未开启".to_string()),
+        removed => Ok(format!("自启已关闭 ({})", removed.join(", "))),
+    }
 }
 #[tauri::command]
 fn check_autostart() -> bool {
-    if let Ok(out) = Command::new("schtasks").args(&["/query", "/tn", "PitayaBoxAutoStart"]).creation_flags(0x08000000).output() { return out.status.success(); }
-    false
+    autostart::Autostart::default().status().is_ok_and(|s| s.enabled)
+}
+#[tauri::command]
+fn get_autostart_status() -> Result<autostart::AutostartStatus, String> {
+    autostart::Autostart::default().status()
 }
 
 #[tauri::command]
 fn open_github() {
-    let _ = Command::new("cmd").args(&["/C", "start", "https://github.com/PitayaBox/FuckACE"]).creation_flags(0x08000000).spawn();
+    const URL: &str = "https://github.com/PitayaBox/FuckACE";
+    #[cfg(windows)]
+    let _ = hidden_command("cmd").args(["/C", "start", URL]).spawn();
+    #[cfg(not(windows))]
+    let _ = hidden_command("xdg-open").arg(URL).spawn();
 }
 
 // --- 关键修改：移除所有 Tray (托盘) 相关代码 ---
@@ -199,21 +627,57 @@ pub fn run() {
         // 必须初始化 shell 和 process 插件，否则 ACL 会报错
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_process::init()) 
-        .on_window_event(|window, event| match event {
-            WindowEvent::CloseRequested { api, .. } => {
+        .setup(|app| {
+            ipc::spawn();
+            let handle = app.handle().clone();
+            std::thread::spawn(move || watch_loop(&|event| { let _ = handle.emit(watch::EVENT, event); }));
+            Ok(())
+        })
+        .on_window_event(|window, event| {
+            if let WindowEvent::CloseRequested { .. } = event {
                 // 不再隐藏到托盘，直接关闭窗口
                 window.close().unwrap();
             }
-            _ => {}
         })
         .invoke_handler(tauri::generate_handler![
-            restrict_processes, get_system_info, get_process_performance,
-            disable_autostart, enable_autostart, check_autostart,
+            restrict_processes, plan_restrictions, apply_plan, reset_processes, restore_all, get_system_info, get_process_performance, get_thread_usage,
+            get_watch_status, configure_watcher, get_drift_status,
+            list_targets, add_target, remove_target,
+            disable_autostart, enable_autostart, check_autostart, get_autostart_status,
             lower_ace_priority, reset_ace_priority,
-            raise_delta_priority, reset_delta_priority,
-            modify_valorant_registry_priority, reset_valorant_priority,
+            list_profiles, apply_profile, reset_profile, list_transactions, revert_transaction,
             check_registry_priority, exit_app, open_github
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
-}
\ No newline at end of file
+        .build(tauri::generate_context!())
+        .expect("error while running tauri application")
+        .run(|_, event| {
+            if let RunEvent::Exit = event {
+                restore_all_processes(process::controller());
+            }
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn relevance(names: &[&str]) -> Relevance {
+        Relevance { built_at: Instant::now(), targets: None, names: names.iter().map(|n| n.to_string()).collect() }
+    }
+
+    #[test]
+    fn relevance_accepts_truncated_comm() {
+        let relevance = relevance(&["Cyberpunk2077.exe", "game.exe"]);
+        let comm = "Cyberpunk2077.e";
+        assert_eq!(comm.len(), events::COMM_MAX);
+        assert!(relevance.may_match(comm));
+        assert!(relevance.is_known(ProcessName { name: comm, ..Default::default() }));
+        let exe = std::path::Path::new("/opt/cyberpunk/Cyberpunk2077.exe");
+        assert!(relevance.is_known(ProcessName { name: comm, exe: Some(exe), argv0: None }));
+
+        assert!(relevance.may_match("GAME.EXE"));
+        assert!(!relevance.may_match("bash"));
+        let other = std::path::Path::new("/opt/cyberpunk/Cyberpunk2077.exe.old");
+        assert!(!relevance.is_known(ProcessName { name: comm, exe: Some(other), argv0: None }));
+    }
+}
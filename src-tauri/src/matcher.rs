@@ -0,0 +1,295 @@
+use std::fmt;
+use std::path::Path;
+
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use sysinfo::{Process, System, Users};
+
+use crate::process::events::COMM_MAX;
+
+type Result<T> = std::result::Result<T, String>;
+
+/// 名称或路径的匹配模式
+///
+/// 默认按不区分大小写的通配符解释（`*` 任意字符，`?` 单个字符，`/` 与 `\` 视为相同）；
+/// 以 `re:` 开头时按正则表达式解释，大小写敏感与否由表达式自身决定。
+#[derive(Clone)]
+pub struct Pattern {
+    source: String,
+    regex: Regex,
+}
+
+impl Pattern {
+    pub fn new(source: &str) -> Result<Self> {
+        let regex = match source.strip_prefix("re:") {
+            Some(expr) => Regex::new(expr).map_err(|e| format!("正则表达式无效 ({}): {}", source, e))?,
+            None => RegexBuilder::new(&glob_to_regex(source))
+                .case_insensitive(true)
+                .build()
+                .map_err(|e| format!("通配符无效 ({}): {}", source, e))?,
+        };
+        Ok(Self { source: source.to_string(), regex })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    pub fn is_match(&self, text: &str) -> bool {
+        self.regex.is_match(text)
+    }
+}
+
+fn glob_to_regex(glob: &str) -> String {
+    let mut out = String::from("^");
+    for c in glob.chars() {
+        match c {
+            '*' => out.push_str(".*"),
+            '?' => out.push('.'),
+            '/' | '\\' => out.push_str(r"[/\\]"),
+            c => out.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    out.push('$');
+    out
+}
+
+impl PartialEq for Pattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.source == other.source
+    }
+}
+
+impl Eq for Pattern {}
+
+impl fmt::Debug for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.source)
+    }
+}
+
+impl Serialize for Pattern {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.source)
+    }
+}
+
+impl<'de> Deserialize<'de> for Pattern {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let source = String::deserialize(deserializer)?;
+        Pattern::new(&source).map_err(serde::de::Error::custom)
+    }
+}
+
+/// 匹配时需要的进程信息，与 sysinfo 解耦以便脱离真实进程表使用
+#[derive(Debug, Clone, Default)]
+pub struct ProcessInfo<'a> {
+    pub name: &'a str,
+    pub exe: Option<&'a Path>,
+    pub cmdline: String,
+    pub parent_name: Option<&'a str>,
+    pub user: Option<&'a str>,
+}
+
+impl<'a> ProcessInfo<'a> {
+    pub fn new(sys: &'a System, users: &'a Users, process: &'a Process) -> Self {
+        Self {
+            name: process.name(),
+            exe: process.exe(),
+            cmdline: process.cmd().join(" "),
+            parent_name: process.parent().and_then(|pid| sys.process(pid)).map(|p| p.name()),
+            user: process.user_id().and_then(|uid| users.get_user_by_id(uid)).map(|u| u.name()),
+        }
+    }
+}
+
+/// 按可执行文件名认出进程时用到的名字
+///
+/// Linux 上的进程名是内核保存的 comm，超过 `COMM_MAX` 字节会被截断，`Cyberpunk2077.exe` 只剩
+/// `Cyberpunk2077.e`，所以还要看可执行文件路径和 `argv[0]`（Wine 下是 Windows 路径）。
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ProcessName<'a> {
+    pub name: &'a str,
+    pub exe: Option<&'a Path>,
+    pub argv0: Option<&'a str>,
+}
+
+impl<'a> ProcessName<'a> {
+    pub fn new(process: &'a Process) -> Self {
+        Self { name: process.name(), exe: process.exe(), argv0: process.cmd().first().map(String::as_str) }
+    }
+
+    /// 是否为可执行文件 `exe_name` 的进程，不区分大小写
+    pub fn is(&self, exe_name: &str) -> bool {
+        let same = |name: &str| name.eq_ignore_ascii_case(exe_name);
+        if same(self.name) {
+            return true;
+        }
+        let exe = self.exe.and_then(|path| path.file_name()).and_then(|name| name.to_str());
+        let argv0 = self.argv0.and_then(|arg| arg.rsplit(['/', '\\']).next()).filter(|name| !name.is_empty());
+        if exe.is_some_and(same) || argv0.is_some_and(same) {
+            return true;
+        }
+        // 路径和命令行都读不到（例如其他用户的进程）时，只能比较被截断的前缀
+        exe.is_none()
+            && argv0.is_none()
+            && self.name.len() == COMM_MAX
+            && exe_name.get(..COMM_MAX).is_some_and(|prefix| prefix.eq_ignore_ascii_case(self.name))
+    }
+}
+
+/// 进程匹配条件，所有已设置的条件都满足才算匹配
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProcessMatcher {
+    /// 进程名
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<Pattern>,
+    /// 可执行文件完整路径，用于区分不同目录下的同名程序
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exe: Option<Pattern>,
+    /// 命令行子串，不区分大小写
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cmdline: Option<String>,
+    /// 父进程名
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<Pattern>,
+    /// 所属用户名
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user: Option<Pattern>,
+}
+
+impl ProcessMatcher {
+    /// 只按进程名匹配
+    pub fn by_name(pattern: &str) -> Result<Self> {
+        Ok(Self { name: Some(Pattern::new(pattern)?), ..Default::default() })
+    }
+
+    /// 没有任何条件的匹配器不匹配任何进程，避免误伤整个系统
+    pub fn is_empty(&self) -> bool {
+        self.name.is_none() && self.exe.is_none() && self.cmdline.is_none() && self.parent.is_none() && self.user.is_none()
+    }
+
+    pub fn matches(&self, info: &ProcessInfo) -> bool {
+        if self.is_empty() {
+            return false;
+        }
+        if let Some(name) = &self.name {
+            if !name.is_match(info.name) { return false; }
+        }
+        if let Some(exe) = &self.exe {
+            if !info.exe.is_some_and(|p| exe.is_match(&p.to_string_lossy())) { return false; }
+        }
+        if let Some(needle) = &self.cmdline {
+            if !info.cmdline.to_lowercase().contains(&needle.to_lowercase()) { return false; }
+        }
+        if let Some(parent) = &self.parent {
+            if !info.parent_name.is_some_and(|p| parent.is_match(p)) { return false; }
+        }
+        if let Some(user) = &self.user {
+            if !info.user.is_some_and(|u| user.is_match(u)) { return false; }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn named(name: &str) -> ProcessInfo<'_> {
+        ProcessInfo { name, ..Default::default() }
+    }
+
+    #[test]
+    fn glob_is_case_insensitive_and_anchored() {
+        let pattern = Pattern::new("SGuard*.exe").unwrap();
+        assert!(pattern.is_match("SGuard64.exe"));
+        assert!(pattern.is_match("sguardsvc64.EXE"));
+        assert!(!pattern.is_match("xSGuard64.exe"));
+        assert!(!pattern.is_match("SGuard64.exe.bak"));
+
+        let single = Pattern::new("game?.exe").unwrap();
+        assert!(single.is_match("game1.exe"));
+        assert!(!single.is_match("game12.exe"));
+        // `.` 和 `+` 是字面量
+        assert!(!Pattern::new("a.b").unwrap().is_match("axb"));
+        assert!(Pattern::new("c++*").unwrap().is_match("c++filt"));
+    }
+
+    #[test]
+    fn glob_treats_slashes_alike() {
+        let pattern = Pattern::new(r"C:\Games\*\bin\*.exe").unwrap();
+        assert!(pattern.is_match("c:/games/Delta/bin/client.exe"));
+        assert!(pattern.is_match(r"C:\Games\Delta\bin\client.exe"));
+    }
+
+    #[test]
+    fn regex_prefix_uses_expression_as_is() {
+        let pattern = Pattern::new(r"re:^SGuard(64|Svc64)\.exe$").unwrap();
+        assert!(pattern.is_match("SGuard64.exe"));
+        assert!(!pattern.is_match("sguard64.exe"));
+        assert!(Pattern::new("re:(?i)^sguard").unwrap().is_match("SGuard64.exe"));
+        assert!(Pattern::new("re:(").is_err());
+        assert_eq!(Pattern::new("re:a+").unwrap().as_str(), "re:a+");
+    }
+
+    #[test]
+    fn matcher_requires_every_condition() {
+        let matcher: ProcessMatcher = toml::from_str(
+            r#"
+            name = "client*.exe"
+            exe = "*/Delta/*"
+            cmdline = "--Launcher"
+            parent = "steam.exe"
+            user = "re:^(alice|bob)$"
+            "#,
+        )
+        .unwrap();
+        let exe = Path::new("D:/Games/Delta/client-win64.exe");
+        let full = ProcessInfo {
+            name: "Client-Win64.exe",
+            exe: Some(exe),
+            cmdline: "client-win64.exe --launcher=1".to_string(),
+            parent_name: Some("Steam.exe"),
+            user: Some("alice"),
+        };
+        assert!(matcher.matches(&full));
+        assert!(!matcher.matches(&ProcessInfo { user: Some("carol"), ..full.clone() }));
+        assert!(!matcher.matches(&ProcessInfo { parent_name: None, ..full.clone() }));
+        assert!(!matcher.matches(&ProcessInfo { exe: None, ..full.clone() }));
+        assert!(!matcher.matches(&ProcessInfo { cmdline: String::new(), ..full }));
+    }
+
+    #[test]
+    fn empty_matcher_matches_nothing() {
+        let matcher = ProcessMatcher::default();
+        assert!(matcher.is_empty());
+        assert!(!matcher.matches(&named("anything")));
+        assert!(ProcessMatcher::by_name("*").unwrap().matches(&named("anything")));
+    }
+
+    #[test]
+    fn invalid_pattern_fails_to_deserialize() {
+        assert!(toml::from_str::<ProcessMatcher>(r#"name = "re:[""#).is_err());
+    }
+
+    #[test]
+    fn process_name_sees_past_truncated_comm() {
+        let exe_name = "Cyberpunk2077.exe";
+        let comm = ProcessName { name: "Cyberpunk2077.e", ..Default::default() };
+        assert!(comm.is(exe_name) && comm.is("CYBERPUNK2077.EXE"));
+        assert!(!ProcessName { name: "Cyberpunk2077", ..Default::default() }.is(exe_name));
+        assert!(ProcessName { name: "game.exe", ..Default::default() }.is("Game.EXE"));
+
+        // 能读到路径时以路径为准，截断的前缀不再作数
+        let exe = Path::new("/opt/game/Cyberpunk2077.exe");
+        assert!(ProcessName { exe: Some(exe), ..comm }.is(exe_name));
+        let other = Path::new("/opt/game/Cyberpunk2077.exe.launcher");
+        assert!(!ProcessName { exe: Some(other), ..comm }.is(exe_name));
+
+        // Wine 下 exe 是加载器，名字要从 Windows 风格的 argv[0] 里取
+        let wine = ProcessName { name: "Cyberpunk2077.e", exe: Some(Path::new("/usr/bin/wine64-preloader")), argv0: Some(r"C:\Games\Cyberpunk2077.exe") };
+        assert!(wine.is(exe_name));
+        assert!(!ProcessName { argv0: Some("/usr/bin/python3"), ..comm }.is(exe_name));
+    }
+}
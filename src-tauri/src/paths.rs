@@ -0,0 +1,93 @@
+use std::path::PathBuf;
+
+const APP_DIR: &str = "PitayaBox";
+
+/// 配置目录：Windows 为 `%APPDATA%\PitayaBox`，其余平台遵循 XDG（`~/.config/pitayabox`）
+///
+/// 设置 `PITAYABOX_CONFIG_DIR` 可以整体改写，便于测试和以守护进程方式运行。
+pub fn config_dir() -> PathBuf {
+    if let Some(dir) = std::env::var_os("PITAYABOX_CONFIG_DIR") {
+        return PathBuf::from(dir);
+    }
+    platform_config_dir()
+}
+
+#[cfg(windows)]
+fn platform_config_dir() -> PathBuf {
+    std::env::var_os("APPDATA")
+        .map(PathBuf::from)
+        .unwrap_or_else(std::env::temp_dir)
+        .join(APP_DIR)
+}
+
+#[cfg(not(windows))]
+fn platform_config_dir() -> PathBuf {
+    if let Some(dir) = std::env::var_os("XDG_CONFIG_HOME") {
+        return PathBuf::from(dir).join(APP_DIR.to_lowercase());
+    }
+    std::env::var_os("HOME")
+        .map(|home| PathBuf::from(home).join(".config"))
+        .unwrap_or_else(std::env::temp_dir)
+        .join(APP_DIR.to_lowercase())
+}
+
+/// 原子写文件：先写临时文件再改名，避免写到一半崩溃留下损坏的配置
+pub fn write_atomic(path: &std::path::Path, content: &str) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let tmp = path.with_extension("tmp");
+    std::fs::write(&tmp, content)?;
+    std::fs::rename(&tmp, path)
+}
+
+/// 跨进程的独占锁，drop 时释放
+///
+/// 界面和命令行可能同时运行，读-改-写同一个状态文件前都要先拿到它；锁在旁边的 `.lock` 文件上，
+/// 不影响 `write_atomic` 对原文件的改名。
+pub struct FileLock {
+    _file: std::fs::File,
+}
+
+/// 锁住 `path`，已被其他进程锁住时阻塞等待
+pub fn lock(path: &std::path::Path) -> std::io::Result<FileLock> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let file = std::fs::OpenOptions::new().create(true).truncate(false).write(true).open(path.with_extension("lock"))?;
+    file.lock()?;
+    Ok(FileLock { _file: file })
+}
+
+/// 测试用的空目录，每次创建都不同，drop 时连同内容一起删除
+#[cfg(test)]
+pub struct ScratchDir(PathBuf);
+
+#[cfg(test)]
+impl ScratchDir {
+    pub fn new(tag: &str) -> Self {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let name = format!("pitayabox-test-{}-{}-{}", tag, std::process::id(), NEXT.fetch_add(1, Ordering::Relaxed));
+        let dir = std::env::temp_dir().join(name);
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).expect("创建测试目录失败");
+        Self(dir)
+    }
+}
+
+#[cfg(test)]
+impl std::ops::Deref for ScratchDir {
+    type Target = std::path::Path;
+
+    fn deref(&self) -> &std::path::Path {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+impl Drop for ScratchDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
@@ -0,0 +1,509 @@
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+
+use crate::actions::{ActionKind, ActionSet, Affinity, CpuPriority, IoPriority, MemoryPriority, ThreadRule};
+use crate::cpuset::CpuSet;
+use crate::drift;
+use crate::journal::Journal;
+use crate::policy::PerfOptions;
+use crate::process::{self, ProcessController};
+use crate::profiles::Profile;
+use crate::targets::{Assignment, TargetConfig};
+use crate::topology::{PlacementContext, Topology};
+use crate::transaction::{self, Change};
+
+type Result<T> = std::result::Result<T, String>;
+
+// --- 计划内容 ---
+
+/// 对单个进程要做的一步；`from` 为生成计划时读回的当前值，读不出来时为 `None`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "action", rename_all = "kebab-case")]
+pub enum Step {
+    /// 让之后 fork 的子进程自动继承限制
+    Contain,
+    Affinity { from: Option<CpuSet>, to: CpuSet },
+    /// `idle` 为当前是否已是空闲优先级
+    Priority { idle: Option<bool>, to: CpuPriority },
+    Io { from: Option<u32>, to: IoPriority },
+    Eco { from: Option<bool>, to: bool },
+    Memory { to: MemoryPriority },
+    Trim,
+    /// 线程占用要到执行时才能算出，这里只列出阈值
+    Threads { above_percent: f32, cpus: Option<CpuSet> },
+}
+
+impl Step {
+    /// 当前值已经是目标值，执行时只是重复写一次
+    pub fn is_noop(&self) -> bool {
+        match self {
+            Step::Affinity { from, to } => from.as_ref() == Some(to),
+            Step::Priority { idle, to } => *idle == Some(*to == CpuPriority::Idle),
+            Step::Io { from, to } => *from == Some(to.hint()),
+            Step::Eco { from, to } => *from == Some(*to),
+            _ => false,
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        fn or_unknown(value: Option<String>) -> String {
+            value.unwrap_or_else(|| "未知".to_string())
+        }
+        let on_off = |on: bool| if on { "开" } else { "关" }.to_string();
+        let line = match self {
+            Step::Contain => "限制整个进程树，之后启动的子进程自动继承".to_string(),
+            Step::Affinity { from, to } => {
+                format!("CPU 亲和性 {} → {}", or_unknown(from.as_ref().map(|c| c.to_string())), to)
+            }
+            Step::Priority { idle, to } => {
+                let from = or_unknown(idle.map(|idle| if idle { "空闲" } else { "非空闲" }.to_string()));
+                let to = match to { CpuPriority::Idle => "空闲", CpuPriority::Normal => "恢复原优先级" };
+                format!("优先级 {} → {}", from, to)
+            }
+            Step::Io { from, to } => format!("I/O 优先级 {} → {}", or_unknown(from.map(io_label)), io_label(to.hint())),
+            Step::Eco { from, to } => format!("效率模式 {} → {}", or_unknown(from.map(on_off)), on_off(*to)),
+            Step::Memory { to } => format!("内存优先级 → {}", to.level()),
+            Step::Trim => "修剪工作集".to_string(),
+            Step::Threads { above_percent, cpus: None } => format!("CPU 占用超过 {}% 的线程降为空闲", above_percent),
+            Step::Threads { above_percent, cpus: Some(cpus) } => {
+                format!("CPU 占用超过 {}% 的线程降为空闲并绑定到 {}", above_percent, cpus)
+            }
+        };
+        if self.is_noop() { format!("{}（不变）", line) } else { line }
+    }
+}
+
+fn io_label(hint: u32) -> String {
+    match hint {
+        0 => "极低",
+        1 => "低",
+        _ => "正常",
+    }
+    .to_string()
+}
+
+/// 计划里的一个进程
+#[derive(Debug, Clone, Serialize)]
+pub struct ProcessPlan {
+    pub pid: u32,
+    /// 命中的规则名
+    pub name: String,
+    /// 进程启动时间（Unix 秒），执行前用来确认 PID 没有被复用
+    pub start_time: u64,
+    pub inherited: bool,
+    pub steps: Vec<Step>,
+    /// 生成计划时就知道无法执行的项，例如选不出核心
+    pub errors: Vec<String>,
+    /// 亲和性已经解析成具体核心，执行时不再按负载重新选择
+    #[serde(skip)]
+    actions: ActionSet,
+    #[serde(skip)]
+    contain: bool,
+}
+
+impl ProcessPlan {
+    pub fn describe(&self) -> String {
+        let mut parts: Vec<String> = self.steps.iter().map(Step::describe).collect();
+        parts.extend(self.errors.iter().map(|e| format!("无法执行: {}", e)));
+        if parts.is_empty() {
+            parts.push("无动作".to_string());
+        }
+        format!("{} (PID: {}): {}", self.name, self.pid, parts.join("；"))
+    }
+}
+
+/// 一次“一键优化”的完整计划：哪些进程做什么，哪些持久化设置从什么改成什么
+#[derive(Debug, Clone, Serialize)]
+pub struct Plan {
+    /// 由 `Planner` 分配，`apply_plan` 凭它执行
+    pub id: u64,
+    /// 生成时间（Unix 秒）
+    pub created_at: u64,
+    pub kinds: Vec<ActionKind>,
+    pub processes: Vec<ProcessPlan>,
+    /// 要写入的 IFEO / 规则文件项，按执行顺序
+    pub policy: Vec<Change>,
+    /// 没有规则设置亲和性时界面显示的核心
+    pub default_cores: CpuSet,
+    /// 每一项一行，供界面和命令行直接显示
+    pub summary: Vec<String>,
+    #[serde(skip)]
+    created: Option<Instant>,
+}
+
+impl Plan {
+    pub fn new(kinds: &[ActionKind], processes: Vec<ProcessPlan>, policy: Vec<Change>, default_cores: CpuSet) -> Self {
+        let mut summary: Vec<String> = processes.iter().map(ProcessPlan::describe).collect();
+        summary.extend(policy.iter().map(describe_change));
+        if summary.is_empty() {
+            summary.push("没有需要修改的项".to_string());
+        }
+        Self {
+            id: 0,
+            created_at: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0),
+            kinds: kinds.to_vec(),
+            processes,
+            policy,
+            default_cores,
+            summary,
+            created: None,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.processes.is_empty() && self.policy.is_empty()
+    }
+}
+
+/// 持久化设置的变化写成“名称: 原值 → 新值”
+fn describe_change(change: &Change) -> String {
+    fn options(value: &Option<PerfOptions>) -> String {
+        let Some(options) = value else { return "无".to_string() };
+        let fields = [("CPU", options.cpu_priority), ("I/O", options.io_priority), ("内存", options.page_priority)];
+        let set: Vec<String> = fields.iter().filter_map(|(name, v)| v.map(|v| format!("{} {}", name, v))).collect();
+        if set.is_empty() { "空".to_string() } else { set.join(", ") }
+    }
+    fn path(value: &Option<std::path::PathBuf>) -> String {
+        value.as_deref().map(Path::display).map_or_else(|| "无".to_string(), |p| p.to_string())
+    }
+    match change {
+        Change::Policy { before, after, .. } => format!("{}: {} → {}", change.describe(), options(before), options(after)),
+        Change::Autostart { before, after, .. } => format!("{}: {} → {}", change.describe(), path(before), path(after)),
+    }
+}
+
+// --- 生成 ---
+
+/// 算出按当前规则会对哪些进程做什么，不修改任何东西；`only_new` 时跳过已在漂移记录中的进程
+pub fn plan_processes(
+    ctl: &dyn ProcessController,
+    targets: &TargetConfig,
+    assignments: Vec<Assignment>,
+    start_time: impl Fn(u32) -> u64,
+    kinds: &[ActionKind],
+    ctx: &PlacementContext,
+    only_new: bool,
+) -> Vec<ProcessPlan> {
+    let mut plans = Vec::new();
+    for Assignment { pid, target, inherited } in assignments {
+        let target = &targets.targets[target];
+        if only_new && drift::tracker().is_tracked(pid, start_time(pid)) {
+            continue;
+        }
+        let mut actions = target.actions.filtered(kinds);
+        if !ctl.supports_memory_priority() {
+            actions.memory = None;
+        }
+        let mut steps = Vec::new();
+        let mut errors = Vec::new();
+        // 选中的类别里没有这条规则的动作时，子进程也没什么可继承的
+        let contain = target.inherit && !inherited && !actions.is_empty();
+        if contain {
+            steps.push(Step::Contain);
+        }
+
+        // 按负载选核心的结果每次都可能不同，计划里固定下来，执行的就是预览看到的
+        if let Some(affinity) = actions.affinity.take() {
+            match affinity.select(ctx) {
+                Ok(cpus) => {
+                    steps.push(Step::Affinity { from: ctl.cpu_affinity(pid).ok(), to: cpus.clone() });
+                    actions.affinity = Some(Affinity::List(cpus));
+                }
+                Err(e) => errors.push(e),
+            }
+        }
+        if let Some(to) = actions.priority {
+            steps.push(Step::Priority { idle: ctl.is_idle_priority(pid).ok(), to });
+        }
+        if let Some(to) = actions.io {
+            steps.push(Step::Io { from: ctl.io_priority(pid).ok(), to });
+        }
+        if let Some(to) = actions.eco {
+            steps.push(Step::Eco { from: ctl.efficiency_mode(pid).ok(), to });
+        }
+        if let Some(to) = actions.memory {
+            steps.push(Step::Memory { to });
+        }
+        if actions.trim {
+            steps.push(Step::Trim);
+        }
+        if let Some(rule) = actions.threads.take() {
+            match rule.affinity.as_ref().map(|a| a.select(ctx)).transpose() {
+                Ok(cpus) => {
+                    steps.push(Step::Threads { above_percent: rule.above_percent, cpus: cpus.clone() });
+                    actions.threads = Some(ThreadRule { above_percent: rule.above_percent, affinity: cpus.map(Affinity::List) });
+                }
+                Err(e) => errors.push(e),
+            }
+        }
+        plans.push(ProcessPlan {
+            pid,
+            name: target.name.clone(),
+            start_time: start_time(pid),
+            inherited,
+            steps,
+            errors,
+            actions,
+            contain,
+        });
+    }
+    plans
+}
+
+/// 演算写入这些游戏方案会改动的持久化设置
+pub fn plan_profiles(profiles: &[&Profile]) -> Result<Vec<Change>> {
+    transaction::preview(|tx| {
+        for profile in profiles {
+            profile.apply(tx)?;
+        }
+        Ok(())
+    })
+}
+
+// --- 执行 ---
+
+/// 按计划执行进程部分的结果
+#[derive(Debug, Default)]
+pub struct Outcome {
+    pub reclaimed_bytes: u64,
+    pub demoted_threads: usize,
+    /// 成功设置的亲和性的并集
+    pub cpus: CpuSet,
+    pub errors: Vec<String>,
+}
+
+/// 严格按计划施加，修改前把原始状态记进 `journal`；`start_time` 返回进程当前的启动时间，已退出或 PID 被复用的进程跳过
+pub fn execute_processes(
+    ctl: &dyn ProcessController,
+    journal: &Journal,
+    processes: &[ProcessPlan],
+    topology: &Topology,
+    start_time: impl Fn(u32) -> Option<u64>,
+) -> Outcome {
+    // 亲和性都已解析成具体核心，这里的上下文只用来核对核心是否存在
+    let ctx = PlacementContext::new(topology);
+    let mut outcome = Outcome::default();
+    for plan in processes {
+        let label = format!("{} (PID: {})", plan.name, plan.pid);
+        outcome.errors.extend(plan.errors.iter().map(|e| format!("{}: {}", label, e)));
+        if start_time(plan.pid) != Some(plan.start_time) {
+            outcome.errors.push(format!("{}: 进程已退出或 PID 已被复用，跳过", label));
+            continue;
+        }
+        // 先记下原始状态再修改；记录失败就不碰这个进程，免得留下无法撤销的修改
+        if let Err(e) = journal.capture(ctl, plan.pid, &plan.name, plan.start_time) {
+            outcome.errors.push(format!("{}: 记录原始状态失败: {}", label, e));
+            continue;
+        }
+        if plan.contain {
+            // 平台支持时让之后 fork 的子进程自动继承；已有的后代在计划里各有一项
+            if let Err(e) = ctl.contain_tree(plan.pid) { outcome.errors.push(format!("{}: {}", label, e)); }
+        }
+        let report = plan.actions.apply(ctl, plan.pid, &ctx);
+        drift::tracker().record(ctl, plan.pid, plan.start_time, &plan.name, &plan.actions);
+        outcome.reclaimed_bytes += report.reclaimed_bytes;
+        outcome.demoted_threads += report.demoted_threads;
+        if let Some(cpus) = &report.cpus { outcome.cpus = outcome.cpus.union(cpus); }
+        outcome.errors.extend(report.errors.into_iter().map(|e| format!("{}: {}", label, e)));
+    }
+    outcome
+}
+
+/// 在一个事务中写入计划里的持久化设置；任何一项的当前值与计划不同都会整体回滚
+pub fn execute_policy(plan: &Plan) -> Result<()> {
+    if plan.policy.is_empty() {
+        return Ok(());
+    }
+    transaction::run(&format!("执行计划 #{}", plan.id), |tx| {
+        for change in &plan.policy {
+            tx.replay(change)?;
+        }
+        Ok(())
+    })
+}
+
+/// 没有规则设置亲和性时显示的核心
+pub fn default_cores(ctx: &PlacementContext) -> CpuSet {
+    process::find_target_core(ctx).1
+}
+
+// --- 暂存 ---
+
+/// 保存最近生成的计划，等用户确认后按 id 执行
+#[derive(Default)]
+pub struct Planner {
+    plans: Mutex<Vec<Plan>>,
+    next_id: Mutex<u64>,
+}
+
+impl Planner {
+    /// 超过这个时间的计划里的进程状态已不可信
+    pub const TTL: Duration = Duration::from_secs(10 * 60);
+    /// 同时保留的计划数，多出的丢掉最旧的
+    pub const KEEP: usize = 8;
+
+    /// 分配 id 后保存，返回保存的副本
+    pub fn insert(&self, mut plan: Plan) -> Plan {
+        let mut next_id = self.next_id.lock().unwrap();
+        *next_id += 1;
+        plan.id = *next_id;
+        plan.created = Some(Instant::now());
+        let mut plans = self.plans.lock().unwrap();
+        plans.retain(|p| p.created.is_some_and(|t| t.elapsed() < Self::TTL));
+        if plans.len() >= Self::KEEP {
+            plans.remove(0);
+        }
+        plans.push(plan.clone());
+        plan
+    }
+
+    /// 取出计划；每个计划只能执行一次
+    pub fn take(&self, id: u64) -> Result<Plan> {
+        let mut plans = self.plans.lock().unwrap();
+        let index = plans.iter().position(|p| p.id == id).ok_or_else(|| format!("计划 #{} 不存在或已执行", id))?;
+        let plan = plans.remove(index);
+        if plan.created.is_some_and(|t| t.elapsed() >= Self::TTL) {
+            return Err(format!("计划 #{} 已过期，请重新生成", id));
+        }
+        Ok(plan)
+    }
+}
+
+/// 进程内共享的计划暂存
+pub fn planner() -> &'static Planner {
+    static PLANNER: OnceLock<Planner> = OnceLock::new();
+    PLANNER.get_or_init(Planner::default)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::paths;
+    use crate::process::mock::{Call, MockController};
+    use crate::targets::Target;
+
+    const START: u64 = 1000;
+
+    fn config(inherit: bool, actions: ActionSet) -> TargetConfig {
+        let target = Target::new("game.exe").unwrap().with_actions(actions).with_inherit(inherit);
+        TargetConfig { targets: vec![target], monitor: Vec::new(), ..TargetConfig::default() }
+    }
+
+    fn assign(pid: u32, inherited: bool) -> Assignment {
+        Assignment { pid, target: 0, inherited }
+    }
+
+    fn restrict() -> ActionSet {
+        ActionSet {
+            affinity: Some(Affinity::Last(1)),
+            priority: Some(CpuPriority::Idle),
+            io: Some(IoPriority::Low),
+            ..Default::default()
+        }
+    }
+
+    fn plan(ctl: &MockController, config: &TargetConfig, assignments: Vec<Assignment>, kinds: &[ActionKind]) -> Vec<ProcessPlan> {
+        let topology = Topology::flat(4);
+        plan_processes(ctl, config, assignments, |_| START, kinds, &PlacementContext::new(&topology), false)
+    }
+
+    fn cpus(ids: &[usize]) -> CpuSet {
+        ids.iter().copied().collect()
+    }
+
+    #[test]
+    fn plan_reads_current_state_without_changing_it() {
+        let ctl = MockController::new();
+        for pid in [1, 2] {
+            ctl.set_cpu_affinity(pid, &cpus(&[0, 1, 2, 3])).unwrap();
+        }
+        ctl.set_idle_priority(2).unwrap();
+        let calls = ctl.calls();
+        let plans = plan(&ctl, &config(true, restrict()), vec![assign(1, false), assign(2, true)], &ActionKind::ALL);
+        assert_eq!(ctl.calls(), calls);
+
+        assert_eq!(plans.len(), 2);
+        assert_eq!(
+            plans[0].steps,
+            vec![
+                Step::Contain,
+                Step::Affinity { from: Some(cpus(&[0, 1, 2, 3])), to: cpus(&[3]) },
+                Step::Priority { idle: Some(false), to: CpuPriority::Idle },
+                Step::Io { from: Some(2), to: IoPriority::Low },
+            ]
+        );
+        assert_eq!(plans[0].start_time, START);
+        // 继承来的进程由根进程的容器覆盖，不再单独收拢
+        assert_eq!(plans[1].steps.first(), Some(&Step::Affinity { from: Some(cpus(&[0, 1, 2, 3])), to: cpus(&[3]) }));
+        assert!(plans[1].steps[1].is_noop());
+        assert!(plans[1].describe().contains("（不变）"));
+    }
+
+    #[test]
+    fn plan_honours_kinds_and_reports_errors() {
+        let ctl = MockController::new();
+        let plans = plan(&ctl, &config(true, restrict()), vec![assign(3, false)], &[ActionKind::Io]);
+        assert_eq!(plans[0].steps, vec![Step::Contain, Step::Io { from: Some(2), to: IoPriority::Low }]);
+        // 选中的类别里没有动作时不收拢进程树
+        let plans = plan(&ctl, &config(true, restrict()), vec![assign(3, false)], &[ActionKind::Memory]);
+        assert!(plans[0].steps.is_empty());
+
+        let actions = ActionSet { affinity: Some(Affinity::Ccd(0)), eco: Some(true), ..Default::default() };
+        let plans = plan(&ctl, &config(false, actions), vec![assign(4, false)], &ActionKind::ALL);
+        assert_eq!(plans[0].errors.len(), 1);
+        assert_eq!(plans[0].steps, vec![Step::Eco { from: Some(false), to: true }]);
+    }
+
+    #[test]
+    fn execute_applies_plan_and_journals_original_state() {
+        let ctl = MockController::new();
+        let dir = paths::ScratchDir::new("plan");
+        let journal = Journal::open(dir.join(Journal::FILE_NAME));
+        let pid = 90_001;
+        ctl.set_cpu_affinity(pid, &cpus(&[0, 1, 2, 3])).unwrap();
+        let plans = plan(&ctl, &config(true, restrict()), vec![assign(pid, false)], &ActionKind::ALL);
+
+        let outcome = execute_processes(&ctl, &journal, &plans, &Topology::flat(4), |_| Some(START));
+        assert!(outcome.errors.is_empty(), "{:?}", outcome.errors);
+        assert_eq!(outcome.cpus, cpus(&[3]));
+        assert_eq!(
+            ctl.calls()[1..],
+            [
+                Call::ContainTree { pid },
+                Call::CpuAffinity { pid, cpus: cpus(&[3]) },
+                Call::IdlePriority { pid },
+                Call::IoPriority { pid, priority: 1 },
+            ]
+        );
+        assert!(drift::tracker().is_tracked(pid, START));
+        let topology = Topology::flat(4);
+        let again = plan_processes(&ctl, &config(true, restrict()), vec![assign(pid, false)], |_| START, &ActionKind::ALL, &PlacementContext::new(&topology), true);
+        assert!(again.is_empty());
+
+        let entries = journal.entries();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].snapshot.cpus, Some(cpus(&[0, 1, 2, 3])));
+        let report = journal.restore_all(&ctl, |_| Some(START));
+        assert_eq!(report.restored, vec![pid]);
+        assert_eq!(ctl.cpu_affinity(pid).unwrap(), cpus(&[0, 1, 2, 3]));
+        assert!(!ctl.is_idle_priority(pid).unwrap());
+        assert_eq!(ctl.io_priority(pid).unwrap(), 2);
+        drift::tracker().forget(pid);
+    }
+
+    #[test]
+    fn execute_skips_exited_or_reused_pids() {
+        let ctl = MockController::new();
+        let dir = paths::ScratchDir::new("plan");
+        let journal = Journal::open(dir.join(Journal::FILE_NAME));
+        let plans = plan(&ctl, &config(false, restrict()), vec![assign(90_002, false), assign(90_003, false)], &ActionKind::ALL);
+        let calls = ctl.calls();
+        let outcome = execute_processes(&ctl, &journal, &plans, &Topology::flat(4), |pid| (pid == 90_002).then_some(START + 1));
+        assert_eq!(outcome.errors.len(), 2);
+        assert_eq!(ctl.calls(), calls);
+        assert!(journal.entries().is_empty());
+    }
+}
@@ -0,0 +1,315 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+use serde::{Deserialize, Serialize};
+
+use crate::matcher::ProcessName;
+use crate::paths;
+use crate::process::ProcessController;
+
+type Result<T> = std::result::Result<T, String>;
+
+/// 针对某个可执行文件持久化的优先级设置，字段含义与 IFEO `PerfOptions` 相同
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PerfOptions {
+    /// CpuPriorityClass：1 空闲，5 低于正常，2 正常，6 高于正常，3 高
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpu_priority: Option<u32>,
+    /// IoPriority：0 极低，1 低，2 正常
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub io_priority: Option<u32>,
+    /// PagePriority：1 最低 ~ 5 正常
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page_priority: Option<u32>,
+}
+
+impl PerfOptions {
+    pub fn is_empty(&self) -> bool {
+        self.cpu_priority.is_none() && self.io_priority.is_none() && self.page_priority.is_none()
+    }
+}
+
+/// “进程启动时就生效”的持久化规则存储
+///
+/// Windows 上由系统根据 IFEO 注册表在进程创建时应用；其他平台写入文件，由守护进程在发现新进程时执行。
+pub trait PolicyStore: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    fn get(&self, exe_name: &str) -> Result<Option<PerfOptions>>;
+
+    /// 整体替换该可执行文件的设置；`None` 字段会被删除
+    fn set(&self, exe_name: &str, options: &PerfOptions) -> Result<()>;
+
+    /// 删除该可执行文件的全部设置，返回之前是否存在
+    fn remove(&self, exe_name: &str) -> Result<bool>;
+
+    fn list(&self) -> Result<Vec<(String, PerfOptions)>>;
+}
+
+// --- 文件存储 ---
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct PolicyFile {
+    #[serde(default = "PolicyFile::current_version")]
+    version: u32,
+    #[serde(default)]
+    rules: BTreeMap<String, PerfOptions>,
+}
+
+impl PolicyFile {
+    const VERSION: u32 = 1;
+
+    fn current_version() -> u32 {
+        Self::VERSION
+    }
+}
+
+/// TOML 文件形式的规则存储，可执行文件名按不区分大小写比较
+pub struct FilePolicyStore {
+    path: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl FilePolicyStore {
+    pub const FILE_NAME: &'static str = "policy.toml";
+
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into(), lock: Mutex::new(()) }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn load(&self) -> Result<PolicyFile> {
+        match fs::read_to_string(&self.path) {
+            Ok(content) => {
+                let file: PolicyFile = toml::from_str(&content)
+                    .map_err(|e| format!("规则文件格式错误 ({}): {}", self.path.display(), e))?;
+                if file.version > PolicyFile::VERSION {
+                    return Err(format!("规则文件版本 {} 过新，当前仅支持 {}", file.version, PolicyFile::VERSION));
+                }
+                Ok(file)
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(PolicyFile::default()),
+            Err(e) => Err(format!("读取规则文件失败 ({}): {}", self.path.display(), e)),
+        }
+    }
+
+    fn save(&self, mut file: PolicyFile) -> Result<()> {
+        file.version = PolicyFile::VERSION;
+        let content = toml::to_string_pretty(&file).map_err(|e| format!("序列化规则失败: {}", e))?;
+        paths::write_atomic(&self.path, &content).map_err(|e| format!("写入规则文件失败 ({}): {}", self.path.display(), e))
+    }
+}
+
+fn find_key<'a>(rules: &'a BTreeMap<String, PerfOptions>, exe_name: &str) -> Option<&'a String> {
+    rules.keys().find(|k| k.eq_ignore_ascii_case(exe_name))
+}
+
+impl PolicyStore for FilePolicyStore {
+    fn name(&self) -> &'static str {
+        "file"
+    }
+
+    fn get(&self, exe_name: &str) -> Result<Option<PerfOptions>> {
+        let _guard = self.lock.lock().unwrap();
+        let file = self.load()?;
+        Ok(find_key(&file.rules, exe_name).map(|k| file.rules[k]))
+    }
+
+    fn set(&self, exe_name: &str, options: &PerfOptions) -> Result<()> {
+        let _guard = self.lock.lock().unwrap();
+        let mut file = self.load()?;
+        let key = find_key(&file.rules, exe_name).cloned().unwrap_or_else(|| exe_name.to_string());
+        file.rules.insert(key, *options);
+        self.save(file)
+    }
+
+    fn remove(&self, exe_name: &str) -> Result<bool> {
+        let _guard = self.lock.lock().unwrap();
+        let mut file = self.load()?;
+        let Some(key) = find_key(&file.rules, exe_name).cloned() else {
+            return Ok(false);
+        };
+        file.rules.remove(&key);
+        self.save(file)?;
+        Ok(true)
+    }
+
+    fn list(&self) -> Result<Vec<(String, PerfOptions)>> {
+        let _guard = self.lock.lock().unwrap();
+        Ok(self.load()?.rules.into_iter().collect())
+    }
+}
+
+// --- 运行时执行 ---
+
+/// 把持久化规则施加到一个已经启动的进程上（非 Windows 平台没有 IFEO，由守护进程调用）
+pub fn enforce(store: &dyn PolicyStore, ctl: &dyn ProcessController, pid: u32, exe_name: &str) -> Result<bool> {
+    let Some(options) = store.get(exe_name)? else {
+        return Ok(false);
+    };
+    apply_options(ctl, pid, &options).map(|()| true)
+}
+
+fn apply_options(ctl: &dyn ProcessController, pid: u32, options: &PerfOptions) -> Result<()> {
+    let mut errors = Vec::new();
+    // 只有“空闲”能直接映射到后端操作，其余优先级类交给系统默认
+    if options.cpu_priority == Some(1) {
+        if let Err(e) = ctl.set_idle_priority(pid) { errors.push(e); }
+    }
+    if let Some(io) = options.io_priority {
+        if let Err(e) = ctl.set_io_priority(pid, io) { errors.push(e); }
+    }
+    if let Some(page) = options.page_priority.filter(|_| ctl.supports_memory_priority()) {
+        if let Err(e) = ctl.set_memory_priority(pid, page) { errors.push(e); }
+    }
+    if errors.is_empty() { Ok(()) } else { Err(errors.join("\n")) }
+}
+
+/// 在没有 IFEO 的平台上模拟“进程启动时生效”：每轮扫描给新出现的、命中规则的进程施加一次
+///
+/// 和 IFEO 一样只在进程启动时生效，之后修改规则不影响已经处理过的进程。
+#[derive(Default)]
+pub struct Enforcer {
+    /// 已处理的 PID 与其启动时间，用来识别 PID 被复用
+    applied: Mutex<HashMap<u32, u64>>,
+}
+
+impl Enforcer {
+    /// `processes` 为当前全部进程的（PID、进程名、启动时间），返回失败原因
+    pub fn enforce_new<'a>(
+        &self,
+        store: &dyn PolicyStore,
+        ctl: &dyn ProcessController,
+        processes: impl IntoIterator<Item = (u32, ProcessName<'a>, u64)>,
+    ) -> Vec<String> {
+        let rules = match store.list() {
+            Ok(rules) => rules,
+            Err(e) => return vec![e],
+        };
+        let processes: Vec<(u32, ProcessName, u64)> = processes.into_iter().collect();
+        let mut applied = self.applied.lock().unwrap();
+        applied.retain(|pid, start| processes.iter().any(|(p, _, s)| p == pid && s == start));
+        let mut errors = Vec::new();
+        for (pid, name, start_time) in processes {
+            let Some((_, options)) = rules.iter().find(|(exe, _)| name.is(exe)) else { continue };
+            if applied.get(&pid) == Some(&start_time) {
+                continue;
+            }
+            // 失败也记为已处理，免得每轮重复报同一个错误
+            applied.insert(pid, start_time);
+            if let Err(e) = apply_options(ctl, pid, options) {
+                errors.push(format!("{} (PID: {}): {}", name.name, pid, e));
+            }
+        }
+        errors
+    }
+}
+
+/// 进程内共享的规则执行记录
+pub fn enforcer() -> &'static Enforcer {
+    static ENFORCER: OnceLock<Enforcer> = OnceLock::new();
+    ENFORCER.get_or_init(Enforcer::default)
+}
+
+// --- 默认存储 ---
+
+/// 当前平台的默认存储：Windows 用注册表，其他平台用配置目录下的 `policy.toml`
+pub fn default_store() -> Box<dyn PolicyStore> {
+    if let Some(path) = std::env::var_os("PITAYABOX_POLICY_FILE") {
+        return Box::new(FilePolicyStore::new(path));
+    }
+    #[cfg(windows)]
+    {
+        Box::new(crate::registry::RegistryStore)
+    }
+    #[cfg(not(windows))]
+    {
+        Box::new(FilePolicyStore::new(paths::config_dir().join(FilePolicyStore::FILE_NAME)))
+    }
+}
+
+pub fn store() -> &'static dyn PolicyStore {
+    static STORE: OnceLock<Box<dyn PolicyStore>> = OnceLock::new();
+    STORE.get_or_init(default_store).as_ref()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process::mock::{Call, MockController};
+
+    fn comm(name: &str) -> ProcessName<'_> {
+        ProcessName { name, ..Default::default() }
+    }
+
+    fn idle() -> PerfOptions {
+        PerfOptions { cpu_priority: Some(1), io_priority: Some(0), page_priority: None }
+    }
+
+    #[test]
+    fn file_store_round_trips_rules() {
+        let dir = paths::ScratchDir::new("policy");
+        let store = FilePolicyStore::new(dir.join(FilePolicyStore::FILE_NAME));
+        assert_eq!(store.get("game.exe").unwrap(), None);
+        assert!(store.list().unwrap().is_empty());
+
+        store.set("Game.exe", &idle()).unwrap();
+        assert_eq!(store.get("GAME.EXE").unwrap(), Some(idle()));
+        // 大小写不同也是同一条规则，保留原来的写法
+        let low = PerfOptions { io_priority: Some(1), ..Default::default() };
+        store.set("game.exe", &low).unwrap();
+        store.set("other.exe", &idle()).unwrap();
+        assert_eq!(store.list().unwrap(), vec![("Game.exe".to_string(), low), ("other.exe".to_string(), idle())]);
+
+        assert!(store.remove("GAME.exe").unwrap());
+        assert!(!store.remove("game.exe").unwrap());
+        assert_eq!(FilePolicyStore::new(store.path()).list().unwrap(), vec![("other.exe".to_string(), idle())]);
+    }
+
+    #[test]
+    fn file_store_rejects_newer_version() {
+        let dir = paths::ScratchDir::new("policy-version");
+        let path = dir.join(FilePolicyStore::FILE_NAME);
+        fs::write(&path, "version = 99\n").unwrap();
+        assert!(FilePolicyStore::new(&path).get("game.exe").is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "version = 99\n");
+    }
+
+    #[test]
+    fn enforcer_applies_once_per_process_instance() {
+        let dir = paths::ScratchDir::new("policy-enforce");
+        let store = FilePolicyStore::new(dir.join(FilePolicyStore::FILE_NAME));
+        store.set("Cyberpunk2077.exe", &idle()).unwrap();
+        let ctl = MockController::new();
+        let enforcer = Enforcer::default();
+
+        let processes = [(10, comm("Cyberpunk2077.e"), 100), (11, comm("bash"), 100)];
+        assert!(enforcer.enforce_new(&store, &ctl, processes).is_empty());
+        assert_eq!(ctl.calls(), vec![Call::IdlePriority { pid: 10 }, Call::IoPriority { pid: 10, priority: 0 }]);
+        assert!(enforcer.enforce_new(&store, &ctl, processes).is_empty());
+        assert_eq!(ctl.calls().len(), 2);
+
+        // 同一个 PID 换了启动时间，说明是被复用的新进程
+        enforcer.enforce_new(&store, &ctl, [(10, comm("Cyberpunk2077.e"), 200)]);
+        assert_eq!(ctl.calls().len(), 4);
+    }
+
+    #[test]
+    fn enforcer_reports_failures_once() {
+        let dir = paths::ScratchDir::new("policy-fail");
+        let store = FilePolicyStore::new(dir.join(FilePolicyStore::FILE_NAME));
+        store.set("game.exe", &idle()).unwrap();
+        let ctl = MockController::new();
+        ctl.fail_pid(20);
+        let enforcer = Enforcer::default();
+        let errors = enforcer.enforce_new(&store, &ctl, [(20, comm("game.exe"), 1)]);
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].starts_with("game.exe (PID: 20)"));
+        assert!(enforcer.enforce_new(&store, &ctl, [(20, comm("game.exe"), 1)]).is_empty());
+    }
+}
@@ -1,119 +1,185 @@
-use sysinfo::{Pid, System};
-use windows::Win32::Foundation::{CloseHandle, HANDLE};
-use windows::Win32::System::Threading::{
-    OpenProcess, SetPriorityClass, SetProcessAffinityMask, SetProcessInformation,
-    IDLE_PRIORITY_CLASS, PROCESS_INFORMATION_CLASS, PROCESS_POWER_THROTTLING_EXECUTION_SPEED,
-    PROCESS_POWER_THROTTLING_IGNORE_TIMER_RESOLUTION, PROCESS_POWER_THROTTLING_STATE,
-    PROCESS_QUERY_INFORMATION, PROCESS_SET_INFORMATION, ProcessPowerThrottling,
-};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::cpuset::CpuSet;
+use crate::topology::PlacementContext;
+
+#[cfg(windows)]
+mod windows;
+#[cfg(target_os = "linux")]
+mod linux;
+#[cfg(target_os = "linux")]
+pub mod cgroup;
+pub mod events;
+pub mod mock;
+pub mod threads;
+pub mod trim;
+
+#[cfg(windows)]
+pub use self::windows::WindowsController;
+#[cfg(target_os = "linux")]
+pub use self::linux::LinuxController;
+pub use self::mock::MockController;
 
 // 定义一个简单的 Result 类型别名，方便错误处理
-type Result<T> = std::result::Result<T, String>;
+pub type Result<T> = std::result::Result<T, String>;
+
+/// 某个线程自创建以来消耗的 CPU 时间（用户态 + 内核态）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ThreadTimes {
+    pub tid: u32,
+    pub cpu_time: Duration,
+}
 
-/// RAII 包装器：确保 Handle 总是被关闭
-struct ScopedHandle(HANDLE);
+/// 修改前的 CPU 优先级，按平台原生的表示保存
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum SavedPriority {
+    /// Windows 优先级类，例如 NORMAL_PRIORITY_CLASS (0x20)
+    Class { class: u32 },
+    /// Linux 主线程的调度策略、实时优先级与 nice 值
+    Sched { policy: i32, rt_priority: i32, nice: i32 },
+}
 
-impl Drop for ScopedHandle {
-    fn drop(&mut self) {
-        if !self.0.is_invalid() {
-            unsafe { let _ = CloseHandle(self.0); }
-        }
-    }
+/// 进程被修改前的状态，由 `journal` 持久化；读不到或平台没有的项为 `None`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProcessSnapshot {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpus: Option<CpuSet>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<SavedPriority>,
+    /// 取值同 `set_io_priority`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub io_priority: Option<u32>,
+    /// Windows 的执行速度节流；Linux 的效率模式由 `cgroup` 表示
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub efficiency_mode: Option<bool>,
+    /// 所在 cgroup（相对 cgroup 根目录），仅 Linux
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cgroup: Option<String>,
 }
 
-impl ScopedHandle {
-    fn open(pid: u32) -> Result<Self> {
-        unsafe {
-            let handle = OpenProcess(
-                PROCESS_SET_INFORMATION | PROCESS_QUERY_INFORMATION,
-                false,
-                pid,
-            ).map_err(|e| format!("无法打开进程 (PID: {}): {}", pid, e))?;
-
-            if handle.is_invalid() {
-                return Err("进程句柄无效".to_string());
-            }
-            Ok(Self(handle))
-        }
+/// 对单个进程施加限制的平台无关接口
+///
+/// 每个平台提供一个实现；`MockController` 只记录调用，用于在任意机器上跑通限制逻辑。
+pub trait ProcessController: Send + Sync {
+    /// 后端名称，用于日志
+    fn name(&self) -> &'static str;
+
+    fn set_cpu_affinity(&self, pid: u32, cpus: &CpuSet) -> Result<()>;
+
+    /// 进程当前允许运行的核心
+    fn cpu_affinity(&self, pid: u32) -> Result<CpuSet>;
+
+    fn set_idle_priority(&self, pid: u32) -> Result<()>;
+
+    /// 把优先级恢复成第一次调用 `set_idle_priority` 之前的状态；没有记录时什么也不做
+    fn restore_priority(&self, pid: u32) -> Result<()>;
+
+    fn set_efficiency_mode(&self, pid: u32) -> Result<()>;
+
+    /// 撤销 `set_efficiency_mode`，交还给系统默认调度
+    fn clear_efficiency_mode(&self, pid: u32) -> Result<()>;
+
+    /// `priority` 沿用 Windows 的 IO_PRIORITY_HINT：0 = 极低，1 = 低，2 = 正常
+    fn set_io_priority(&self, pid: u32, priority: u32) -> Result<()>;
+
+    fn set_memory_priority(&self, pid: u32, priority: u32) -> Result<()>;
+
+    /// 平台没有内存优先级时返回 false，调用方直接跳过这一项而不是当作失败
+    fn supports_memory_priority(&self) -> bool {
+        true
     }
-    
-    // 获取原始 Handle 用于 API 调用
-    fn raw(&self) -> HANDLE {
-        self.0
+
+    /// 当前常驻内存（字节）
+    fn resident_bytes(&self, pid: u32) -> Result<u64>;
+
+    /// 把进程的常驻内存尽量换出；是否值得修剪由 `trim::Trimmer` 决定
+    fn trim_working_set(&self, pid: u32) -> Result<()>;
+
+    /// 把进程放进一个会被后代自动继承的受限容器；返回 false 表示平台不支持，
+    /// 后代只能靠每次枚举时逐个处理
+    fn contain_tree(&self, _pid: u32) -> Result<bool> {
+        Ok(false)
     }
+
+    // --- 读回当前状态，用于发现设置被目标进程自己改掉 ---
+
+    /// 进程（Linux 上为主线程）是否处于空闲优先级
+    fn is_idle_priority(&self, pid: u32) -> Result<bool>;
+
+    /// 当前 I/O 优先级，取值同 `set_io_priority`
+    fn io_priority(&self, pid: u32) -> Result<u32>;
+
+    /// 效率模式是否仍在生效
+    fn efficiency_mode(&self, pid: u32) -> Result<bool>;
+
+    // --- 状态快照 ---
+
+    /// 读出 `restore_snapshot` 需要的全部状态；进程不存在时返回错误
+    fn snapshot(&self, pid: u32) -> Result<ProcessSnapshot>;
+
+    /// 把进程恢复成快照时的样子，同时丢弃后端自己为该进程保存的恢复记录
+    fn restore_snapshot(&self, pid: u32, snapshot: &ProcessSnapshot) -> Result<()>;
+
+    // --- 线程级操作 ---
+
+    /// 列出进程的所有线程及其累计 CPU 时间
+    fn thread_times(&self, pid: u32) -> Result<Vec<ThreadTimes>>;
+
+    /// 只把一个线程降到空闲优先级；第一次修改前的状态会被记录
+    fn set_thread_idle(&self, pid: u32, tid: u32) -> Result<()>;
+
+    /// 撤销 `set_thread_idle`；没有记录时什么也不做
+    fn restore_thread(&self, pid: u32, tid: u32) -> Result<()>;
+
+    fn set_thread_affinity(&self, pid: u32, tid: u32, cpus: &CpuSet) -> Result<()>;
 }
 
-// --- 核心功能封装 ---
+// --- 后端选择 ---
 
-pub fn set_cpu_affinity(pid: u32, core_mask: u64) -> Result<()> {
-    let handle = ScopedHandle::open(pid)?;
-    unsafe {
-        SetProcessAffinityMask(handle.raw(), core_mask as usize)
-            .map_err(|e| format!("设置 CPU 亲和性失败: {}", e))?;
+/// 当前平台的默认后端；设置环境变量 `PITAYABOX_BACKEND=mock` 可强制使用 mock
+pub fn default_controller() -> Box<dyn ProcessController> {
+    if std::env::var("PITAYABOX_BACKEND").is_ok_and(|v| v == "mock") {
+        return Box::new(MockController::new());
     }
-    Ok(())
+    native_controller()
 }
 
-pub fn set_idle_priority(pid: u32) -> Result<()> {
-    let handle = ScopedHandle::open(pid)?;
-    unsafe {
-        SetPriorityClass(handle.raw(), IDLE_PRIORITY_CLASS)
-            .map_err(|e| format!("设置进程优先级失败: {}", e))?;
-    }
-    Ok(())
+#[cfg(windows)]
+fn native_controller() -> Box<dyn ProcessController> {
+    Box::new(WindowsController::new())
 }
 
-pub fn set_efficiency_mode(pid: u32) -> Result<()> {
-    let handle = ScopedHandle::open(pid)?;
-    unsafe {
-        let mut policy = PROCESS_POWER_THROTTLING_STATE {
-            Version: 1,
-            ControlMask: PROCESS_POWER_THROTTLING_EXECUTION_SPEED | PROCESS_POWER_THROTTLING_IGNORE_TIMER_RESOLUTION,
-            StateMask: PROCESS_POWER_THROTTLING_EXECUTION_SPEED | PROCESS_POWER_THROTTLING_IGNORE_TIMER_RESOLUTION,
-        };
-
-        SetProcessInformation(
-            handle.raw(),
-            ProcessPowerThrottling,
-            &mut policy as *mut _ as *mut _,
-            std::mem::size_of::<PROCESS_POWER_THROTTLING_STATE>() as u32,
-        ).map_err(|e| format!("设置效率模式失败: {}", e))?;
-    }
-    Ok(())
+#[cfg(target_os = "linux")]
+fn native_controller() -> Box<dyn ProcessController> {
+    Box::new(LinuxController::new())
 }
 
-pub fn set_io_priority(pid: u32, priority: u32) -> Result<()> {
-    let handle = ScopedHandle::open(pid)?;
-    unsafe {
-        SetProcessInformation(
-            handle.raw(),
-            PROCESS_INFORMATION_CLASS(33), // ProcessIoPriority
-            &priority as *const _ as *const _,
-            std::mem::size_of::<u32>() as u32,
-        ).map_err(|e| format!("设置 I/O 优先级失败: {}", e))?;
-    }
-    Ok(())
+#[cfg(not(any(windows, target_os = "linux")))]
+fn native_controller() -> Box<dyn ProcessController> {
+    Box::new(MockController::new())
 }
 
-pub fn set_memory_priority(pid: u32, priority: u32) -> Result<()> {
-    let handle = ScopedHandle::open(pid)?;
-    unsafe {
-        SetProcessInformation(
-            handle.raw(),
-            PROCESS_INFORMATION_CLASS(39), // ProcessMemoryPriority
-            &priority as *const _ as *const _,
-            std::mem::size_of::<u32>() as u32,
-        ).map_err(|e| format!("设置内存优先级失败: {}", e))?;
-    }
-    Ok(())
+/// 进程内共享的后端实例（后端可能需要在多次调用之间保存状态）
+pub fn controller() -> &'static dyn ProcessController {
+    static CONTROLLER: OnceLock<Box<dyn ProcessController>> = OnceLock::new();
+    CONTROLLER.get_or_init(default_controller).as_ref()
 }
 
 // --- 辅助逻辑 ---
 
-pub fn find_target_core() -> (u32, u64) {
-    let system = System::new_all();
-    let total_cores = system.cpus().len() as u32;
-    let target_core = if total_cores > 0 { total_cores - 1 } else { 0 };
-    let core_mask = 1u64 << target_core;
-    (target_core, core_mask)
-}
\ No newline at end of file
+/// 没有指定放置策略时使用的核心：混合架构选最后一个能效核心，否则选负载最低的 SMT 副线程，
+/// 都没有时退回最后一个逻辑核心
+pub fn find_target_core(ctx: &PlacementContext) -> (usize, CpuSet) {
+    let target_core = ctx
+        .topology
+        .efficiency_cpus()
+        .last()
+        .or_else(|| ctx.smt_sibling())
+        .or_else(|| ctx.topology.cpus().last().map(|c| c.id))
+        .unwrap_or(0);
+    (target_core, CpuSet::from_iter([target_core]))
+}
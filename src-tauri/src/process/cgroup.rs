@@ -0,0 +1,339 @@
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use super::Result;
+
+/// cgroup v2 版的“效率模式”：把目标进程放进一个 `cpu.weight` 极低的子树
+///
+/// 同样的机制也用来收拢进程树（`contained` 子树，权重保持默认），之后 fork 的子进程留在原处。
+/// cgroup 文件系统根目录和 `/proc` 都可以替换成普通目录，方便脱离真实系统验证逻辑。
+pub struct CgroupManager {
+    root: PathBuf,
+    proc_root: PathBuf,
+    subtree: PathBuf,
+    weight: u32,
+    /// 每个被移入的 PID 原来所在的 cgroup（相对根目录）
+    original: Mutex<HashMap<u32, PathBuf>>,
+}
+
+impl CgroupManager {
+    pub const DEFAULT_ROOT: &'static str = "/sys/fs/cgroup";
+    pub const DEFAULT_SUBTREE: &'static str = "pitayabox.slice/restricted";
+    /// 收拢进程树用的子树，不限制 CPU
+    pub const CONTAIN_SUBTREE: &'static str = "pitayabox.slice/contained";
+    /// cpu.weight 的合法范围是 1..=10000，默认值为 100
+    pub const MIN_WEIGHT: u32 = 1;
+    pub const DEFAULT_WEIGHT: u32 = 100;
+
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            proc_root: PathBuf::from("/proc"),
+            subtree: PathBuf::from(Self::DEFAULT_SUBTREE),
+            weight: Self::MIN_WEIGHT,
+            original: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn with_proc_root(mut self, proc_root: impl Into<PathBuf>) -> Self {
+        self.proc_root = proc_root.into();
+        self
+    }
+
+    pub fn with_subtree(mut self, subtree: impl Into<PathBuf>) -> Self {
+        self.subtree = subtree.into();
+        self
+    }
+
+    pub fn with_weight(mut self, weight: u32) -> Self {
+        self.weight = weight.clamp(1, 10000);
+        self
+    }
+
+    /// 受限子树的绝对路径
+    pub fn path(&self) -> PathBuf {
+        self.root.join(&self.subtree)
+    }
+
+    /// 当前在受限子树中的 PID
+    pub fn members(&self) -> Vec<u32> {
+        read_procs(&self.path())
+    }
+
+    /// 创建子树、逐级打开 cpu 控制器并写入 `cpu.weight`
+    pub fn ensure(&self) -> Result<()> {
+        let leaf = self.path();
+        fs::create_dir_all(&leaf).map_err(|e| format!("创建 cgroup 失败 ({}): {}", leaf.display(), e))?;
+
+        // cgroup v2 要求父级在 subtree_control 中启用 cpu，子级才会出现 cpu.weight
+        let mut dir = self.root.clone();
+        for part in self.subtree.components() {
+            let control = dir.join("cgroup.subtree_control");
+            write_file(&control, "+cpu")
+                .map_err(|e| format!("启用 cpu 控制器失败 ({}): {}", dir.display(), e))?;
+            // memory 控制器只用于 memory.reclaim，启用失败不影响效率模式本身
+            let _ = write_file(&control, "+memory");
+            dir.push(part);
+        }
+
+        write_file(&leaf.join("cpu.weight"), &self.weight.to_string())
+            .map_err(|e| format!("写入 cpu.weight 失败: {}", e))
+    }
+
+    /// 进程当前是否在受限子树中
+    pub fn contains(&self, pid: u32) -> bool {
+        self.current_cgroup(pid).is_ok_and(|p| self.root.join(p) == self.path())
+    }
+
+    /// 通过 `memory.reclaim` 让内核从整个受限子树回收内存（需要 Linux 5.19+）
+    pub fn reclaim(&self, bytes: u64) -> Result<()> {
+        write_file(&self.path().join("memory.reclaim"), &bytes.to_string())
+            .map_err(|e| format!("memory.reclaim 失败: {}", e))
+    }
+
+    /// 把进程移入受限子树，并记住它原来所在的 cgroup
+    pub fn restrict(&self, pid: u32) -> Result<()> {
+        self.ensure()?;
+        let leaf = self.path();
+        {
+            let mut original = self.original.lock().unwrap();
+            if let Entry::Vacant(slot) = original.entry(pid) {
+                let current = self.current_cgroup(pid)?;
+                if self.root.join(&current) != leaf {
+                    slot.insert(current);
+                }
+            }
+        }
+        write_file(&leaf.join("cgroup.procs"), &pid.to_string())
+            .map_err(|e| format!("移入 cgroup 失败 (PID: {}): {}", pid, e))
+    }
+
+    /// 把进程移回原来的 cgroup；最后一个被移入的进程释放后顺带拆除子树
+    pub fn release(&self, pid: u32) -> Result<()> {
+        let Some(original) = self.original.lock().unwrap().remove(&pid) else {
+            return Ok(());
+        };
+        let result = match write_file(&self.root.join(&original).join("cgroup.procs"), &pid.to_string()) {
+            Ok(()) => Ok(()),
+            Err(e) if e.raw_os_error() == Some(libc::ESRCH) => Ok(()),
+            Err(e) => Err(format!("移回 cgroup 失败 (PID: {}): {}", pid, e)),
+        };
+        if self.original.lock().unwrap().is_empty() {
+            self.teardown()?;
+        }
+        result
+    }
+
+    /// 按持久化的记录把进程移回 `cgroup`（相对根目录）；进程不在受限子树中时什么也不做
+    pub fn move_to(&self, pid: u32, cgroup: &Path) -> Result<()> {
+        self.original.lock().unwrap().remove(&pid);
+        if !self.contains(pid) {
+            return Ok(());
+        }
+        let result = match write_file(&self.root.join(cgroup).join("cgroup.procs"), &pid.to_string()) {
+            Ok(()) => Ok(()),
+            Err(e) if e.raw_os_error() == Some(libc::ESRCH) => Ok(()),
+            Err(e) => Err(format!("移回 cgroup 失败 (PID: {}): {}", pid, e)),
+        };
+        if self.original.lock().unwrap().is_empty() && self.members().is_empty() {
+            self.teardown()?;
+        }
+        result
+    }
+
+    /// 把子树中剩余的进程全部移回原处（没有记录的移到根 cgroup），然后删除子树
+    pub fn teardown(&self) -> Result<()> {
+        let leaf = self.path();
+        if !leaf.exists() {
+            return Ok(());
+        }
+        let original = std::mem::take(&mut *self.original.lock().unwrap());
+        for pid in self.members() {
+            let target = original.get(&pid).map(|p| self.root.join(p)).unwrap_or_else(|| self.root.clone());
+            match write_file(&target.join("cgroup.procs"), &pid.to_string()) {
+                Ok(()) => {}
+                Err(e) if e.raw_os_error() == Some(libc::ESRCH) => {}
+                Err(e) => return Err(format!("移出 cgroup 失败 (PID: {}): {}", pid, e)),
+            }
+        }
+
+        // 自下而上删除，直到根目录；父级还有别的子组时停下
+        let mut dir = leaf;
+        while dir != self.root {
+            match remove_cgroup_dir(&dir) {
+                Ok(()) => {}
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(_) if dir != self.path() => break,
+                Err(e) => return Err(format!("删除 cgroup 失败 ({}): {}", dir.display(), e)),
+            }
+            if !dir.pop() {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// 读取 `/proc/<pid>/cgroup` 中 v2 层级（`0::`）的路径
+    pub fn current_cgroup(&self, pid: u32) -> Result<PathBuf> {
+        let path = self.proc_root.join(pid.to_string()).join("cgroup");
+        let content = fs::read_to_string(&path).map_err(|e| format!("读取 cgroup 失败 (PID: {}): {}", pid, e))?;
+        content
+            .lines()
+            .find_map(|line| line.strip_prefix("0::"))
+            .map(|p| PathBuf::from(p.trim_start_matches('/')))
+            .ok_or_else(|| format!("未找到 cgroup v2 层级 (PID: {})", pid))
+    }
+}
+
+impl Default for CgroupManager {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_ROOT)
+    }
+}
+
+impl CgroupManager {
+    /// 默认根目录下收拢进程树用的管理器
+    pub fn container() -> Self {
+        Self::default().with_subtree(Self::CONTAIN_SUBTREE).with_weight(Self::DEFAULT_WEIGHT)
+    }
+}
+
+// --- 文件辅助 ---
+
+/// cgroupfs 中每次 write 都是一条独立命令；普通目录下用追加模式模拟同样的效果
+fn write_file(path: &Path, value: &str) -> io::Result<()> {
+    let mut file = OpenOptions::new().append(true).create(true).open(path)?;
+    file.write_all(format!("{}\n", value).as_bytes())
+}
+
+fn read_procs(dir: &Path) -> Vec<u32> {
+    fs::read_to_string(dir.join("cgroup.procs"))
+        .map(|s| s.lines().filter_map(|l| l.trim().parse().ok()).collect())
+        .unwrap_or_default()
+}
+
+/// 真实的 cgroup 目录里只有内核生成的接口文件，直接 rmdir；普通目录（测试用）整体删除
+fn remove_cgroup_dir(dir: &Path) -> io::Result<()> {
+    const CGROUP2_SUPER_MAGIC: libc::c_long = 0x6367_7270;
+    let path = std::ffi::CString::new(dir.as_os_str().as_encoded_bytes()).map_err(io::Error::other)?;
+    let mut stat: libc::statfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statfs(path.as_ptr(), &mut stat) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    if stat.f_type as libc::c_long == CGROUP2_SUPER_MAGIC {
+        fs::remove_dir(dir)
+    } else {
+        // 与真实 cgroup 一致：还有子组时拒绝删除
+        if fs::read_dir(dir)?.flatten().any(|e| e.path().is_dir()) {
+            return Err(io::Error::from_raw_os_error(libc::EBUSY));
+        }
+        fs::remove_dir_all(dir)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::paths;
+
+    /// 普通目录模拟的 cgroup 根和 /proc；`pids` 里的进程起初都在 `user.slice`
+    fn fixture(pids: &[u32]) -> (paths::ScratchDir, CgroupManager) {
+        let dir = paths::ScratchDir::new("cgroup");
+        let (root, proc_root) = (dir.join("cgroup"), dir.join("proc"));
+        fs::create_dir_all(root.join("user.slice")).unwrap();
+        for pid in pids {
+            set_cgroup(&proc_root, *pid, "user.slice");
+        }
+        let manager = CgroupManager::new(&root).with_proc_root(&proc_root);
+        (dir, manager)
+    }
+
+    /// 模拟内核完成迁移后 `/proc/<pid>/cgroup` 的变化
+    fn set_cgroup(proc_root: &Path, pid: u32, cgroup: &str) {
+        let dir = proc_root.join(pid.to_string());
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("cgroup"), format!("0::/{}\n", cgroup)).unwrap();
+    }
+
+    fn read(path: PathBuf) -> String {
+        fs::read_to_string(path).unwrap_or_default()
+    }
+
+    #[test]
+    fn ensure_enables_cpu_controller_on_every_level() {
+        let (dir, manager) = fixture(&[]);
+        manager.ensure().unwrap();
+        let root = dir.join("cgroup");
+        assert!(read(root.join("cgroup.subtree_control")).contains("+cpu"));
+        assert!(read(root.join("pitayabox.slice/cgroup.subtree_control")).contains("+cpu"));
+        assert_eq!(read(manager.path().join("cpu.weight")), "1\n");
+        assert!(!manager.path().join("cgroup.subtree_control").exists());
+    }
+
+    #[test]
+    fn restrict_and_release_move_between_cgroups() {
+        let (dir, manager) = fixture(&[100]);
+        let (root, proc_root) = (dir.join("cgroup"), dir.join("proc"));
+        assert_eq!(manager.current_cgroup(100).unwrap(), PathBuf::from("user.slice"));
+        assert!(!manager.contains(100));
+
+        manager.restrict(100).unwrap();
+        assert_eq!(manager.members(), vec![100]);
+        set_cgroup(&proc_root, 100, CgroupManager::DEFAULT_SUBTREE);
+        assert!(manager.contains(100));
+        // 已在子树中再次移入不会把子树记成原来的位置
+        manager.restrict(100).unwrap();
+
+        manager.release(100).unwrap();
+        assert_eq!(read(root.join("user.slice/cgroup.procs")), "100\n");
+        // 最后一个进程离开后子树被拆除，根下的其他 cgroup 不受影响
+        assert!(!root.join("pitayabox.slice").exists());
+        assert!(root.join("user.slice").exists());
+        manager.release(100).unwrap();
+    }
+
+    #[test]
+    fn move_to_uses_persisted_cgroup() {
+        let (dir, manager) = fixture(&[200, 201]);
+        let (root, proc_root) = (dir.join("cgroup"), dir.join("proc"));
+        manager.ensure().unwrap();
+        fs::create_dir_all(root.join("app.slice")).unwrap();
+        // 另一个实例移入的进程，本实例没有原位置的记录
+        set_cgroup(&proc_root, 200, CgroupManager::DEFAULT_SUBTREE);
+        manager.move_to(200, Path::new("app.slice")).unwrap();
+        assert_eq!(read(root.join("app.slice/cgroup.procs")), "200\n");
+        // 不在子树中的进程保持原样
+        manager.move_to(201, Path::new("app.slice")).unwrap();
+        assert_eq!(read(root.join("app.slice/cgroup.procs")), "200\n");
+    }
+
+    #[test]
+    fn missing_process_or_v1_only_is_an_error() {
+        let (dir, manager) = fixture(&[]);
+        assert!(manager.current_cgroup(300).is_err());
+        assert!(manager.restrict(300).is_err());
+        let proc_dir = dir.join("proc/301");
+        fs::create_dir_all(&proc_dir).unwrap();
+        fs::write(proc_dir.join("cgroup"), "12:cpu,cpuacct:/user.slice\n").unwrap();
+        assert!(manager.current_cgroup(301).is_err());
+    }
+
+    #[test]
+    fn container_keeps_default_weight() {
+        let (dir, manager) = fixture(&[]);
+        let container = CgroupManager::new(dir.join("cgroup"))
+            .with_proc_root(dir.join("proc"))
+            .with_subtree(CgroupManager::CONTAIN_SUBTREE)
+            .with_weight(CgroupManager::DEFAULT_WEIGHT);
+        container.ensure().unwrap();
+        manager.ensure().unwrap();
+        assert_eq!(read(container.path().join("cpu.weight")), "100\n");
+        assert_eq!(read(manager.path().join("cpu.weight")), "1\n");
+        assert_ne!(container.path(), manager.path());
+        assert_eq!(CgroupManager::container().path(), Path::new("/sys/fs/cgroup/pitayabox.slice/contained"));
+    }
+}
@@ -0,0 +1,165 @@
+use std::collections::{HashSet, VecDeque};
+use std::time::{Duration, Instant};
+
+use super::Result;
+
+#[cfg(target_os = "linux")]
+mod netlink;
+#[cfg(windows)]
+mod wmi;
+
+#[cfg(target_os = "linux")]
+pub use self::netlink::ProcConnector;
+#[cfg(windows)]
+pub use self::wmi::WmiSource;
+
+/// 进程表的一次变化
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProcessEvent {
+    /// 新进程出现；`parent` 已知时一并给出
+    Started { pid: u32, parent: Option<u32> },
+    Exited { pid: u32 },
+    /// 内核缓冲区溢出等原因导致事件丢失，调用方应做一次全量扫描
+    Lost,
+}
+
+/// 进程启动/退出事件的来源
+///
+/// 实现可能持有只能在创建线程上使用的句柄（如 COM 接口），所以不要求 `Send`，
+/// 应在消费事件的线程里调用 `open`。
+pub trait EventSource {
+    /// 来源名称，用于日志
+    fn name(&self) -> &'static str;
+
+    /// 等待下一个事件，超时返回 `Ok(None)`
+    fn next(&mut self, timeout: Duration) -> Result<Option<ProcessEvent>>;
+}
+
+/// 当前平台最合适的事件源；原生接口不可用（通常是权限不足）时退回 `PidDiff`
+pub fn open() -> Box<dyn EventSource> {
+    match open_native() {
+        Ok(source) => source,
+        Err(e) => {
+            eprintln!("进程事件订阅失败，改为轮询: {}", e);
+            Box::new(PidDiff::new(PidDiff::DEFAULT_INTERVAL))
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn open_native() -> Result<Box<dyn EventSource>> {
+    Ok(Box::new(ProcConnector::open()?))
+}
+
+#[cfg(windows)]
+fn open_native() -> Result<Box<dyn EventSource>> {
+    Ok(Box::new(WmiSource::open()?))
+}
+
+#[cfg(not(any(windows, target_os = "linux")))]
+fn open_native() -> Result<Box<dyn EventSource>> {
+    Err("当前平台没有进程事件接口".to_string())
+}
+
+// --- 兜底：比对 PID 列表 ---
+
+/// 定期列出全部 PID 并与上一次比较；只读目录项，比完整刷新进程信息便宜得多
+pub struct PidDiff {
+    interval: Duration,
+    known: Option<HashSet<u32>>,
+    pending: VecDeque<ProcessEvent>,
+}
+
+impl PidDiff {
+    pub const DEFAULT_INTERVAL: Duration = Duration::from_millis(250);
+
+    pub fn new(interval: Duration) -> Self {
+        Self { interval, known: None, pending: VecDeque::new() }
+    }
+
+    fn scan(&mut self) -> Result<()> {
+        let current = list_pids()?;
+        // 第一次只建立基线，不把已有进程当成新进程
+        if let Some(known) = &self.known {
+            let mut started: Vec<u32> = current.difference(known).copied().collect();
+            let mut exited: Vec<u32> = known.difference(&current).copied().collect();
+            started.sort_unstable();
+            exited.sort_unstable();
+            self.pending.extend(exited.into_iter().map(|pid| ProcessEvent::Exited { pid }));
+            self.pending.extend(started.into_iter().map(|pid| ProcessEvent::Started { pid, parent: None }));
+        }
+        self.known = Some(current);
+        Ok(())
+    }
+}
+
+impl EventSource for PidDiff {
+    fn name(&self) -> &'static str {
+        "pid-diff"
+    }
+
+    fn next(&mut self, timeout: Duration) -> Result<Option<ProcessEvent>> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Ok(Some(event));
+            }
+            self.scan()?;
+            if !self.pending.is_empty() {
+                continue;
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return Ok(None);
+            }
+            std::thread::sleep(self.interval.min(deadline - now));
+        }
+    }
+}
+
+// --- 廉价的进程信息 ---
+
+/// 内核保存的进程名（`/proc/<pid>/comm`），最多 `COMM_MAX` 个字节，更长的会被截断
+///
+/// 每个新进程都要看一眼，所以只读一个小文件，不构造 sysinfo 的 `System`；其他平台返回 `None`。
+#[cfg(target_os = "linux")]
+pub fn process_name(pid: u32) -> Option<String> {
+    let comm = std::fs::read_to_string(format!("/proc/{}/comm", pid)).ok()?;
+    Some(comm.trim_end_matches('\n').to_string())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn process_name(_pid: u32) -> Option<String> {
+    None
+}
+
+/// `process_name` 的长度上限（`TASK_COMM_LEN - 1`）
+pub const COMM_MAX: usize = 15;
+
+/// 父进程 PID，取自 `/proc/<pid>/stat` 的第 4 个字段；其他平台返回 `None`
+#[cfg(target_os = "linux")]
+pub fn parent_pid(pid: u32) -> Option<u32> {
+    let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    // comm 字段可能含空格和括号，从最后一个 ')' 之后开始切分
+    stat.rsplit_once(')')?.1.split_whitespace().nth(1)?.parse().ok()
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn parent_pid(_pid: u32) -> Option<u32> {
+    None
+}
+
+#[cfg(target_os = "linux")]
+fn list_pids() -> Result<HashSet<u32>> {
+    let entries = std::fs::read_dir("/proc").map_err(|e| format!("无法读取 /proc: {}", e))?;
+    Ok(entries.filter_map(|entry| entry.ok()?.file_name().to_str()?.parse().ok()).collect())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn list_pids() -> Result<HashSet<u32>> {
+    use sysinfo::{ProcessRefreshKind, System};
+
+    let mut sys = System::new();
+    sys.refresh_processes_specifics(ProcessRefreshKind::new());
+    Ok(sys.processes().keys().map(|pid| pid.as_u32()).collect())
+}
@@ -0,0 +1,150 @@
+use std::collections::VecDeque;
+use std::io;
+use std::mem;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::time::Duration;
+
+use super::{EventSource, ProcessEvent};
+use crate::process::Result;
+
+// linux/connector.h、linux/cn_proc.h 中的常量，libc 没有导出
+const CN_IDX_PROC: u32 = 1;
+const CN_VAL_PROC: u32 = 1;
+const PROC_CN_MCAST_LISTEN: u32 = 1;
+const PROC_EVENT_EXEC: u32 = 0x0000_0002;
+const PROC_EVENT_EXIT: u32 = 0x8000_0000;
+
+/// `struct nlmsghdr` 的长度
+const NLMSG_HDRLEN: usize = 16;
+/// `struct cn_msg` 的长度（不含数据）
+const CN_MSG_LEN: usize = 20;
+/// `struct proc_event` 中 `event_data` 之前的部分：what、cpu、timestamp_ns
+const PROC_EVENT_HEADER: usize = 16;
+
+/// 内核 proc connector：exec/exit 发生时由内核直接推送，需要 CAP_NET_ADMIN
+///
+/// fork 事件直接丢弃：编译之类的负载每秒上千次 fork，而只 fork 不 exec 的子进程与父进程同名，
+/// 目标规则要等 exec 换成新程序才可能命中。
+pub struct ProcConnector {
+    fd: OwnedFd,
+    pending: VecDeque<ProcessEvent>,
+    buffer: Vec<u8>,
+}
+
+impl ProcConnector {
+    pub fn open() -> Result<Self> {
+        let raw = unsafe {
+            libc::socket(libc::AF_NETLINK, libc::SOCK_DGRAM | libc::SOCK_CLOEXEC, libc::NETLINK_CONNECTOR)
+        };
+        if raw < 0 {
+            return Err(format!("创建 netlink 套接字失败: {}", io::Error::last_os_error()));
+        }
+        let fd = unsafe { OwnedFd::from_raw_fd(raw) };
+
+        let mut addr: libc::sockaddr_nl = unsafe { mem::zeroed() };
+        addr.nl_family = libc::AF_NETLINK as libc::sa_family_t;
+        addr.nl_groups = CN_IDX_PROC;
+        let bound = unsafe {
+            libc::bind(
+                fd.as_raw_fd(),
+                &addr as *const libc::sockaddr_nl as *const libc::sockaddr,
+                mem::size_of::<libc::sockaddr_nl>() as libc::socklen_t,
+            )
+        };
+        if bound != 0 {
+            return Err(format!("订阅 proc connector 失败: {}", io::Error::last_os_error()));
+        }
+
+        // nlmsghdr + cn_msg + 操作码
+        let total = NLMSG_HDRLEN + CN_MSG_LEN + 4;
+        let mut message = Vec::with_capacity(total);
+        message.extend_from_slice(&(total as u32).to_ne_bytes());
+        message.extend_from_slice(&(libc::NLMSG_DONE as u16).to_ne_bytes());
+        message.extend_from_slice(&0u16.to_ne_bytes()); // flags
+        message.extend_from_slice(&0u32.to_ne_bytes()); // seq
+        message.extend_from_slice(&0u32.to_ne_bytes()); // pid
+        message.extend_from_slice(&CN_IDX_PROC.to_ne_bytes());
+        message.extend_from_slice(&CN_VAL_PROC.to_ne_bytes());
+        message.extend_from_slice(&0u32.to_ne_bytes()); // seq
+        message.extend_from_slice(&0u32.to_ne_bytes()); // ack
+        message.extend_from_slice(&4u16.to_ne_bytes()); // len
+        message.extend_from_slice(&0u16.to_ne_bytes()); // flags
+        message.extend_from_slice(&PROC_CN_MCAST_LISTEN.to_ne_bytes());
+        let sent = unsafe { libc::send(fd.as_raw_fd(), message.as_ptr() as *const libc::c_void, message.len(), 0) };
+        if sent < 0 {
+            return Err(format!("启用 proc connector 失败: {}", io::Error::last_os_error()));
+        }
+
+        Ok(Self { fd, pending: VecDeque::new(), buffer: vec![0; 8192] })
+    }
+
+    /// 解析一个数据报里的全部 netlink 消息
+    fn parse(&mut self, len: usize) {
+        let data = &self.buffer[..len];
+        let read_u32 = |offset: usize| -> Option<u32> {
+            data.get(offset..offset + 4).map(|b| u32::from_ne_bytes([b[0], b[1], b[2], b[3]]))
+        };
+
+        let mut offset = 0;
+        while let Some(message_len) = read_u32(offset) {
+            let message_len = message_len as usize;
+            if message_len < NLMSG_HDRLEN || offset + message_len > len {
+                break;
+            }
+            let event = offset + NLMSG_HDRLEN + CN_MSG_LEN;
+            let field = |index: usize| read_u32(event + PROC_EVENT_HEADER + index * 4);
+            // 线程也会产生这些事件，只保留 pid == tgid 的进程级事件
+            let parsed = match read_u32(event) {
+                Some(PROC_EVENT_EXEC) => match (field(0), field(1)) {
+                    (Some(pid), Some(tgid)) if pid == tgid => Some(ProcessEvent::Started { pid, parent: None }),
+                    _ => None,
+                },
+                Some(PROC_EVENT_EXIT) => match (field(0), field(1)) {
+                    (Some(pid), Some(tgid)) if pid == tgid => Some(ProcessEvent::Exited { pid }),
+                    _ => None,
+                },
+                _ => None,
+            };
+            self.pending.extend(parsed);
+            // NLMSG_ALIGN
+            offset += (message_len + 3) & !3;
+        }
+    }
+}
+
+impl EventSource for ProcConnector {
+    fn name(&self) -> &'static str {
+        "proc-connector"
+    }
+
+    fn next(&mut self, timeout: Duration) -> Result<Option<ProcessEvent>> {
+        if let Some(event) = self.pending.pop_front() {
+            return Ok(Some(event));
+        }
+
+        let mut poll = libc::pollfd { fd: self.fd.as_raw_fd(), events: libc::POLLIN, revents: 0 };
+        let timeout_ms = timeout.as_millis().min(i32::MAX as u128) as libc::c_int;
+        let ready = unsafe { libc::poll(&mut poll, 1, timeout_ms) };
+        if ready < 0 {
+            let err = io::Error::last_os_error();
+            return if err.kind() == io::ErrorKind::Interrupted { Ok(None) } else { Err(format!("等待进程事件失败: {}", err)) };
+        }
+        if ready == 0 {
+            return Ok(None);
+        }
+
+        let received = unsafe {
+            libc::recv(self.fd.as_raw_fd(), self.buffer.as_mut_ptr() as *mut libc::c_void, self.buffer.len(), 0)
+        };
+        if received < 0 {
+            let err = io::Error::last_os_error();
+            // 接收缓冲区溢出，中间的事件已经丢了
+            if err.raw_os_error() == Some(libc::ENOBUFS) {
+                return Ok(Some(ProcessEvent::Lost));
+            }
+            return Err(format!("读取进程事件失败: {}", err));
+        }
+        self.parse(received as usize);
+        Ok(self.pending.pop_front())
+    }
+}
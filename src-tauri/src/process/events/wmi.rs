@@ -0,0 +1,119 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use windows::core::{Interface, BSTR, PCWSTR, VARIANT, w};
+use windows::Win32::System::Com::{
+    CoCreateInstance, CoInitializeEx, CoSetProxyBlanket, CLSCTX_INPROC_SERVER, COINIT_MULTITHREADED, EOAC_NONE,
+    RPC_C_AUTHN_LEVEL_CALL, RPC_C_IMP_LEVEL_IMPERSONATE,
+};
+use windows::Win32::System::Wmi::{
+    IEnumWbemClassObject, IWbemClassObject, IWbemLocator, IWbemServices, WbemLocator, WBEM_FLAG_FORWARD_ONLY,
+    WBEM_FLAG_RETURN_IMMEDIATELY,
+};
+
+use super::{EventSource, ProcessEvent};
+use crate::process::Result;
+
+// rpcdce.h，避免为两个常量引入 Win32_System_Rpc
+const RPC_C_AUTHN_WINNT: u32 = 10;
+const RPC_C_AUTHZ_NONE: u32 = 0;
+
+/// 内核跟踪事件，几乎没有延迟，但需要管理员权限
+const TRACE_QUERY: &str = "SELECT * FROM Win32_ProcessTrace";
+/// 普通权限下的退路：WMI 每秒比对一次进程表
+const INSTANCE_QUERY: &str = "SELECT * FROM __InstanceOperationEvent WITHIN 1 WHERE TargetInstance ISA 'Win32_Process'";
+
+/// 通过 WMI 事件查询接收进程启动/退出
+pub struct WmiSource {
+    events: IEnumWbemClassObject,
+    pending: VecDeque<ProcessEvent>,
+}
+
+impl WmiSource {
+    pub fn open() -> Result<Self> {
+        unsafe {
+            // 同一线程重复初始化返回 S_FALSE，不算错误
+            let _ = CoInitializeEx(None, COINIT_MULTITHREADED);
+            let locator: IWbemLocator = CoCreateInstance(&WbemLocator, None, CLSCTX_INPROC_SERVER)
+                .map_err(|e| format!("创建 WMI 定位器失败: {}", e))?;
+            let services: IWbemServices = locator
+                .ConnectServer(&BSTR::from("ROOT\\CIMV2"), &BSTR::new(), &BSTR::new(), &BSTR::new(), 0, &BSTR::new(), None)
+                .map_err(|e| format!("连接 WMI 失败: {}", e))?;
+            CoSetProxyBlanket(
+                &services,
+                RPC_C_AUTHN_WINNT,
+                RPC_C_AUTHZ_NONE,
+                PCWSTR::null(),
+                RPC_C_AUTHN_LEVEL_CALL,
+                RPC_C_IMP_LEVEL_IMPERSONATE,
+                None,
+                EOAC_NONE,
+            )
+            .map_err(|e| format!("设置 WMI 代理安全失败: {}", e))?;
+
+            let flags = WBEM_FLAG_FORWARD_ONLY | WBEM_FLAG_RETURN_IMMEDIATELY;
+            let query = |text: &str| services.ExecNotificationQuery(&BSTR::from("WQL"), &BSTR::from(text), flags, None);
+            let events = query(TRACE_QUERY)
+                .or_else(|_| query(INSTANCE_QUERY))
+                .map_err(|e| format!("订阅 WMI 进程事件失败: {}", e))?;
+            Ok(Self { events, pending: VecDeque::new() })
+        }
+    }
+}
+
+fn get(object: &IWbemClassObject, name: PCWSTR) -> Option<VARIANT> {
+    let mut value = VARIANT::default();
+    unsafe { object.Get(name, 0, &mut value, None, None).ok()? };
+    Some(value)
+}
+
+fn get_u32(object: &IWbemClassObject, name: PCWSTR) -> Option<u32> {
+    u32::try_from(&get(object, name)?).ok()
+}
+
+/// 把一个事件对象转换成 `ProcessEvent`
+fn parse(object: &IWbemClassObject) -> Option<ProcessEvent> {
+    let class = BSTR::try_from(&get(object, w!("__Class"))?).ok()?.to_string();
+    match class.as_str() {
+        "Win32_ProcessStartTrace" => Some(ProcessEvent::Started {
+            pid: get_u32(object, w!("ProcessID"))?,
+            parent: get_u32(object, w!("ParentProcessID")),
+        }),
+        "Win32_ProcessStopTrace" => Some(ProcessEvent::Exited { pid: get_u32(object, w!("ProcessID"))? }),
+        "__InstanceCreationEvent" | "__InstanceDeletionEvent" => {
+            let target: IWbemClassObject = windows::core::IUnknown::try_from(&get(object, w!("TargetInstance"))?)
+                .ok()?
+                .cast()
+                .ok()?;
+            let pid = get_u32(&target, w!("ProcessId"))?;
+            if class == "__InstanceCreationEvent" {
+                Some(ProcessEvent::Started { pid, parent: get_u32(&target, w!("ParentProcessId")) })
+            } else {
+                Some(ProcessEvent::Exited { pid })
+            }
+        }
+        _ => None,
+    }
+}
+
+impl EventSource for WmiSource {
+    fn name(&self) -> &'static str {
+        "wmi"
+    }
+
+    fn next(&mut self, timeout: Duration) -> Result<Option<ProcessEvent>> {
+        if let Some(event) = self.pending.pop_front() {
+            return Ok(Some(event));
+        }
+        let mut objects: [Option<IWbemClassObject>; 16] = Default::default();
+        let mut returned = 0u32;
+        let timeout_ms = timeout.as_millis().min(i32::MAX as u128) as i32;
+        let hr = unsafe { self.events.Next(timeout_ms, &mut objects, &mut returned) };
+        // 超时返回 WBEM_S_TIMEDOUT，也是成功码
+        if hr.is_err() {
+            return Err(format!("读取 WMI 进程事件失败: {}", windows::core::Error::from(hr)));
+        }
+        self.pending.extend(objects.iter().take(returned as usize).flatten().filter_map(parse));
+        Ok(self.pending.pop_front())
+    }
+}
@@ -0,0 +1,660 @@
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::mem;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use super::cgroup::CgroupManager;
+use crate::cpuset::CpuSet;
+use super::{ProcessController, ProcessSnapshot, Result, SavedPriority, ThreadTimes};
+
+/// Linux 后端：通过 sched_* / setpriority 系统调用直接作用于目标进程
+///
+/// Proton/Wine 下 SGuard64.exe 就是一个普通的 Linux 进程，所以这里直接按 PID 操作。
+/// Linux 的调度属性是按线程生效的，因此凡是需要覆盖整个进程的操作都会遍历 `/proc/<pid>/task`。
+/// 效率模式没有直接对应物，用 cgroup v2 的 `cpu.weight` 代替。
+pub struct LinuxController {
+    /// 每个被降级线程在修改前的调度策略，按 PID -> TID 保存，用于原样恢复
+    saved_sched: Mutex<HashMap<u32, HashMap<u32, SchedSnapshot>>>,
+    cgroup: CgroupManager,
+    /// 收拢进程树用的 cgroup，权重为默认值；不能和效率模式共用，否则没选效率模式的规则也会被限速
+    container: CgroupManager,
+}
+
+impl Default for LinuxController {
+    fn default() -> Self {
+        Self { saved_sched: Mutex::default(), cgroup: CgroupManager::default(), container: CgroupManager::container() }
+    }
+}
+
+/// 线程在修改前的调度状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SchedSnapshot {
+    /// `sched_getscheduler` 的返回值（可能带有 SCHED_RESET_ON_FORK 标志）
+    pub policy: i32,
+    /// 实时策略下的静态优先级，普通策略为 0
+    pub rt_priority: i32,
+    pub nice: i32,
+}
+
+/// 线程最终被设置成的空闲策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IdlePolicy {
+    SchedIdle,
+    /// 没有权限切换到 SCHED_IDLE 时的回退：nice 19 + SCHED_BATCH
+    BatchNice19,
+}
+
+/// ioprio_set 使用的调度类（见 linux/ioprio.h）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IoClass {
+    /// 跟随 CPU nice 值
+    None,
+    /// best-effort，`level` 为 0（最高）到 7（最低）
+    BestEffort(u8),
+    /// 只有磁盘空闲时才会得到服务
+    Idle,
+}
+
+impl IoClass {
+    const SHIFT: i32 = 13;
+
+    /// 把 Windows 的 IO_PRIORITY_HINT 映射成 Linux 的 I/O 调度类
+    pub fn from_hint(priority: u32) -> Self {
+        match priority {
+            0 => IoClass::Idle,
+            1 => IoClass::BestEffort(7),
+            _ => IoClass::None,
+        }
+    }
+
+    /// `from_hint` 的逆映射；其他 best-effort 级别都算正常
+    pub fn to_hint(self) -> u32 {
+        match self {
+            IoClass::Idle => 0,
+            IoClass::BestEffort(7) => 1,
+            _ => 2,
+        }
+    }
+
+    fn from_ioprio(value: libc::c_int) -> Self {
+        match value >> Self::SHIFT {
+            2 => IoClass::BestEffort((value & 7) as u8),
+            3 => IoClass::Idle,
+            _ => IoClass::None,
+        }
+    }
+
+    fn to_ioprio(self) -> libc::c_int {
+        match self {
+            IoClass::None => 0,
+            IoClass::BestEffort(level) => (2 << Self::SHIFT) | level.min(7) as libc::c_int,
+            IoClass::Idle => 3 << Self::SHIFT,
+        }
+    }
+}
+
+/// 按线程执行某个操作后的结果汇总
+#[derive(Debug, Default)]
+pub struct ThreadReport {
+    /// 设置成功的线程
+    pub applied: Vec<u32>,
+    /// 设置失败的线程及原因
+    pub failed: Vec<(u32, String)>,
+}
+
+impl ThreadReport {
+    /// 只要有线程失败就转换成错误，错误信息中列出失败的线程
+    fn into_result(self, action: &str, pid: u32) -> Result<()> {
+        if self.failed.is_empty() {
+            return Ok(());
+        }
+        let details: Vec<String> = self.failed.iter().map(|(tid, e)| format!("TID {}: {}", tid, e)).collect();
+        Err(format!(
+            "{}失败 (PID: {}，{}/{} 个线程): {}",
+            action,
+            pid,
+            self.failed.len(),
+            self.failed.len() + self.applied.len(),
+            details.join("; ")
+        ))
+    }
+}
+
+impl LinuxController {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 使用自定义 cgroup 管理器（例如委派给当前用户的子树）
+    pub fn with_cgroup(mut self, cgroup: CgroupManager) -> Self {
+        self.cgroup = cgroup;
+        self
+    }
+
+    pub fn cgroup(&self) -> &CgroupManager {
+        &self.cgroup
+    }
+
+    /// 使用自定义的收拢进程树用的 cgroup 管理器
+    pub fn with_container(mut self, container: CgroupManager) -> Self {
+        self.container = container;
+        self
+    }
+
+    /// 把亲和性应用到目标进程的每个线程，并用 `sched_getaffinity` 读回校验
+    pub fn apply_affinity(&self, pid: u32, cpus: &CpuSet) -> Result<ThreadReport> {
+        let mut report = ThreadReport::default();
+
+        for tid in list_threads(pid)? {
+            match set_thread_affinity(tid, cpus) {
+                Ok(()) => report.applied.push(tid),
+                // 线程在遍历期间退出，不算失败
+                Err(e) if e.raw_os_error() == Some(libc::ESRCH) => {}
+                Err(e) => report.failed.push((tid, e.to_string())),
+            }
+        }
+
+        if report.applied.is_empty() && report.failed.is_empty() {
+            return Err(format!("进程已退出 (PID: {})", pid));
+        }
+        Ok(report)
+    }
+
+    /// 把目标进程的每个线程切换到 SCHED_IDLE，失败时回退为 nice 19 + SCHED_BATCH
+    ///
+    /// 每个线程第一次被修改前的状态会被记录下来，重复调用不会覆盖原始记录。
+    pub fn apply_idle(&self, pid: u32) -> Result<(ThreadReport, Vec<(u32, IdlePolicy)>)> {
+        let mut report = ThreadReport::default();
+        let mut policies = Vec::new();
+        let mut saved = self.saved_sched.lock().unwrap();
+        let snapshots = saved.entry(pid).or_default();
+
+        for tid in list_threads(pid)? {
+            if let Entry::Vacant(slot) = snapshots.entry(tid) {
+                match read_sched(tid) {
+                    Ok(snapshot) => { slot.insert(snapshot); }
+                    Err(e) if e.raw_os_error() == Some(libc::ESRCH) => continue,
+                    Err(e) => { report.failed.push((tid, e.to_string())); continue; }
+                }
+            }
+            match set_thread_idle(tid) {
+                Ok(policy) => { report.applied.push(tid); policies.push((tid, policy)); }
+                Err(e) if e.raw_os_error() == Some(libc::ESRCH) => { snapshots.remove(&tid); }
+                Err(e) => report.failed.push((tid, e.to_string())),
+            }
+        }
+
+        if report.applied.is_empty() && report.failed.is_empty() {
+            saved.remove(&pid);
+            return Err(format!("进程已退出 (PID: {})", pid));
+        }
+        Ok((report, policies))
+    }
+
+    /// 对每个线程调用 `ioprio_set`；IDLE 类被拒绝时退回 best-effort 7
+    pub fn apply_io_class(&self, pid: u32, class: IoClass) -> Result<ThreadReport> {
+        let mut report = ThreadReport::default();
+        for tid in list_threads(pid)? {
+            let mut result = set_thread_ioprio(tid, class);
+            if class == IoClass::Idle && matches!(&result, Err(e) if e.raw_os_error() == Some(libc::EPERM)) {
+                result = set_thread_ioprio(tid, IoClass::BestEffort(7));
+            }
+            match result {
+                Ok(()) => report.applied.push(tid),
+                Err(e) if e.raw_os_error() == Some(libc::ESRCH) => {}
+                Err(e) => report.failed.push((tid, e.to_string())),
+            }
+        }
+
+        if report.applied.is_empty() && report.failed.is_empty() {
+            return Err(format!("进程已退出 (PID: {})", pid));
+        }
+        Ok(report)
+    }
+
+    /// 按记录把线程的调度策略与 nice 值原样写回
+    pub fn restore_sched(&self, pid: u32) -> Result<ThreadReport> {
+        let snapshots = self.saved_sched.lock().unwrap().remove(&pid).unwrap_or_default();
+        let mut report = ThreadReport::default();
+        for (tid, snapshot) in snapshots {
+            match write_sched(tid, &snapshot) {
+                Ok(()) => report.applied.push(tid),
+                Err(e) if e.raw_os_error() == Some(libc::ESRCH) => {}
+                Err(e) => report.failed.push((tid, e.to_string())),
+            }
+        }
+        Ok(report)
+    }
+}
+
+fn last_os_error() -> io::Error {
+    io::Error::last_os_error()
+}
+
+// --- 线程与亲和性辅助 ---
+
+/// 列出 `/proc/<pid>/task` 下的全部线程 ID
+fn list_threads(pid: u32) -> Result<Vec<u32>> {
+    let entries = fs::read_dir(format!("/proc/{}/task", pid))
+        .map_err(|e| format!("无法读取线程列表 (PID: {}): {}", pid, e))?;
+    let mut tids: Vec<u32> = entries
+        .filter_map(|entry| entry.ok()?.file_name().to_str()?.parse().ok())
+        .collect();
+    tids.sort_unstable();
+    Ok(tids)
+}
+
+/// 确认 TID 属于该进程，避免误伤复用了同一 ID 的其它线程
+fn check_thread(pid: u32, tid: u32) -> Result<()> {
+    if fs::metadata(format!("/proc/{}/task/{}", pid, tid)).is_ok() {
+        Ok(())
+    } else {
+        Err(format!("线程不存在 (PID: {}, TID: {})", pid, tid))
+    }
+}
+
+/// 读取 `/proc/<pid>/task/<tid>/stat` 中的 utime + stime
+fn read_thread_time(pid: u32, tid: u32) -> io::Result<Duration> {
+    let stat = fs::read_to_string(format!("/proc/{}/task/{}/stat", pid, tid))?;
+    // comm 字段可能含空格和括号，从最后一个 ')' 之后开始按空格切分；utime/stime 是第 14、15 个字段
+    let rest = stat.rsplit_once(')').map(|(_, r)| r).ok_or_else(|| io::Error::other("stat 格式错误"))?;
+    let fields: Vec<&str> = rest.split_whitespace().collect();
+    let ticks = |i: usize| fields.get(i).and_then(|v| v.parse::<u64>().ok()).ok_or_else(|| io::Error::other("stat 格式错误"));
+    let total = ticks(11)? + ticks(12)?;
+    let hz = unsafe { libc::sysconf(libc::_SC_CLK_TCK) }.max(1) as u64;
+    Ok(Duration::from_nanos(total.saturating_mul(1_000_000_000) / hz))
+}
+
+const ULONG_BITS: usize = libc::c_ulong::BITS as usize;
+
+/// 内核的 CPU 位图：`unsigned long` 数组，长度不小于 `cpu_set_t`，CPU 超过 1024 个时继续加长
+fn to_kernel_mask(cpus: &CpuSet) -> Vec<libc::c_ulong> {
+    let min_len = mem::size_of::<libc::cpu_set_t>() / mem::size_of::<libc::c_ulong>();
+    let mut words = vec![0; cpus.last().map_or(0, |cpu| cpu / ULONG_BITS + 1).max(min_len)];
+    for cpu in cpus.iter() {
+        words[cpu / ULONG_BITS] |= 1 << (cpu % ULONG_BITS);
+    }
+    words
+}
+
+fn from_kernel_mask(words: &[libc::c_ulong]) -> CpuSet {
+    (0..words.len() * ULONG_BITS).filter(|cpu| words[cpu / ULONG_BITS] & (1 << (cpu % ULONG_BITS)) != 0).collect()
+}
+
+/// 读取线程的亲和性；缓冲区比内核的位图短时返回 EINVAL，加倍后重试
+fn get_thread_affinity(tid: u32) -> io::Result<CpuSet> {
+    let mut words = to_kernel_mask(&CpuSet::new());
+    loop {
+        let size = words.len() * mem::size_of::<libc::c_ulong>();
+        let ret = unsafe { libc::sched_getaffinity(tid as libc::pid_t, size, words.as_mut_ptr() as *mut libc::cpu_set_t) };
+        if ret == 0 {
+            return Ok(from_kernel_mask(&words));
+        }
+        let err = last_os_error();
+        if err.raw_os_error() != Some(libc::EINVAL) || size >= 1 << 16 {
+            return Err(err);
+        }
+        words.resize(words.len() * 2, 0);
+    }
+}
+
+/// 设置单个线程的亲和性，随后读回；内核按 cpuset 裁剪掩码时视为失败
+fn set_thread_affinity(tid: u32, wanted: &CpuSet) -> io::Result<()> {
+    let words = to_kernel_mask(wanted);
+    let size = words.len() * mem::size_of::<libc::c_ulong>();
+    if unsafe { libc::sched_setaffinity(tid as libc::pid_t, size, words.as_ptr() as *const libc::cpu_set_t) } != 0 {
+        return Err(last_os_error());
+    }
+    if get_thread_affinity(tid)? != *wanted {
+        return Err(io::Error::other("读回的亲和性与期望不一致"));
+    }
+    Ok(())
+}
+
+// --- 调度策略辅助 ---
+
+fn read_sched(tid: u32) -> io::Result<SchedSnapshot> {
+    unsafe {
+        let policy = libc::sched_getscheduler(tid as libc::pid_t);
+        if policy < 0 {
+            return Err(last_os_error());
+        }
+        let mut param: libc::sched_param = mem::zeroed();
+        if libc::sched_getparam(tid as libc::pid_t, &mut param) != 0 {
+            return Err(last_os_error());
+        }
+        // getpriority 可以合法地返回 -1，只能通过 errno 判断是否出错
+        *libc::__errno_location() = 0;
+        let nice = libc::getpriority(libc::PRIO_PROCESS, tid as libc::id_t);
+        if nice == -1 && *libc::__errno_location() != 0 {
+            return Err(last_os_error());
+        }
+        Ok(SchedSnapshot { policy, rt_priority: param.sched_priority, nice })
+    }
+}
+
+fn write_sched(tid: u32, snapshot: &SchedSnapshot) -> io::Result<()> {
+    unsafe {
+        let param = libc::sched_param { sched_priority: snapshot.rt_priority };
+        if libc::sched_setscheduler(tid as libc::pid_t, snapshot.policy, &param) != 0 {
+            return Err(last_os_error());
+        }
+        if libc::setpriority(libc::PRIO_PROCESS, tid as libc::id_t, snapshot.nice) != 0 {
+            return Err(last_os_error());
+        }
+    }
+    Ok(())
+}
+
+fn set_thread_idle(tid: u32) -> io::Result<IdlePolicy> {
+    unsafe {
+        let param = libc::sched_param { sched_priority: 0 };
+        if libc::sched_setscheduler(tid as libc::pid_t, libc::SCHED_IDLE, &param) == 0 {
+            return Ok(IdlePolicy::SchedIdle);
+        }
+        let err = last_os_error();
+        if err.raw_os_error() != Some(libc::EPERM) {
+            return Err(err);
+        }
+        if libc::setpriority(libc::PRIO_PROCESS, tid as libc::id_t, 19) != 0 {
+            return Err(last_os_error());
+        }
+        if libc::sched_setscheduler(tid as libc::pid_t, libc::SCHED_BATCH, &param) != 0 {
+            return Err(last_os_error());
+        }
+    }
+    Ok(IdlePolicy::BatchNice19)
+}
+
+/// `set_thread_idle` 的两种结果都算空闲
+fn is_idle(snapshot: &SchedSnapshot) -> bool {
+    let policy = snapshot.policy & !libc::SCHED_RESET_ON_FORK;
+    policy == libc::SCHED_IDLE || (policy == libc::SCHED_BATCH && snapshot.nice == 19)
+}
+
+// --- I/O 优先级辅助 ---
+
+fn set_thread_ioprio(tid: u32, class: IoClass) -> io::Result<()> {
+    const IOPRIO_WHO_PROCESS: libc::c_int = 1; // 对 TID 使用时只作用于该线程
+    let ret = unsafe { libc::syscall(libc::SYS_ioprio_set, IOPRIO_WHO_PROCESS, tid as libc::c_int, class.to_ioprio()) };
+    if ret != 0 {
+        return Err(last_os_error());
+    }
+    Ok(())
+}
+
+fn get_thread_ioprio(tid: u32) -> io::Result<IoClass> {
+    const IOPRIO_WHO_PROCESS: libc::c_int = 1;
+    let ret = unsafe { libc::syscall(libc::SYS_ioprio_get, IOPRIO_WHO_PROCESS, tid as libc::c_int) };
+    if ret < 0 {
+        return Err(last_os_error());
+    }
+    Ok(IoClass::from_ioprio(ret as libc::c_int))
+}
+
+// --- 内存辅助 ---
+
+fn page_size() -> u64 {
+    unsafe { libc::sysconf(libc::_SC_PAGESIZE) as u64 }
+}
+
+/// `/proc/<pid>/statm` 的第二列是常驻页数
+fn read_resident(pid: u32) -> Result<u64> {
+    let statm = fs::read_to_string(format!("/proc/{}/statm", pid))
+        .map_err(|e| format!("读取内存信息失败 (PID: {}): {}", pid, e))?;
+    let pages: u64 = statm
+        .split_whitespace()
+        .nth(1)
+        .and_then(|v| v.parse().ok())
+        .ok_or_else(|| format!("无法解析 statm (PID: {})", pid))?;
+    Ok(pages * page_size())
+}
+
+/// 可以换出的映射区间；vdso 之类的特殊映射会让 process_madvise 整批失败，提前排除
+fn pageout_ranges(pid: u32) -> Result<Vec<(usize, usize)>> {
+    let maps = fs::read_to_string(format!("/proc/{}/maps", pid))
+        .map_err(|e| format!("读取内存映射失败 (PID: {}): {}", pid, e))?;
+    let mut ranges = Vec::new();
+    for line in maps.lines() {
+        let mut fields = line.split_whitespace();
+        let (Some(range), Some(perms)) = (fields.next(), fields.next()) else { continue };
+        let name = fields.nth(3).unwrap_or("");
+        if !perms.starts_with('r') || matches!(name, "[vsyscall]" | "[vvar]" | "[vdso]" | "[vvar_vclock]") {
+            continue;
+        }
+        if let Some((start, end)) = range.split_once('-') {
+            if let (Ok(start), Ok(end)) = (usize::from_str_radix(start, 16), usize::from_str_radix(end, 16)) {
+                ranges.push((start, end - start));
+            }
+        }
+    }
+    Ok(ranges)
+}
+
+/// 用 `process_madvise(MADV_PAGEOUT)` 把目标进程的页面换出（需要 Linux 5.10+ 与 CAP_SYS_NICE）
+fn pageout(pid: u32) -> io::Result<()> {
+    let ranges = pageout_ranges(pid).map_err(io::Error::other)?;
+    let pidfd = unsafe { libc::syscall(libc::SYS_pidfd_open, pid as libc::pid_t, 0) };
+    if pidfd < 0 {
+        return Err(last_os_error());
+    }
+    let pidfd = pidfd as libc::c_int;
+
+    let madvise = |iov: &[libc::iovec]| unsafe {
+        libc::syscall(libc::SYS_process_madvise, pidfd, iov.as_ptr(), iov.len(), libc::MADV_PAGEOUT, 0u32)
+    };
+    let iovecs: Vec<libc::iovec> = ranges
+        .iter()
+        .map(|&(base, len)| libc::iovec { iov_base: base as *mut libc::c_void, iov_len: len })
+        .collect();
+
+    let mut result = Ok(());
+    'batches: for batch in iovecs.chunks(libc::UIO_MAXIOV as usize) {
+        let mut rest = batch.to_vec();
+        // 返回值是实际处理的字节数，遇到不能换出的区间会提前停下；跳过已处理的部分接着做
+        while !rest.is_empty() {
+            let done = madvise(&rest);
+            if done < 0 {
+                let err = last_os_error();
+                if err.raw_os_error() != Some(libc::EINVAL) && err.raw_os_error() != Some(libc::ENOMEM) {
+                    result = Err(err);
+                    break 'batches;
+                }
+                // 第一个区间不支持换出（或已被解除映射），跳过它
+                rest.remove(0);
+                continue;
+            }
+            let mut done = done as usize;
+            if done == 0 {
+                rest.remove(0);
+                continue;
+            }
+            while done > 0 && !rest.is_empty() {
+                let first = &mut rest[0];
+                if done < first.iov_len {
+                    first.iov_base = (first.iov_base as usize + done) as *mut libc::c_void;
+                    first.iov_len -= done;
+                    break;
+                }
+                done -= first.iov_len;
+                rest.remove(0);
+            }
+        }
+    }
+    unsafe { libc::close(pidfd) };
+    result
+}
+
+// --- 核心功能封装 ---
+
+impl ProcessController for LinuxController {
+    fn name(&self) -> &'static str {
+        "linux"
+    }
+
+    fn set_cpu_affinity(&self, pid: u32, cpus: &CpuSet) -> Result<()> {
+        if cpus.is_empty() {
+            return Err("CPU 亲和性不能为空".to_string());
+        }
+        self.apply_affinity(pid, cpus)?.into_result("设置 CPU 亲和性", pid)
+    }
+
+    /// 读主线程的亲和性
+    fn cpu_affinity(&self, pid: u32) -> Result<CpuSet> {
+        get_thread_affinity(pid).map_err(|e| format!("读取 CPU 亲和性失败 (PID: {}): {}", pid, e))
+    }
+
+    fn set_idle_priority(&self, pid: u32) -> Result<()> {
+        let (report, _) = self.apply_idle(pid)?;
+        report.into_result("设置进程优先级", pid)
+    }
+
+    fn restore_priority(&self, pid: u32) -> Result<()> {
+        self.restore_sched(pid)?.into_result("恢复进程优先级", pid)
+    }
+
+    fn set_efficiency_mode(&self, pid: u32) -> Result<()> {
+        self.cgroup.restrict(pid)
+    }
+
+    fn clear_efficiency_mode(&self, pid: u32) -> Result<()> {
+        self.cgroup.release(pid)
+    }
+
+    /// 之后 fork 出的子进程天然留在同一个 cgroup 里；已在效率模式的 cgroup 中时不再移动
+    fn contain_tree(&self, pid: u32) -> Result<bool> {
+        if self.cgroup.contains(pid) {
+            return Ok(true);
+        }
+        self.container.restrict(pid).map(|()| true)
+    }
+
+    fn is_idle_priority(&self, pid: u32) -> Result<bool> {
+        read_sched(pid).map(|s| is_idle(&s)).map_err(|e| format!("读取调度策略失败 (PID: {}): {}", pid, e))
+    }
+
+    fn io_priority(&self, pid: u32) -> Result<u32> {
+        get_thread_ioprio(pid).map(IoClass::to_hint).map_err(|e| format!("读取 I/O 优先级失败 (PID: {}): {}", pid, e))
+    }
+
+    /// 效率模式对应受限 cgroup，进程被移出即视为失效
+    fn efficiency_mode(&self, pid: u32) -> Result<bool> {
+        Ok(self.cgroup.contains(pid))
+    }
+
+    /// I/O 优先级只能按 `from_hint` 的三档保存，其他 best-effort 级别恢复为跟随 nice
+    fn snapshot(&self, pid: u32) -> Result<ProcessSnapshot> {
+        let sched = read_sched(pid).map_err(|e| format!("读取调度策略失败 (PID: {}): {}", pid, e))?;
+        Ok(ProcessSnapshot {
+            cpus: get_thread_affinity(pid).ok(),
+            priority: Some(SavedPriority::Sched { policy: sched.policy, rt_priority: sched.rt_priority, nice: sched.nice }),
+            io_priority: get_thread_ioprio(pid).ok().map(IoClass::to_hint),
+            efficiency_mode: None,
+            cgroup: self.cgroup.current_cgroup(pid).ok().map(|p| p.to_string_lossy().into_owned()),
+        })
+    }
+
+    fn restore_snapshot(&self, pid: u32, snapshot: &ProcessSnapshot) -> Result<()> {
+        let mut errors = Vec::new();
+        if let Some(cpus) = &snapshot.cpus {
+            if let Err(e) = self.set_cpu_affinity(pid, cpus) { errors.push(e); }
+        }
+        if let Some(SavedPriority::Sched { policy, rt_priority, nice }) = snapshot.priority {
+            self.saved_sched.lock().unwrap().remove(&pid);
+            let original = SchedSnapshot { policy, rt_priority, nice };
+            match list_threads(pid) {
+                Ok(tids) => {
+                    let mut report = ThreadReport::default();
+                    for tid in tids {
+                        match write_sched(tid, &original) {
+                            Ok(()) => report.applied.push(tid),
+                            Err(e) if e.raw_os_error() == Some(libc::ESRCH) => {}
+                            Err(e) => report.failed.push((tid, e.to_string())),
+                        }
+                    }
+                    if let Err(e) = report.into_result("恢复进程优先级", pid) { errors.push(e); }
+                }
+                Err(e) => errors.push(e),
+            }
+        }
+        if let Some(priority) = snapshot.io_priority {
+            if let Err(e) = self.set_io_priority(pid, priority) { errors.push(e); }
+        }
+        if let Some(cgroup) = &snapshot.cgroup {
+            if let Err(e) = self.cgroup.move_to(pid, Path::new(cgroup)) { errors.push(e); }
+            if let Err(e) = self.container.move_to(pid, Path::new(cgroup)) { errors.push(e); }
+        }
+        if errors.is_empty() { Ok(()) } else { Err(errors.join("; ")) }
+    }
+
+    fn thread_times(&self, pid: u32) -> Result<Vec<ThreadTimes>> {
+        let mut times = Vec::new();
+        for tid in list_threads(pid)? {
+            match read_thread_time(pid, tid) {
+                Ok(cpu_time) => times.push(ThreadTimes { tid, cpu_time }),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => return Err(format!("读取线程 CPU 时间失败 (TID: {}): {}", tid, e)),
+            }
+        }
+        Ok(times)
+    }
+
+    fn set_thread_idle(&self, pid: u32, tid: u32) -> Result<()> {
+        check_thread(pid, tid)?;
+        let mut saved = self.saved_sched.lock().unwrap();
+        if let Entry::Vacant(slot) = saved.entry(pid).or_default().entry(tid) {
+            slot.insert(read_sched(tid).map_err(|e| format!("读取线程调度策略失败 (TID: {}): {}", tid, e))?);
+        }
+        set_thread_idle(tid).map(|_| ()).map_err(|e| format!("降低线程优先级失败 (TID: {}): {}", tid, e))
+    }
+
+    fn restore_thread(&self, pid: u32, tid: u32) -> Result<()> {
+        let snapshot = self.saved_sched.lock().unwrap().get_mut(&pid).and_then(|threads| threads.remove(&tid));
+        let Some(snapshot) = snapshot else {
+            return Ok(());
+        };
+        match write_sched(tid, &snapshot) {
+            Ok(()) => Ok(()),
+            Err(e) if e.raw_os_error() == Some(libc::ESRCH) => Ok(()),
+            Err(e) => Err(format!("恢复线程优先级失败 (TID: {}): {}", tid, e)),
+        }
+    }
+
+    fn set_thread_affinity(&self, pid: u32, tid: u32, cpus: &CpuSet) -> Result<()> {
+        check_thread(pid, tid)?;
+        set_thread_affinity(tid, cpus)
+            .map_err(|e| format!("设置线程亲和性失败 (TID: {}): {}", tid, e))
+    }
+
+    fn set_io_priority(&self, pid: u32, priority: u32) -> Result<()> {
+        self.apply_io_class(pid, IoClass::from_hint(priority))?.into_result("设置 I/O 优先级", pid)
+    }
+
+    fn set_memory_priority(&self, _pid: u32, _priority: u32) -> Result<()> {
+        Err("Linux 暂不支持内存优先级".to_string())
+    }
+
+    fn supports_memory_priority(&self) -> bool {
+        false
+    }
+
+    fn resident_bytes(&self, pid: u32) -> Result<u64> {
+        read_resident(pid)
+    }
+
+    fn trim_working_set(&self, pid: u32) -> Result<()> {
+        match pageout(pid) {
+            Ok(()) => Ok(()),
+            // 没有权限或内核太旧时，如果进程已在效率模式的 cgroup 中，改用 memory.reclaim
+            Err(e) if self.cgroup.contains(pid) => {
+                let resident = read_resident(pid)?;
+                self.cgroup.reclaim(resident).map_err(|r| format!("清理工作集失败 (PID: {}): {}; {}", pid, e, r))
+            }
+            Err(e) => Err(format!("清理工作集失败 (PID: {}): {}", pid, e)),
+        }
+    }
+}
@@ -0,0 +1,200 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+
+use super::{ProcessController, ProcessSnapshot, Result, SavedPriority, ThreadTimes};
+use crate::cpuset::CpuSet;
+
+/// `MockController` 记录下的一次调用
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Call {
+    CpuAffinity { pid: u32, cpus: CpuSet },
+    IdlePriority { pid: u32 },
+    RestorePriority { pid: u32 },
+    EfficiencyMode { pid: u32 },
+    ClearEfficiencyMode { pid: u32 },
+    IoPriority { pid: u32, priority: u32 },
+    MemoryPriority { pid: u32, priority: u32 },
+    TrimWorkingSet { pid: u32 },
+    ContainTree { pid: u32 },
+    RestoreSnapshot { pid: u32 },
+    ThreadIdle { pid: u32, tid: u32 },
+    RestoreThread { pid: u32, tid: u32 },
+    ThreadAffinity { pid: u32, tid: u32, cpus: CpuSet },
+}
+
+/// 内存中的假后端：不碰任何真实进程，只按顺序记录调用
+#[derive(Default)]
+pub struct MockController {
+    calls: Mutex<Vec<Call>>,
+    failing_pids: Mutex<HashSet<u32>>,
+    resident: Mutex<HashMap<u32, u64>>,
+    threads: Mutex<HashMap<u32, Vec<ThreadTimes>>>,
+    affinity: Mutex<HashMap<u32, CpuSet>>,
+    idle: Mutex<HashSet<u32>>,
+    io: Mutex<HashMap<u32, u32>>,
+    eco: Mutex<HashSet<u32>>,
+}
+
+impl MockController {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 到目前为止记录的全部调用
+    pub fn calls(&self) -> Vec<Call> {
+        self.calls.lock().unwrap().clone()
+    }
+
+    /// 让针对该 PID 的后续调用全部失败，用于模拟权限不足
+    pub fn fail_pid(&self, pid: u32) {
+        self.failing_pids.lock().unwrap().insert(pid);
+    }
+
+    /// 设定某个 PID 的常驻内存；修剪后归零
+    pub fn set_resident(&self, pid: u32, bytes: u64) {
+        self.resident.lock().unwrap().insert(pid, bytes);
+    }
+
+    /// 设定 `thread_times` 返回的线程列表
+    pub fn set_threads(&self, pid: u32, threads: Vec<ThreadTimes>) {
+        self.threads.lock().unwrap().insert(pid, threads);
+    }
+
+    fn record(&self, pid: u32, call: Call) -> Result<()> {
+        if self.failing_pids.lock().unwrap().contains(&pid) {
+            return Err(format!("无法打开进程 (PID: {}): mock", pid));
+        }
+        self.calls.lock().unwrap().push(call);
+        Ok(())
+    }
+}
+
+impl ProcessController for MockController {
+    fn name(&self) -> &'static str {
+        "mock"
+    }
+
+    fn set_cpu_affinity(&self, pid: u32, cpus: &CpuSet) -> Result<()> {
+        self.record(pid, Call::CpuAffinity { pid, cpus: cpus.clone() })?;
+        self.affinity.lock().unwrap().insert(pid, cpus.clone());
+        Ok(())
+    }
+
+    /// 没有设置过时视为可以使用本机全部核心
+    fn cpu_affinity(&self, pid: u32) -> Result<CpuSet> {
+        if self.failing_pids.lock().unwrap().contains(&pid) {
+            return Err(format!("无法打开进程 (PID: {}): mock", pid));
+        }
+        Ok(self.affinity.lock().unwrap().get(&pid).cloned().unwrap_or_else(|| crate::topology::current().all()))
+    }
+
+    fn set_idle_priority(&self, pid: u32) -> Result<()> {
+        self.record(pid, Call::IdlePriority { pid })?;
+        self.idle.lock().unwrap().insert(pid);
+        Ok(())
+    }
+
+    fn restore_priority(&self, pid: u32) -> Result<()> {
+        self.record(pid, Call::RestorePriority { pid })?;
+        self.idle.lock().unwrap().remove(&pid);
+        Ok(())
+    }
+
+    fn set_efficiency_mode(&self, pid: u32) -> Result<()> {
+        self.record(pid, Call::EfficiencyMode { pid })?;
+        self.eco.lock().unwrap().insert(pid);
+        Ok(())
+    }
+
+    fn clear_efficiency_mode(&self, pid: u32) -> Result<()> {
+        self.record(pid, Call::ClearEfficiencyMode { pid })?;
+        self.eco.lock().unwrap().remove(&pid);
+        Ok(())
+    }
+
+    fn set_io_priority(&self, pid: u32, priority: u32) -> Result<()> {
+        self.record(pid, Call::IoPriority { pid, priority })?;
+        self.io.lock().unwrap().insert(pid, priority);
+        Ok(())
+    }
+
+    fn set_memory_priority(&self, pid: u32, priority: u32) -> Result<()> {
+        self.record(pid, Call::MemoryPriority { pid, priority })
+    }
+
+    fn resident_bytes(&self, pid: u32) -> Result<u64> {
+        Ok(self.resident.lock().unwrap().get(&pid).copied().unwrap_or(0))
+    }
+
+    fn trim_working_set(&self, pid: u32) -> Result<()> {
+        self.record(pid, Call::TrimWorkingSet { pid })?;
+        self.resident.lock().unwrap().insert(pid, 0);
+        Ok(())
+    }
+
+    /// 模拟没有原生继承能力的平台，让调用方走逐个处理的路径
+    fn contain_tree(&self, pid: u32) -> Result<bool> {
+        self.record(pid, Call::ContainTree { pid })?;
+        Ok(false)
+    }
+
+    fn is_idle_priority(&self, pid: u32) -> Result<bool> {
+        Ok(self.idle.lock().unwrap().contains(&pid))
+    }
+
+    /// 没有设置过时为正常
+    fn io_priority(&self, pid: u32) -> Result<u32> {
+        Ok(self.io.lock().unwrap().get(&pid).copied().unwrap_or(2))
+    }
+
+    fn efficiency_mode(&self, pid: u32) -> Result<bool> {
+        Ok(self.eco.lock().unwrap().contains(&pid))
+    }
+
+    /// 优先级按 Windows 的优先级类表示：空闲 0x40，正常 0x20
+    fn snapshot(&self, pid: u32) -> Result<ProcessSnapshot> {
+        let idle = self.is_idle_priority(pid)?;
+        Ok(ProcessSnapshot {
+            cpus: Some(self.cpu_affinity(pid)?),
+            priority: Some(SavedPriority::Class { class: if idle { 0x40 } else { 0x20 } }),
+            io_priority: Some(self.io_priority(pid)?),
+            efficiency_mode: Some(self.efficiency_mode(pid)?),
+            cgroup: None,
+        })
+    }
+
+    fn restore_snapshot(&self, pid: u32, snapshot: &ProcessSnapshot) -> Result<()> {
+        self.record(pid, Call::RestoreSnapshot { pid })?;
+        if let Some(cpus) = &snapshot.cpus {
+            self.affinity.lock().unwrap().insert(pid, cpus.clone());
+        }
+        if let Some(SavedPriority::Class { class }) = snapshot.priority {
+            let mut idle = self.idle.lock().unwrap();
+            if class == 0x40 { idle.insert(pid); } else { idle.remove(&pid); }
+        }
+        if let Some(priority) = snapshot.io_priority {
+            self.io.lock().unwrap().insert(pid, priority);
+        }
+        if let Some(eco) = snapshot.efficiency_mode {
+            let mut set = self.eco.lock().unwrap();
+            if eco { set.insert(pid); } else { set.remove(&pid); }
+        }
+        Ok(())
+    }
+
+    fn thread_times(&self, pid: u32) -> Result<Vec<ThreadTimes>> {
+        Ok(self.threads.lock().unwrap().get(&pid).cloned().unwrap_or_default())
+    }
+
+    fn set_thread_idle(&self, pid: u32, tid: u32) -> Result<()> {
+        self.record(pid, Call::ThreadIdle { pid, tid })
+    }
+
+    fn restore_thread(&self, pid: u32, tid: u32) -> Result<()> {
+        self.record(pid, Call::RestoreThread { pid, tid })
+    }
+
+    fn set_thread_affinity(&self, pid: u32, tid: u32, cpus: &CpuSet) -> Result<()> {
+        self.record(pid, Call::ThreadAffinity { pid, tid, cpus: cpus.clone() })
+    }
+}
@@ -0,0 +1,242 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use super::{ProcessController, Result};
+use crate::cpuset::CpuSet;
+
+/// 一个线程在两次采样之间的 CPU 占用
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize)]
+pub struct ThreadUsage {
+    pub tid: u32,
+    /// 累计 CPU 时间（毫秒）
+    pub cpu_time_ms: u64,
+    /// 100 表示占满一个逻辑核心；该线程第一次被采样时为 `None`
+    pub cpu_percent: Option<f32>,
+}
+
+/// `demote_hot` 的结果
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct DemoteOutcome {
+    pub demoted: Vec<u32>,
+    pub restored: Vec<u32>,
+}
+
+struct Sample {
+    at: Instant,
+    times: HashMap<u32, Duration>,
+}
+
+/// 按 PID 保存上一次的线程 CPU 时间，计算增量并降级热点线程
+#[derive(Default)]
+pub struct ThreadMonitor {
+    samples: Mutex<HashMap<u32, Sample>>,
+    /// 当前被单独降级的线程
+    demoted: Mutex<HashMap<u32, HashSet<u32>>>,
+}
+
+impl ThreadMonitor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 采样一次并与上一次比较
+    pub fn sample(&self, ctl: &dyn ProcessController, pid: u32) -> Result<Vec<ThreadUsage>> {
+        let now = Instant::now();
+        let current = ctl.thread_times(pid)?;
+        let mut samples = self.samples.lock().unwrap();
+        let previous = samples.get(&pid);
+        let elapsed = previous.map(|p| now.duration_since(p.at).as_secs_f32()).filter(|&s| s > 0.0);
+
+        let usage = current
+            .iter()
+            .map(|t| {
+                let cpu_percent = previous.and_then(|p| p.times.get(&t.tid)).zip(elapsed).map(|(before, secs)| {
+                    t.cpu_time.saturating_sub(*before).as_secs_f32() / secs * 100.0
+                });
+                ThreadUsage { tid: t.tid, cpu_time_ms: t.cpu_time.as_millis() as u64, cpu_percent }
+            })
+            .collect();
+
+        samples.insert(pid, Sample { at: now, times: current.iter().map(|t| (t.tid, t.cpu_time)).collect() });
+        Ok(usage)
+    }
+
+    /// 把占用超过 `above_percent` 的线程降到空闲优先级（可选再绑到 `affinity` 指定的核心），
+    /// 已降级的线程占用回落到阈值一半以下时恢复，避免来回抖动
+    ///
+    /// 亲和性没有记录原值，恢复时只恢复优先级。
+    pub fn demote_hot(
+        &self,
+        ctl: &dyn ProcessController,
+        pid: u32,
+        above_percent: f32,
+        affinity: Option<&CpuSet>,
+    ) -> Result<DemoteOutcome> {
+        let usage = self.sample(ctl, pid)?;
+        let mut outcome = DemoteOutcome::default();
+        let mut errors = Vec::new();
+        let mut demoted = self.demoted.lock().unwrap();
+        let current = demoted.entry(pid).or_default();
+
+        // 已退出的线程直接丢掉记录
+        let alive: HashSet<u32> = usage.iter().map(|u| u.tid).collect();
+        current.retain(|tid| alive.contains(tid));
+
+        for thread in &usage {
+            let Some(percent) = thread.cpu_percent else { continue };
+            if percent > above_percent && !current.contains(&thread.tid) {
+                let mut result = ctl.set_thread_idle(pid, thread.tid);
+                if let Some(cpus) = affinity {
+                    result = result.and_then(|()| ctl.set_thread_affinity(pid, thread.tid, cpus));
+                }
+                match result {
+                    Ok(()) => { current.insert(thread.tid); outcome.demoted.push(thread.tid); }
+                    Err(e) => errors.push(e),
+                }
+            } else if percent < above_percent / 2.0 && current.contains(&thread.tid) {
+                match ctl.restore_thread(pid, thread.tid) {
+                    Ok(()) => { current.remove(&thread.tid); outcome.restored.push(thread.tid); }
+                    Err(e) => errors.push(e),
+                }
+            }
+        }
+
+        if errors.is_empty() { Ok(outcome) } else { Err(errors.join("\n")) }
+    }
+
+    /// 恢复该进程所有被单独降级的线程
+    pub fn restore_all(&self, ctl: &dyn ProcessController, pid: u32) -> Result<()> {
+        let tids = self.demoted.lock().unwrap().remove(&pid).unwrap_or_default();
+        let errors: Vec<String> = tids.into_iter().filter_map(|tid| ctl.restore_thread(pid, tid).err()).collect();
+        if errors.is_empty() { Ok(()) } else { Err(errors.join("\n")) }
+    }
+
+    pub fn demoted(&self, pid: u32) -> Vec<u32> {
+        let mut tids: Vec<u32> = self.demoted.lock().unwrap().get(&pid).into_iter().flatten().copied().collect();
+        tids.sort_unstable();
+        tids
+    }
+
+    /// 丢弃已经退出的进程的记录
+    pub fn forget(&self, pid: u32) {
+        self.samples.lock().unwrap().remove(&pid);
+        self.demoted.lock().unwrap().remove(&pid);
+    }
+}
+
+/// 进程内共享的线程监视器，保证两次调用之间能算出增量
+pub fn monitor() -> &'static ThreadMonitor {
+    static MONITOR: OnceLock<ThreadMonitor> = OnceLock::new();
+    MONITOR.get_or_init(ThreadMonitor::default)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process::mock::{Call, MockController};
+    use crate::process::ThreadTimes;
+
+    const PID: u32 = 10;
+
+    /// 设定各线程的累计 CPU 时间（毫秒）
+    fn set_times(ctl: &MockController, times: &[(u32, u64)]) {
+        let threads = times.iter().map(|&(tid, ms)| ThreadTimes { tid, cpu_time: Duration::from_millis(ms) }).collect();
+        ctl.set_threads(PID, threads);
+    }
+
+    /// 把上一次采样的时间往前挪，模拟两次采样之间过了 `secs` 秒
+    fn elapse(monitor: &ThreadMonitor, secs: u64) {
+        let mut samples = monitor.samples.lock().unwrap();
+        let sample = samples.get_mut(&PID).unwrap();
+        sample.at = sample.at.checked_sub(Duration::from_secs(secs)).unwrap();
+    }
+
+    fn percent(usage: &[ThreadUsage], tid: u32) -> f32 {
+        usage.iter().find(|u| u.tid == tid).and_then(|u| u.cpu_percent).unwrap()
+    }
+
+    #[test]
+    fn sample_computes_usage_between_calls() {
+        let ctl = MockController::new();
+        let monitor = ThreadMonitor::new();
+        set_times(&ctl, &[(1, 1000), (2, 500)]);
+        let first = monitor.sample(&ctl, PID).unwrap();
+        assert!(first.iter().all(|u| u.cpu_percent.is_none()));
+        assert_eq!(first[0].cpu_time_ms, 1000);
+
+        elapse(&monitor, 2);
+        set_times(&ctl, &[(1, 2600), (2, 500), (3, 100)]);
+        let second = monitor.sample(&ctl, PID).unwrap();
+        assert!((percent(&second, 1) - 80.0).abs() < 1.0);
+        assert_eq!(percent(&second, 2), 0.0);
+        // 新出现的线程要到下一次采样才有占用
+        assert_eq!(second.iter().find(|u| u.tid == 3).unwrap().cpu_percent, None);
+    }
+
+    #[test]
+    fn demotes_hot_threads_with_hysteresis() {
+        let ctl = MockController::new();
+        let monitor = ThreadMonitor::new();
+        let cpus: CpuSet = [3].into_iter().collect();
+        set_times(&ctl, &[(1, 0), (2, 0)]);
+        assert_eq!(monitor.demote_hot(&ctl, PID, 50.0, Some(&cpus)).unwrap(), DemoteOutcome::default());
+
+        // 线程 1 占用 90%，超过阈值
+        elapse(&monitor, 1);
+        set_times(&ctl, &[(1, 900), (2, 100)]);
+        let outcome = monitor.demote_hot(&ctl, PID, 50.0, Some(&cpus)).unwrap();
+        assert_eq!(outcome, DemoteOutcome { demoted: vec![1], restored: vec![] });
+        assert_eq!(
+            ctl.calls(),
+            vec![Call::ThreadIdle { pid: PID, tid: 1 }, Call::ThreadAffinity { pid: PID, tid: 1, cpus: cpus.clone() }]
+        );
+
+        // 回落到 40%：低于阈值但高于一半，保持降级
+        elapse(&monitor, 1);
+        set_times(&ctl, &[(1, 1300), (2, 200)]);
+        assert_eq!(monitor.demote_hot(&ctl, PID, 50.0, Some(&cpus)).unwrap(), DemoteOutcome::default());
+        assert_eq!(monitor.demoted(PID), vec![1]);
+
+        // 回落到 10%：低于一半，恢复
+        elapse(&monitor, 1);
+        set_times(&ctl, &[(1, 1400), (2, 300)]);
+        let outcome = monitor.demote_hot(&ctl, PID, 50.0, None).unwrap();
+        assert_eq!(outcome, DemoteOutcome { demoted: vec![], restored: vec![1] });
+        assert_eq!(ctl.calls().last(), Some(&Call::RestoreThread { pid: PID, tid: 1 }));
+        assert!(monitor.demoted(PID).is_empty());
+    }
+
+    #[test]
+    fn restore_all_and_exited_threads() {
+        let ctl = MockController::new();
+        let monitor = ThreadMonitor::new();
+        set_times(&ctl, &[(1, 0), (2, 0), (3, 0)]);
+        monitor.demote_hot(&ctl, PID, 50.0, None).unwrap();
+        elapse(&monitor, 1);
+        set_times(&ctl, &[(1, 900), (2, 900), (3, 0)]);
+        assert_eq!(monitor.demote_hot(&ctl, PID, 50.0, None).unwrap().demoted.len(), 2);
+
+        // 线程 2 退出后记录随之丢弃，恢复时不再碰它
+        elapse(&monitor, 1);
+        set_times(&ctl, &[(1, 1800), (3, 0)]);
+        monitor.demote_hot(&ctl, PID, 50.0, None).unwrap();
+        assert_eq!(monitor.demoted(PID), vec![1]);
+        monitor.restore_all(&ctl, PID).unwrap();
+        assert_eq!(ctl.calls().last(), Some(&Call::RestoreThread { pid: PID, tid: 1 }));
+        assert!(monitor.demoted(PID).is_empty());
+    }
+
+    #[test]
+    fn failures_are_reported_and_not_recorded() {
+        let ctl = MockController::new();
+        let monitor = ThreadMonitor::new();
+        set_times(&ctl, &[(1, 0)]);
+        monitor.demote_hot(&ctl, PID, 50.0, None).unwrap();
+        elapse(&monitor, 1);
+        set_times(&ctl, &[(1, 900)]);
+        ctl.fail_pid(PID);
+        assert!(monitor.demote_hot(&ctl, PID, 50.0, None).is_err());
+        assert!(monitor.demoted(PID).is_empty());
+    }
+}
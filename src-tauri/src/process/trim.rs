@@ -0,0 +1,179 @@
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use super::{ProcessController, Result};
+
+/// 工作集修剪的触发条件，对应 `targets.toml` 中的 `[trim]`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TrimPolicy {
+    /// 常驻内存低于该值（MB）时不修剪
+    pub min_resident_mb: u64,
+    /// 同一进程两次修剪之间的最短间隔（秒）
+    pub interval_secs: u64,
+}
+
+impl TrimPolicy {
+    pub fn min_resident_bytes(&self) -> u64 {
+        self.min_resident_mb.saturating_mul(1024 * 1024)
+    }
+
+    pub fn min_interval(&self) -> Duration {
+        Duration::from_secs(self.interval_secs)
+    }
+}
+
+impl Default for TrimPolicy {
+    fn default() -> Self {
+        Self { min_resident_mb: 64, interval_secs: 60 }
+    }
+}
+
+/// 一次修剪请求的结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(tag = "result", rename_all = "snake_case")]
+pub enum TrimOutcome {
+    Trimmed { before: u64, after: u64, reclaimed: u64 },
+    /// 常驻内存没有超过阈值
+    BelowThreshold { resident: u64 },
+    /// 距离上次修剪太近
+    RateLimited,
+}
+
+impl TrimOutcome {
+    pub fn reclaimed(&self) -> u64 {
+        match self {
+            TrimOutcome::Trimmed { reclaimed, .. } => *reclaimed,
+            _ => 0,
+        }
+    }
+}
+
+/// 按阈值和频率限制执行工作集修剪，并统计回收的字节数
+#[derive(Default)]
+pub struct Trimmer {
+    policy: Mutex<TrimPolicy>,
+    last_trim: Mutex<HashMap<u32, Instant>>,
+}
+
+impl Trimmer {
+    pub fn new(policy: TrimPolicy) -> Self {
+        Self { policy: Mutex::new(policy), last_trim: Mutex::new(HashMap::new()) }
+    }
+
+    pub fn policy(&self) -> TrimPolicy {
+        *self.policy.lock().unwrap()
+    }
+
+    pub fn set_policy(&self, policy: TrimPolicy) {
+        *self.policy.lock().unwrap() = policy;
+    }
+
+    pub fn trim(&self, ctl: &dyn ProcessController, pid: u32) -> Result<TrimOutcome> {
+        let policy = self.policy();
+        let now = Instant::now();
+        {
+            let mut last_trim = self.last_trim.lock().unwrap();
+            // 过了间隔的记录已经不影响判断，顺带清掉，免得退出的进程越积越多
+            last_trim.retain(|_, last| now.duration_since(*last) < policy.min_interval());
+            if last_trim.contains_key(&pid) {
+                return Ok(TrimOutcome::RateLimited);
+            }
+        }
+
+        let before = ctl.resident_bytes(pid)?;
+        if before < policy.min_resident_bytes() {
+            return Ok(TrimOutcome::BelowThreshold { resident: before });
+        }
+
+        self.last_trim.lock().unwrap().insert(pid, now);
+        ctl.trim_working_set(pid)?;
+        let after = ctl.resident_bytes(pid)?;
+        Ok(TrimOutcome::Trimmed { before, after, reclaimed: before.saturating_sub(after) })
+    }
+
+    /// 丢弃已经退出或被恢复的进程的记录，PID 被复用时新进程不受频率限制
+    pub fn forget(&self, pid: u32) {
+        self.last_trim.lock().unwrap().remove(&pid);
+    }
+}
+
+/// 进程内共享的修剪器，保证频率限制跨多次调用生效；条件取自目标列表
+pub fn trimmer() -> &'static Trimmer {
+    static TRIMMER: OnceLock<Trimmer> = OnceLock::new();
+    TRIMMER.get_or_init(|| Trimmer::new(crate::targets::store().config().trim))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process::mock::{Call, MockController};
+
+    const MB: u64 = 1024 * 1024;
+
+    fn trimmer(min_resident_mb: u64, interval_secs: u64) -> Trimmer {
+        Trimmer::new(TrimPolicy { min_resident_mb, interval_secs })
+    }
+
+    #[test]
+    fn skips_processes_below_threshold() {
+        let ctl = MockController::new();
+        ctl.set_resident(1, 10 * MB);
+        let outcome = trimmer(64, 60).trim(&ctl, 1).unwrap();
+        assert_eq!(outcome, TrimOutcome::BelowThreshold { resident: 10 * MB });
+        assert_eq!(outcome.reclaimed(), 0);
+        assert!(ctl.calls().is_empty());
+    }
+
+    #[test]
+    fn trims_and_reports_reclaimed_bytes() {
+        let ctl = MockController::new();
+        ctl.set_resident(2, 200 * MB);
+        let outcome = trimmer(64, 60).trim(&ctl, 2).unwrap();
+        assert_eq!(outcome, TrimOutcome::Trimmed { before: 200 * MB, after: 0, reclaimed: 200 * MB });
+        assert_eq!(ctl.calls(), vec![Call::TrimWorkingSet { pid: 2 }]);
+    }
+
+    #[test]
+    fn rate_limits_each_process_until_forgotten() {
+        let ctl = MockController::new();
+        let trimmer = trimmer(64, 60);
+        for pid in [3, 4] {
+            ctl.set_resident(pid, 100 * MB);
+            assert_eq!(trimmer.trim(&ctl, pid).unwrap().reclaimed(), 100 * MB);
+        }
+        ctl.set_resident(3, 100 * MB);
+        assert_eq!(trimmer.trim(&ctl, 3).unwrap(), TrimOutcome::RateLimited);
+
+        // 进程退出后 PID 被复用，新进程不受上一次修剪的限制
+        trimmer.forget(3);
+        assert_eq!(trimmer.trim(&ctl, 3).unwrap().reclaimed(), 100 * MB);
+        assert_eq!(ctl.calls().len(), 3);
+    }
+
+    #[test]
+    fn prunes_entries_older_than_interval() {
+        let ctl = MockController::new();
+        let trimmer = trimmer(0, 60);
+        for pid in [5, 6] {
+            trimmer.trim(&ctl, pid).unwrap();
+        }
+        assert_eq!(trimmer.last_trim.lock().unwrap().len(), 2);
+
+        trimmer.set_policy(TrimPolicy { min_resident_mb: 0, interval_secs: 0 });
+        trimmer.trim(&ctl, 7).unwrap();
+        assert_eq!(trimmer.last_trim.lock().unwrap().keys().copied().collect::<Vec<_>>(), vec![7]);
+        assert!(matches!(trimmer.trim(&ctl, 5).unwrap(), TrimOutcome::Trimmed { .. }));
+    }
+
+    #[test]
+    fn policy_reads_partial_config() {
+        let policy: TrimPolicy = toml::from_str("interval_secs = 5").unwrap();
+        assert_eq!(policy, TrimPolicy { min_resident_mb: 64, interval_secs: 5 });
+        assert_eq!(policy.min_resident_bytes(), 64 * MB);
+        assert_eq!(TrimPolicy { min_resident_mb: u64::MAX, interval_secs: 0 }.min_resident_bytes(), u64::MAX);
+    }
+}
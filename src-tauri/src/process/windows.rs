@@ -0,0 +1,514 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use windows::Win32::Foundation::{CloseHandle, FILETIME, HANDLE, NTSTATUS};
+use windows::Win32::System::Diagnostics::ToolHelp::{
+    CreateToolhelp32Snapshot, Thread32First, Thread32Next, TH32CS_SNAPTHREAD, THREADENTRY32,
+};
+use windows::Win32::System::SystemInformation::{
+    GetSystemCpuSetInformation, CpuSetInformation, GROUP_AFFINITY, SYSTEM_CPU_SET_INFORMATION,
+};
+use windows::Win32::System::ProcessStatus::{EmptyWorkingSet, GetProcessMemoryInfo, PROCESS_MEMORY_COUNTERS};
+use windows::Win32::System::Threading::{
+    GetPriorityClass, GetProcessAffinityMask, GetProcessInformation, GetProcessDefaultCpuSets, GetProcessGroupAffinity, GetThreadPriority, GetThreadTimes, OpenProcess, OpenThread, SetPriorityClass,
+    SetProcessAffinityMask, SetProcessDefaultCpuSets, SetProcessInformation, SetThreadGroupAffinity,
+    SetThreadPriority, SetThreadSelectedCpuSets,
+    IDLE_PRIORITY_CLASS, THREAD_ACCESS_RIGHTS, THREAD_PRIORITY, THREAD_PRIORITY_IDLE,
+    THREAD_QUERY_INFORMATION, THREAD_QUERY_LIMITED_INFORMATION, THREAD_SET_INFORMATION, MEMORY_PRIORITY, MEMORY_PRIORITY_INFORMATION, PROCESS_ACCESS_RIGHTS,
+    PROCESS_CREATION_FLAGS, PROCESS_POWER_THROTTLING_EXECUTION_SPEED,
+    PROCESS_POWER_THROTTLING_IGNORE_TIMER_RESOLUTION, PROCESS_POWER_THROTTLING_STATE,
+    PROCESS_QUERY_INFORMATION, PROCESS_QUERY_LIMITED_INFORMATION, PROCESS_SET_INFORMATION, PROCESS_SET_LIMITED_INFORMATION, PROCESS_SET_QUOTA,
+    PROCESS_VM_READ,
+    ProcessMemoryPriority, ProcessPowerThrottling,
+};
+
+use super::{ProcessController, ProcessSnapshot, Result, SavedPriority, ThreadTimes};
+use crate::cpuset::CpuSet;
+use crate::topology;
+
+// SetProcessInformation 不接受 ProcessIoPriority，只能走 ntdll；windows crate 0.58 没有导出它
+#[link(name = "ntdll")]
+extern "system" {
+    fn NtSetInformationProcess(
+        process: HANDLE,
+        class: u32,
+        information: *const std::ffi::c_void,
+        length: u32,
+    ) -> NTSTATUS;
+
+    fn NtQueryInformationProcess(
+        process: HANDLE,
+        class: u32,
+        information: *mut std::ffi::c_void,
+        length: u32,
+        return_length: *mut u32,
+    ) -> NTSTATUS;
+}
+
+const PROCESS_IO_PRIORITY: u32 = 33; // PROCESSINFOCLASS::ProcessIoPriority
+
+/// RAII 包装器：确保 Handle 总是被关闭
+struct ScopedHandle(HANDLE);
+
+impl Drop for ScopedHandle {
+    fn drop(&mut self) {
+        if !self.0.is_invalid() {
+            unsafe { let _ = CloseHandle(self.0); }
+        }
+    }
+}
+
+impl ScopedHandle {
+    fn open(pid: u32) -> Result<Self> {
+        Self::open_with(pid, PROCESS_SET_INFORMATION | PROCESS_QUERY_INFORMATION)
+    }
+
+    fn open_with(pid: u32, access: PROCESS_ACCESS_RIGHTS) -> Result<Self> {
+        unsafe {
+            let handle = OpenProcess(access, false, pid)
+                .map_err(|e| format!("无法打开进程 (PID: {}): {}", pid, e))?;
+
+            if handle.is_invalid() {
+                return Err("进程句柄无效".to_string());
+            }
+            Ok(Self(handle))
+        }
+    }
+
+    fn open_thread(tid: u32, access: THREAD_ACCESS_RIGHTS) -> Result<Self> {
+        unsafe {
+            let handle = OpenThread(access, false, tid)
+                .map_err(|e| format!("无法打开线程 (TID: {}): {}", tid, e))?;
+            Ok(Self(handle))
+        }
+    }
+
+    // 获取原始 Handle 用于 API 调用
+    fn raw(&self) -> HANDLE {
+        self.0
+    }
+}
+
+// --- 处理器组与 CPU Sets ---
+
+/// 全部核心都在 0 号处理器组、要设置的核心也在其中时返回该组的掩码
+fn single_group(cpus: &CpuSet) -> Option<(u16, u64)> {
+    if topology::current().all().groups().count() > 1 {
+        return None;
+    }
+    match cpus.groups().collect::<Vec<_>>().as_slice() {
+        [(0, mask)] => Some((0, *mask)),
+        _ => None,
+    }
+}
+
+/// 全局编号（组号 * 64 + 组内序号）到 CPU Set ID 的映射
+fn cpu_set_table() -> Result<HashMap<usize, u32>> {
+    let mut length = 0u32;
+    unsafe {
+        let _ = GetSystemCpuSetInformation(None, 0, &mut length, HANDLE::default(), 0);
+    }
+    if length == 0 {
+        return Err("GetSystemCpuSetInformation 未返回数据".to_string());
+    }
+    let mut buffer = vec![0u8; length as usize];
+    unsafe {
+        GetSystemCpuSetInformation(
+            Some(buffer.as_mut_ptr() as *mut SYSTEM_CPU_SET_INFORMATION),
+            length,
+            &mut length,
+            HANDLE::default(),
+            0,
+        )
+        .ok()
+        .map_err(|e| format!("读取 CPU Sets 失败: {}", e))?;
+    }
+
+    let mut table = HashMap::new();
+    let mut offset = 0usize;
+    while offset < length as usize {
+        let info = unsafe { &*(buffer.as_ptr().add(offset) as *const SYSTEM_CPU_SET_INFORMATION) };
+        if info.Size == 0 {
+            break;
+        }
+        if info.Type == CpuSetInformation {
+            let cpu = unsafe { info.Anonymous.CpuSet };
+            table.insert(cpu.Group as usize * 64 + cpu.LogicalProcessorIndex as usize, cpu.Id);
+        }
+        offset += info.Size as usize;
+    }
+    Ok(table)
+}
+
+fn to_cpu_set_ids(cpus: &CpuSet) -> Result<Vec<u32>> {
+    let table = cpu_set_table()?;
+    cpus.iter().map(|cpu| table.get(&cpu).copied().ok_or_else(|| format!("逻辑核心 {} 不存在", cpu))).collect()
+}
+
+fn from_cpu_set_ids(ids: &[u32]) -> Result<CpuSet> {
+    let table = cpu_set_table()?;
+    Ok(table.into_iter().filter(|(_, id)| ids.contains(id)).map(|(cpu, _)| cpu).collect())
+}
+
+fn set_power_throttling(pid: u32, control_mask: u32, state_mask: u32) -> Result<()> {
+    let handle = ScopedHandle::open(pid)?;
+    unsafe {
+        let mut policy = PROCESS_POWER_THROTTLING_STATE {
+            Version: 1,
+            ControlMask: control_mask,
+            StateMask: state_mask,
+        };
+
+        SetProcessInformation(
+            handle.raw(),
+            ProcessPowerThrottling,
+            &mut policy as *mut _ as *mut _,
+            std::mem::size_of::<PROCESS_POWER_THROTTLING_STATE>() as u32,
+        ).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// 用 ToolHelp 快照列出属于该进程的线程 ID
+fn list_threads(pid: u32) -> Result<Vec<u32>> {
+    unsafe {
+        let snapshot = ScopedHandle(
+            CreateToolhelp32Snapshot(TH32CS_SNAPTHREAD, 0).map_err(|e| format!("创建线程快照失败: {}", e))?,
+        );
+        let mut entry = THREADENTRY32 { dwSize: std::mem::size_of::<THREADENTRY32>() as u32, ..Default::default() };
+        let mut tids = Vec::new();
+        let mut next = Thread32First(snapshot.raw(), &mut entry);
+        while next.is_ok() {
+            if entry.th32OwnerProcessID == pid {
+                tids.push(entry.th32ThreadID);
+            }
+            next = Thread32Next(snapshot.raw(), &mut entry);
+        }
+        Ok(tids)
+    }
+}
+
+/// 线程 ID 会被复用，操作前确认它仍属于该进程
+fn check_thread(pid: u32, tid: u32) -> Result<()> {
+    if list_threads(pid)?.contains(&tid) {
+        Ok(())
+    } else {
+        Err(format!("线程不存在 (PID: {}, TID: {})", pid, tid))
+    }
+}
+
+fn filetime_to_duration(time: FILETIME) -> Duration {
+    // FILETIME 的单位是 100 纳秒
+    let ticks = ((time.dwHighDateTime as u64) << 32) | time.dwLowDateTime as u64;
+    Duration::from_nanos(ticks.saturating_mul(100))
+}
+
+/// Win32 后端
+#[derive(Default)]
+pub struct WindowsController {
+    /// 第一次降级前的优先级类，用于原样恢复
+    saved_priority: Mutex<HashMap<u32, u32>>,
+    /// 单独降级过的线程原来的优先级，按 PID -> TID 保存
+    saved_thread_priority: Mutex<HashMap<u32, HashMap<u32, i32>>>,
+}
+
+impl WindowsController {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+// --- 核心功能封装 ---
+
+impl ProcessController for WindowsController {
+    fn name(&self) -> &'static str {
+        "windows"
+    }
+
+    /// 只有一个处理器组时沿用传统的亲和性掩码，否则改用可以跨组的 CPU Sets
+    fn set_cpu_affinity(&self, pid: u32, cpus: &CpuSet) -> Result<()> {
+        if cpus.is_empty() {
+            return Err("CPU 亲和性不能为空".to_string());
+        }
+        let access = PROCESS_SET_INFORMATION | PROCESS_QUERY_INFORMATION | PROCESS_SET_LIMITED_INFORMATION;
+        let handle = ScopedHandle::open_with(pid, access)?;
+        unsafe {
+            if let Some((_, mask)) = single_group(cpus) {
+                SetProcessAffinityMask(handle.raw(), mask as usize)
+                    .map_err(|e| format!("设置 CPU 亲和性失败: {}", e))?;
+            } else {
+                let ids = to_cpu_set_ids(cpus)?;
+                SetProcessDefaultCpuSets(handle.raw(), Some(&ids))
+                    .ok()
+                    .map_err(|e| format!("设置 CPU Sets 失败: {}", e))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 设置过 CPU Sets 时以它为准，否则读所在处理器组的亲和性掩码
+    fn cpu_affinity(&self, pid: u32) -> Result<CpuSet> {
+        let handle = ScopedHandle::open_with(pid, PROCESS_QUERY_LIMITED_INFORMATION)?;
+        unsafe {
+            let mut count = 0u32;
+            let _ = GetProcessDefaultCpuSets(handle.raw(), None, &mut count);
+            if count > 0 {
+                let mut ids = vec![0u32; count as usize];
+                GetProcessDefaultCpuSets(handle.raw(), Some(&mut ids), &mut count)
+                    .ok()
+                    .map_err(|e| format!("读取 CPU Sets 失败: {}", e))?;
+                return from_cpu_set_ids(&ids[..count as usize]);
+            }
+
+            let mut groups = [0u16; 64];
+            let mut group_count = groups.len() as u16;
+            GetProcessGroupAffinity(handle.raw(), &mut group_count, groups.as_mut_ptr())
+                .ok()
+                .map_err(|e| format!("读取处理器组失败: {}", e))?;
+            let groups = &groups[..group_count as usize];
+            if groups.len() != 1 {
+                // 跨组的进程没有单一的掩码，视为可以使用这些组里的全部核心
+                return Ok(topology::current().all().iter().filter(|cpu| groups.contains(&((cpu / 64) as u16))).collect());
+            }
+
+            let (mut process_mask, mut system_mask) = (0usize, 0usize);
+            GetProcessAffinityMask(handle.raw(), &mut process_mask, &mut system_mask)
+                .map_err(|e| format!("读取 CPU 亲和性失败: {}", e))?;
+            let base = groups[0] as usize * 64;
+            Ok((0..usize::BITS as usize).filter(|bit| process_mask & (1usize << bit) != 0).map(|bit| base + bit).collect())
+        }
+    }
+
+    fn set_idle_priority(&self, pid: u32) -> Result<()> {
+        let handle = ScopedHandle::open(pid)?;
+        unsafe {
+            let previous = GetPriorityClass(handle.raw());
+            if previous != 0 {
+                self.saved_priority.lock().unwrap().entry(pid).or_insert(previous);
+            }
+            SetPriorityClass(handle.raw(), IDLE_PRIORITY_CLASS)
+                .map_err(|e| format!("设置进程优先级失败: {}", e))?;
+        }
+        Ok(())
+    }
+
+    fn restore_priority(&self, pid: u32) -> Result<()> {
+        let Some(previous) = self.saved_priority.lock().unwrap().remove(&pid) else {
+            return Ok(());
+        };
+        let handle = ScopedHandle::open(pid)?;
+        unsafe {
+            SetPriorityClass(handle.raw(), PROCESS_CREATION_FLAGS(previous))
+                .map_err(|e| format!("恢复进程优先级失败: {}", e))?;
+        }
+        Ok(())
+    }
+
+    fn set_efficiency_mode(&self, pid: u32) -> Result<()> {
+        let mask = PROCESS_POWER_THROTTLING_EXECUTION_SPEED | PROCESS_POWER_THROTTLING_IGNORE_TIMER_RESOLUTION;
+        set_power_throttling(pid, mask, mask).map_err(|e| format!("设置效率模式失败: {}", e))
+    }
+
+    fn clear_efficiency_mode(&self, pid: u32) -> Result<()> {
+        // ControlMask 为 0 表示不再干预，由系统自行决定是否节流
+        set_power_throttling(pid, 0, 0).map_err(|e| format!("关闭效率模式失败: {}", e))
+    }
+
+    fn set_io_priority(&self, pid: u32, priority: u32) -> Result<()> {
+        let handle = ScopedHandle::open(pid)?;
+        let status = unsafe {
+            NtSetInformationProcess(
+                handle.raw(),
+                PROCESS_IO_PRIORITY,
+                &priority as *const _ as *const _,
+                std::mem::size_of::<u32>() as u32,
+            )
+        };
+        if status.0 < 0 {
+            return Err(format!("设置 I/O 优先级失败: NTSTATUS 0x{:08X}", status.0 as u32));
+        }
+        Ok(())
+    }
+
+    fn is_idle_priority(&self, pid: u32) -> Result<bool> {
+        let handle = ScopedHandle::open_with(pid, PROCESS_QUERY_LIMITED_INFORMATION)?;
+        let class = unsafe { GetPriorityClass(handle.raw()) };
+        if class == 0 {
+            return Err(format!("读取进程优先级失败: {}", windows::core::Error::from_win32()));
+        }
+        Ok(class == IDLE_PRIORITY_CLASS.0)
+    }
+
+    fn io_priority(&self, pid: u32) -> Result<u32> {
+        let handle = ScopedHandle::open_with(pid, PROCESS_QUERY_INFORMATION)?;
+        let mut priority = 0u32;
+        let mut returned = 0u32;
+        let status = unsafe {
+            NtQueryInformationProcess(
+                handle.raw(),
+                PROCESS_IO_PRIORITY,
+                &mut priority as *mut _ as *mut _,
+                std::mem::size_of::<u32>() as u32,
+                &mut returned,
+            )
+        };
+        if status.0 < 0 {
+            return Err(format!("读取 I/O 优先级失败: NTSTATUS 0x{:08X}", status.0 as u32));
+        }
+        Ok(priority)
+    }
+
+    /// 只看执行速度节流：控制位和状态位都打开才算处于效率模式
+    fn efficiency_mode(&self, pid: u32) -> Result<bool> {
+        let handle = ScopedHandle::open_with(pid, PROCESS_QUERY_LIMITED_INFORMATION)?;
+        let mut state = PROCESS_POWER_THROTTLING_STATE { Version: 1, ControlMask: 0, StateMask: 0 };
+        unsafe {
+            GetProcessInformation(
+                handle.raw(),
+                ProcessPowerThrottling,
+                &mut state as *mut _ as *mut _,
+                std::mem::size_of::<PROCESS_POWER_THROTTLING_STATE>() as u32,
+            )
+            .map_err(|e| format!("读取效率模式失败: {}", e))?;
+        }
+        let speed = PROCESS_POWER_THROTTLING_EXECUTION_SPEED;
+        Ok(state.ControlMask & speed != 0 && state.StateMask & speed != 0)
+    }
+
+    fn snapshot(&self, pid: u32) -> Result<ProcessSnapshot> {
+        let handle = ScopedHandle::open_with(pid, PROCESS_QUERY_LIMITED_INFORMATION)?;
+        let class = unsafe { GetPriorityClass(handle.raw()) };
+        if class == 0 {
+            return Err(format!("读取进程优先级失败: {}", windows::core::Error::from_win32()));
+        }
+        Ok(ProcessSnapshot {
+            cpus: self.cpu_affinity(pid).ok(),
+            priority: Some(SavedPriority::Class { class }),
+            io_priority: self.io_priority(pid).ok(),
+            efficiency_mode: self.efficiency_mode(pid).ok(),
+            cgroup: None,
+        })
+    }
+
+    fn restore_snapshot(&self, pid: u32, snapshot: &ProcessSnapshot) -> Result<()> {
+        let mut errors = Vec::new();
+        if let Some(cpus) = &snapshot.cpus {
+            if let Err(e) = self.set_cpu_affinity(pid, cpus) { errors.push(e); }
+        }
+        if let Some(SavedPriority::Class { class }) = snapshot.priority {
+            self.saved_priority.lock().unwrap().remove(&pid);
+            let result = ScopedHandle::open(pid).and_then(|handle| unsafe {
+                SetPriorityClass(handle.raw(), PROCESS_CREATION_FLAGS(class)).map_err(|e| format!("恢复进程优先级失败: {}", e))
+            });
+            if let Err(e) = result { errors.push(e); }
+        }
+        if let Some(priority) = snapshot.io_priority {
+            if let Err(e) = self.set_io_priority(pid, priority) { errors.push(e); }
+        }
+        let eco = match snapshot.efficiency_mode {
+            Some(true) => self.set_efficiency_mode(pid),
+            Some(false) => self.clear_efficiency_mode(pid),
+            None => Ok(()),
+        };
+        if let Err(e) = eco { errors.push(e); }
+        if errors.is_empty() { Ok(()) } else { Err(errors.join("; ")) }
+    }
+
+    fn set_memory_priority(&self, pid: u32, priority: u32) -> Result<()> {
+        let handle = ScopedHandle::open(pid)?;
+        unsafe {
+            let info = MEMORY_PRIORITY_INFORMATION { MemoryPriority: MEMORY_PRIORITY(priority) };
+            SetProcessInformation(
+                handle.raw(),
+                ProcessMemoryPriority,
+                &info as *const _ as *const _,
+                std::mem::size_of::<MEMORY_PRIORITY_INFORMATION>() as u32,
+            ).map_err(|e| format!("设置内存优先级失败: {}", e))?;
+        }
+        Ok(())
+    }
+
+    fn resident_bytes(&self, pid: u32) -> Result<u64> {
+        let handle = ScopedHandle::open_with(pid, PROCESS_QUERY_INFORMATION | PROCESS_VM_READ)?;
+        unsafe {
+            let cb = std::mem::size_of::<PROCESS_MEMORY_COUNTERS>() as u32;
+            let mut counters = PROCESS_MEMORY_COUNTERS { cb, ..Default::default() };
+            GetProcessMemoryInfo(handle.raw(), &mut counters, cb)
+                .map_err(|e| format!("读取内存信息失败: {}", e))?;
+            Ok(counters.WorkingSetSize as u64)
+        }
+    }
+
+    fn trim_working_set(&self, pid: u32) -> Result<()> {
+        let handle = ScopedHandle::open_with(pid, PROCESS_SET_QUOTA | PROCESS_QUERY_INFORMATION)?;
+        unsafe {
+            EmptyWorkingSet(handle.raw())
+                .map_err(|e| format!("清理工作集失败: {}", e))?;
+        }
+        Ok(())
+    }
+
+    fn thread_times(&self, pid: u32) -> Result<Vec<ThreadTimes>> {
+        let mut times = Vec::new();
+        for tid in list_threads(pid)? {
+            // 线程可能已经退出，跳过即可
+            let Ok(handle) = ScopedHandle::open_thread(tid, THREAD_QUERY_LIMITED_INFORMATION) else { continue };
+            let (mut created, mut exited) = (FILETIME::default(), FILETIME::default());
+            let (mut kernel, mut user) = (FILETIME::default(), FILETIME::default());
+            unsafe {
+                if GetThreadTimes(handle.raw(), &mut created, &mut exited, &mut kernel, &mut user).is_err() {
+                    continue;
+                }
+            }
+            times.push(ThreadTimes { tid, cpu_time: filetime_to_duration(kernel) + filetime_to_duration(user) });
+        }
+        Ok(times)
+    }
+
+    fn set_thread_idle(&self, pid: u32, tid: u32) -> Result<()> {
+        check_thread(pid, tid)?;
+        let handle = ScopedHandle::open_thread(tid, THREAD_SET_INFORMATION | THREAD_QUERY_INFORMATION)?;
+        unsafe {
+            // THREAD_PRIORITY_ERROR_RETURN
+            let previous = GetThreadPriority(handle.raw());
+            if previous != 0x7FFF_FFFF {
+                self.saved_thread_priority.lock().unwrap().entry(pid).or_default().entry(tid).or_insert(previous);
+            }
+            SetThreadPriority(handle.raw(), THREAD_PRIORITY_IDLE)
+                .map_err(|e| format!("降低线程优先级失败 (TID: {}): {}", tid, e))
+        }
+    }
+
+    fn restore_thread(&self, pid: u32, tid: u32) -> Result<()> {
+        let previous = self.saved_thread_priority.lock().unwrap().get_mut(&pid).and_then(|threads| threads.remove(&tid));
+        let Some(previous) = previous else {
+            return Ok(());
+        };
+        let Ok(handle) = ScopedHandle::open_thread(tid, THREAD_SET_INFORMATION | THREAD_QUERY_INFORMATION) else {
+            // 线程已退出
+            return Ok(());
+        };
+        unsafe {
+            SetThreadPriority(handle.raw(), THREAD_PRIORITY(previous))
+                .map_err(|e| format!("恢复线程优先级失败 (TID: {}): {}", tid, e))
+        }
+    }
+
+    /// 落在同一个处理器组里时用组亲和性，否则用线程的 CPU Sets
+    fn set_thread_affinity(&self, pid: u32, tid: u32, cpus: &CpuSet) -> Result<()> {
+        check_thread(pid, tid)?;
+        let handle = ScopedHandle::open_thread(tid, THREAD_SET_INFORMATION | THREAD_QUERY_INFORMATION)?;
+        let result = unsafe {
+            match cpus.groups().collect::<Vec<_>>().as_slice() {
+                [] => return Err("CPU 亲和性不能为空".to_string()),
+                [(group, mask)] => {
+                    let affinity = GROUP_AFFINITY { Mask: *mask as usize, Group: *group, Reserved: [0; 3] };
+                    SetThreadGroupAffinity(handle.raw(), &affinity, None).ok()
+                }
+                _ => SetThreadSelectedCpuSets(handle.raw(), &to_cpu_set_ids(cpus)?).ok(),
+            }
+        };
+        result.map_err(|e| format!("设置线程亲和性失败 (TID: {}): {}", tid, e))
+    }
+}
@@ -0,0 +1,215 @@
+use std::fs;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use serde::{Deserialize, Serialize};
+
+use crate::paths;
+use crate::policy::PolicyStore;
+use crate::registry::{self, GameConfig};
+use crate::transaction::Transaction;
+
+type Result<T> = std::result::Result<T, String>;
+
+/// 随程序发布的内置配置
+const BUILTIN_CATALOG: &str = include_str!("../profiles/catalog.toml");
+
+/// 一个游戏可执行文件及其持久化优先级，对应 `registry::GameConfig`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GameEntry {
+    pub exe_name: String,
+    pub cpu_priority: u32,
+    pub io_priority: u32,
+}
+
+impl GameEntry {
+    pub fn config(&self) -> GameConfig<'_> {
+        GameConfig { exe_name: &self.exe_name, cpu_priority: self.cpu_priority, io_priority: self.io_priority }
+    }
+}
+
+/// 一款游戏的优化方案
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Profile {
+    pub id: String,
+    pub name: String,
+    pub games: Vec<GameEntry>,
+    /// 游戏运行期间需要一并限制的反作弊进程
+    #[serde(default)]
+    pub companions: Vec<String>,
+}
+
+impl Profile {
+    /// 所有游戏都已按方案写入策略存储
+    pub fn is_applied(&self, store: &dyn PolicyStore) -> bool {
+        self.games.iter().all(|game| {
+            store.get(&game.exe_name).ok().flatten().is_some_and(|options| {
+                options.cpu_priority == Some(game.cpu_priority) && options.io_priority == Some(game.io_priority)
+            })
+        })
+    }
+
+    pub fn apply(&self, tx: &mut Transaction) -> Result<String> {
+        let configs: Vec<GameConfig> = self.games.iter().map(GameEntry::config).collect();
+        registry::apply_game_optimizations(tx, &configs)
+    }
+
+    pub fn reset(&self, tx: &mut Transaction) -> Result<String> {
+        let names: Vec<&str> = self.games.iter().map(|g| g.exe_name.as_str()).collect();
+        registry::reset_optimizations(tx, &names)
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Catalog {
+    #[serde(default = "Catalog::current_version")]
+    pub version: u32,
+    #[serde(default)]
+    pub profiles: Vec<Profile>,
+}
+
+impl Catalog {
+    pub const VERSION: u32 = 1;
+    pub const FILE_NAME: &'static str = "profiles.toml";
+
+    fn current_version() -> u32 {
+        Self::VERSION
+    }
+
+    fn parse(content: &str, origin: &str) -> Result<Self> {
+        let catalog: Catalog = toml::from_str(content).map_err(|e| format!("游戏配置格式错误 ({}): {}", origin, e))?;
+        if catalog.version > Self::VERSION {
+            return Err(format!("游戏配置版本 {} 过新，当前仅支持 {}", catalog.version, Self::VERSION));
+        }
+        Ok(catalog)
+    }
+
+    pub fn builtin() -> Self {
+        Self::parse(BUILTIN_CATALOG, "内置").expect("内置游戏配置无效")
+    }
+
+    /// 内置配置叠加用户文件：同 id 的方案被替换，新 id 追加在后面
+    pub fn load(user_file: &Path) -> Result<Self> {
+        let mut catalog = Self::builtin();
+        let content = match fs::read_to_string(user_file) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(catalog),
+            Err(e) => return Err(format!("读取游戏配置失败 ({}): {}", user_file.display(), e)),
+        };
+        let user = Self::parse(&content, &user_file.display().to_string())?;
+        for profile in user.profiles {
+            match catalog.profiles.iter_mut().find(|p| p.id == profile.id) {
+                Some(existing) => *existing = profile,
+                None => catalog.profiles.push(profile),
+            }
+        }
+        Ok(catalog)
+    }
+
+    pub fn get(&self, id: &str) -> Result<&Profile> {
+        self.profiles.iter().find(|p| p.id == id).ok_or_else(|| format!("未知的游戏配置: {}", id))
+    }
+}
+
+/// 列表展示用：方案本身加上是否已生效
+#[derive(Debug, Clone, Serialize)]
+pub struct ProfileStatus {
+    #[serde(flatten)]
+    pub profile: Profile,
+    pub applied: bool,
+}
+
+/// 进程内共享的配置目录；用户文件损坏时退回内置配置
+pub fn catalog() -> &'static Catalog {
+    static CATALOG: OnceLock<Catalog> = OnceLock::new();
+    CATALOG.get_or_init(|| {
+        let path = paths::config_dir().join(Catalog::FILE_NAME);
+        Catalog::load(&path).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            Catalog::builtin()
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::autostart::Autostart;
+    use crate::policy::{FilePolicyStore, PerfOptions};
+    use crate::transaction::{History, Stores};
+
+    #[test]
+    fn builtin_catalog_is_valid() {
+        let catalog = Catalog::builtin();
+        assert_eq!(catalog.version, Catalog::VERSION);
+        assert!(!catalog.profiles.is_empty());
+        for (i, profile) in catalog.profiles.iter().enumerate() {
+            assert!(!profile.games.is_empty(), "{} 没有游戏", profile.id);
+            assert!(catalog.profiles[..i].iter().all(|p| p.id != profile.id), "重复的 id {}", profile.id);
+        }
+        assert_eq!(catalog.get("delta-force").unwrap().games[0].exe_name, "DeltaForceClient.exe");
+        assert!(catalog.get("nope").is_err());
+    }
+
+    #[test]
+    fn user_file_overrides_and_extends_builtin() {
+        let dir = paths::ScratchDir::new("profiles");
+        let path = dir.join(Catalog::FILE_NAME);
+        assert_eq!(Catalog::load(&path).unwrap().profiles.len(), Catalog::builtin().profiles.len());
+
+        fs::write(
+            &path,
+            r#"
+            [[profiles]]
+            id = "valorant"
+            name = "VALORANT"
+            games = [{ exe_name = "VALORANT.exe", cpu_priority = 6, io_priority = 2 }]
+
+            [[profiles]]
+            id = "my-game"
+            name = "我的游戏"
+            games = [{ exe_name = "game.exe", cpu_priority = 3, io_priority = 2 }]
+            "#,
+        )
+        .unwrap();
+        let catalog = Catalog::load(&path).unwrap();
+        assert_eq!(catalog.profiles.len(), Catalog::builtin().profiles.len() + 1);
+        let valorant = catalog.get("valorant").unwrap();
+        assert_eq!((valorant.name.as_str(), valorant.games.len()), ("VALORANT", 1));
+        assert!(valorant.companions.is_empty());
+        assert_eq!(catalog.profiles.last().unwrap().id, "my-game");
+
+        fs::write(&path, "version = 2\n").unwrap();
+        assert!(Catalog::load(&path).unwrap_err().contains("过新"));
+        fs::write(&path, "[[profiles]]\nid = \"x\"\n").unwrap();
+        assert!(Catalog::load(&path).is_err());
+    }
+
+    #[test]
+    fn apply_and_reset_against_file_store() {
+        let dir = paths::ScratchDir::new("profiles");
+        let store = FilePolicyStore::new(dir.join("policy.toml"));
+        let autostart = Autostart::new(Vec::new());
+        let stores = Stores { policy: &store, autostart: &autostart };
+        let history = History::open(dir.join("transactions.json"));
+        let profile = Catalog::builtin().get("delta-force").unwrap().clone();
+        let exe = profile.games[0].exe_name.as_str();
+
+        // 已有的其他设置在写入方案后保留
+        store.set(&exe.to_lowercase(), &PerfOptions { page_priority: Some(5), ..Default::default() }).unwrap();
+        assert!(!profile.is_applied(&store));
+        history.run(stores, "应用", |tx| profile.apply(tx)).unwrap();
+        assert!(profile.is_applied(&store));
+        let options = store.get(exe).unwrap().unwrap();
+        assert_eq!(options, PerfOptions { cpu_priority: Some(3), io_priority: Some(2), page_priority: Some(5) });
+
+        // 优先级被改掉后不再算作已生效
+        store.set(exe, &PerfOptions { cpu_priority: Some(2), ..options }).unwrap();
+        assert!(!profile.is_applied(&store));
+
+        let message = history.run(stores, "重置", |tx| profile.reset(tx)).unwrap();
+        assert!(message.contains("已恢复默认"));
+        assert_eq!(store.get(exe).unwrap(), None);
+        assert!(!profile.is_applied(&store));
+    }
+}
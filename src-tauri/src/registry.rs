@@ -1,88 +1,124 @@
-﻿use winreg::enums::*;
+#[cfg(windows)]
+use winreg::enums::*;
+#[cfg(windows)]
 use winreg::RegKey;
 
+#[cfg(windows)]
+use crate::policy::{PerfOptions, PolicyStore};
+use crate::transaction::Transaction;
+
+#[cfg(windows)]
 const IFEO_PATH: &str = r"SOFTWARE\Microsoft\Windows NT\CurrentVersion\Image File Execution Options";
 
-// 瀹氫箟涓€涓粨鏋勪綋鏉ラ厤缃父鎴忎紭鍖栧弬鏁?
+// 定义一个结构体来配置游戏优化参数
 pub struct GameConfig<'a> {
     pub exe_name: &'a str,
     pub cpu_priority: u32,
     pub io_priority: u32,
 }
 
-pub fn apply_game_optimizations(games: &[GameConfig]) -> Result<String, String> {
-    // 鏉冮檺妫€鏌ュ簲璇ュ湪涓婂眰鍋氾紝鎴栬€呰繖閲岀畝鍗曠殑渚濋潬 API 澶辫触杩斿洖
-    let hklm = RegKey::predef(HKEY_LOCAL_MACHINE);
+/// 在事务中写入所有游戏的设置；任何一个失败都会让整个事务回滚
+pub fn apply_game_optimizations(tx: &mut Transaction, games: &[GameConfig]) -> Result<String, String> {
     let mut results = Vec::new();
 
     for game in games {
-        let key_path = format!(r"{}\{}\PerfOptions", IFEO_PATH, game.exe_name);
-        
-        match hklm.create_subkey(&key_path) {
-            Ok((key, _)) => {
-                let mut success = true;
-                if let Err(e) = key.set_value("CpuPriorityClass", &game.cpu_priority) {
-                    results.push(format!("{}: 璁剧疆 CPU 浼樺厛绾уけ璐? {}", game.exe_name, e));
-                    success = false;
-                }
-                if let Err(e) = key.set_value("IoPriority", &game.io_priority) {
-                    results.push(format!("{}: 璁剧疆 I/O 浼樺厛绾уけ璐? {}", game.exe_name, e));
-                    success = false;
-                }
-                if success {
-                    results.push(format!("{}: 浼樺寲鎴愬姛 (CPU: {}, I/O: {})", game.exe_name, game.cpu_priority, game.io_priority));
-                }
-            }
-            Err(e) => {
-                // 濡傛灉鏄€滄嫆缁濊闂€濓紝缁欎釜鏇村弸濂界殑鎻愮ず
-                let err_msg = e.to_string();
-                if err_msg.contains("Access is denied") {
-                    results.push(format!("{}: 鏉冮檺涓嶈冻锛岃浠ョ鐞嗗憳杩愯", game.exe_name));
-                } else {
-                    results.push(format!("{}: 鍒涘缓娉ㄥ唽琛ㄥけ璐? {}", game.exe_name, e));
-                }
+        let result = tx.update_policy(game.exe_name, &|options| {
+            options.cpu_priority = Some(game.cpu_priority);
+            options.io_priority = Some(game.io_priority);
+        });
+        match result {
+            Ok(_) => results.push(format!("{}: 优化成功 (CPU: {}, I/O: {})", game.exe_name, game.cpu_priority, game.io_priority)),
+            // 如果是“拒绝访问”，给个更友好的提示
+            Err(e) if e.contains("Access is denied") || e.contains("Permission denied") => {
+                return Err(format!("{}: 权限不足，请以管理员运行", game.exe_name));
             }
+            Err(e) => return Err(format!("{}: 创建注册表失败: {}", game.exe_name, e)),
         }
     }
-    
+
     Ok(results.join("\n"))
 }
 
-pub fn reset_optimizations(exe_names: &[&str]) -> Result<String, String> {
-    let hklm = RegKey::predef(HKEY_LOCAL_MACHINE);
+pub fn reset_optimizations(tx: &mut Transaction, exe_names: &[&str]) -> Result<String, String> {
     let mut results = Vec::new();
 
     for &exe_name in exe_names {
-        let exe_key_path = format!(r"{}\{}", IFEO_PATH, exe_name);
-        match hklm.open_subkey_with_flags(&exe_key_path, KEY_WRITE) {
-            Ok(exe_key) => {
-                match exe_key.delete_subkey("PerfOptions") {
-                    Ok(_) => results.push(format!("{}: 宸叉仮澶嶉粯璁?, exe_name)),
-                    Err(e) => results.push(format!("{}: 鎭㈠澶辫触: {}", exe_name, e)),
-                }
-            }
-            Err(_) => results.push(format!("{}: 鏈壘鍒伴厤缃?, exe_name)),
+        match tx.remove_policy(exe_name) {
+            Ok(true) => results.push(format!("{}: 已恢复默认", exe_name)),
+            Ok(false) => results.push(format!("{}: 未找到配置", exe_name)),
+            Err(e) => return Err(format!("{}: 恢复失败: {}", exe_name, e)),
         }
     }
     Ok(results.join("\n"))
 }
 
-// 杩欓噷鐨勮嚜鍚姩閫昏緫淇濇寔鍘熸牱锛屼絾涔熸惉杩囨潵
-pub fn set_autostart(enable: bool) -> Result<String, String> {
-    let hkcu = RegKey::predef(HKEY_CURRENT_USER);
-    let path = r"Software\Microsoft\Windows\CurrentVersion\Run";
-    let app_name = "PitayaBox";
-
-    if enable {
-        let (key, _) = hkcu.create_subkey(path).map_err(|e| e.to_string())?;
-        let exe_path = std::env::current_exe().map_err(|e| e.to_string())?;
-        key.set_value(app_name, &exe_path.to_str().unwrap_or("")).map_err(|e| e.to_string())?;
-        Ok("宸插惎鐢ㄥ紑鏈鸿嚜鍚?.to_string())
-    } else {
-        let key = hkcu.open_subkey_with_flags(path, KEY_WRITE).map_err(|e| e.to_string())?;
-        match key.delete_value(app_name) {
-            Ok(_) => Ok("宸茬鐢ㄥ紑鏈鸿嚜鍚?.to_string()),
-            Err(_) => Ok("寮€鏈鸿嚜鍚師鏈氨鏈惎鐢?.to_string()), 
+// --- IFEO 注册表存储 ---
+
+/// 写入 `IFEO\<exe>\PerfOptions`，由系统在进程创建时应用
+#[cfg(windows)]
+pub struct RegistryStore;
+
+#[cfg(windows)]
+impl RegistryStore {
+    const VALUES: [&'static str; 3] = ["CpuPriorityClass", "IoPriority", "PagePriority"];
+
+    fn perf_path(exe_name: &str) -> String {
+        format!(r"{}\{}\PerfOptions", IFEO_PATH, exe_name)
+    }
+}
+
+#[cfg(windows)]
+impl PolicyStore for RegistryStore {
+    fn name(&self) -> &'static str {
+        "registry"
+    }
+
+    fn get(&self, exe_name: &str) -> Result<Option<PerfOptions>, String> {
+        let hklm = RegKey::predef(HKEY_LOCAL_MACHINE);
+        let Ok(key) = hklm.open_subkey(Self::perf_path(exe_name)) else {
+            return Ok(None);
+        };
+        Ok(Some(PerfOptions {
+            cpu_priority: key.get_value("CpuPriorityClass").ok(),
+            io_priority: key.get_value("IoPriority").ok(),
+            page_priority: key.get_value("PagePriority").ok(),
+        }))
+    }
+
+    fn set(&self, exe_name: &str, options: &PerfOptions) -> Result<(), String> {
+        let hklm = RegKey::predef(HKEY_LOCAL_MACHINE);
+        let (key, _) = hklm.create_subkey(Self::perf_path(exe_name)).map_err(|e| format!("权限不足: {}", e))?;
+        let values = [options.cpu_priority, options.io_priority, options.page_priority];
+        for (name, value) in Self::VALUES.iter().zip(values) {
+            match value {
+                Some(v) => key.set_value(name, &v).map_err(|e| format!("写入 {} 失败: {}", name, e))?,
+                None => { let _ = key.delete_value(name); }
+            }
+        }
+        Ok(())
+    }
+
+    fn remove(&self, exe_name: &str) -> Result<bool, String> {
+        let hklm = RegKey::predef(HKEY_LOCAL_MACHINE);
+        let Ok(key) = hklm.open_subkey_with_flags(format!(r"{}\{}", IFEO_PATH, exe_name), KEY_ALL_ACCESS) else {
+            return Ok(false);
+        };
+        match key.delete_subkey("PerfOptions") {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(format!("恢复失败: {}", e)),
+        }
+    }
+
+    fn list(&self) -> Result<Vec<(String, PerfOptions)>, String> {
+        let hklm = RegKey::predef(HKEY_LOCAL_MACHINE);
+        let ifeo = hklm.open_subkey(IFEO_PATH).map_err(|e| format!("读取注册表失败: {}", e))?;
+        let mut rules = Vec::new();
+        for exe_name in ifeo.enum_keys().flatten() {
+            if let Some(options) = self.get(&exe_name)? {
+                rules.push((exe_name, options));
+            }
         }
+        Ok(rules)
     }
 }
@@ -0,0 +1,493 @@
+use std::collections::btree_map::Entry;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+use serde::{Deserialize, Serialize};
+
+use crate::actions::{ActionSet, Affinity, CpuPriority, IoPriority, MemoryPriority, ThreadRule};
+use sysinfo::{System, Users};
+
+use crate::matcher::{ProcessInfo, ProcessMatcher};
+use crate::paths;
+use crate::process::trim::{self, TrimPolicy};
+use crate::tree::{Lineage, ProcessTree};
+
+type Result<T> = std::result::Result<T, String>;
+
+/// 一个需要限制的进程
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "TargetRepr", into = "TargetRepr")]
+pub struct Target {
+    /// 显示名称；没有写 `match` 时同时作为进程名通配符
+    pub name: String,
+    pub matcher: ProcessMatcher,
+    /// 命中后执行的动作
+    pub actions: ActionSet,
+    /// 动作是否沿进程树传给所有后代
+    pub inherit: bool,
+}
+
+impl Target {
+    /// 按进程名（通配符）匹配的目标，动作为空
+    pub fn new(name: impl Into<String>) -> Result<Self> {
+        let name = name.into();
+        let matcher = ProcessMatcher::by_name(&name)?;
+        Ok(Self { name, matcher, actions: ActionSet::default(), inherit: false })
+    }
+
+    pub fn with_actions(mut self, actions: ActionSet) -> Self {
+        self.actions = actions;
+        self
+    }
+
+    pub fn with_inherit(mut self, inherit: bool) -> Self {
+        self.inherit = inherit;
+        self
+    }
+
+    pub fn matches(&self, info: &ProcessInfo) -> bool {
+        self.matcher.matches(info)
+    }
+}
+
+/// 文件中的写法：`match` 省略时由 `name` 推出
+#[derive(Serialize, Deserialize)]
+struct TargetRepr {
+    name: String,
+    #[serde(default, rename = "match", skip_serializing_if = "Option::is_none")]
+    matcher: Option<ProcessMatcher>,
+    #[serde(default, skip_serializing_if = "ActionSet::is_empty")]
+    actions: ActionSet,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    inherit: bool,
+}
+
+impl TryFrom<TargetRepr> for Target {
+    type Error = String;
+
+    fn try_from(repr: TargetRepr) -> Result<Self> {
+        match repr.matcher {
+            Some(matcher) => Ok(Self { name: repr.name, matcher, actions: repr.actions, inherit: repr.inherit }),
+            None => Ok(Target::new(repr.name)?.with_actions(repr.actions).with_inherit(repr.inherit)),
+        }
+    }
+}
+
+impl From<Target> for TargetRepr {
+    fn from(target: Target) -> Self {
+        let derived = ProcessMatcher::by_name(&target.name).ok();
+        let matcher = (derived.as_ref() != Some(&target.matcher)).then_some(target.matcher);
+        Self { name: target.name, matcher, actions: target.actions, inherit: target.inherit }
+    }
+}
+
+/// 一个进程被分配到的规则
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Assignment {
+    pub pid: u32,
+    /// `TargetConfig::targets` 中的下标
+    pub target: usize,
+    /// 因祖先命中 `inherit` 规则而受限
+    pub inherited: bool,
+}
+
+/// `targets.toml` 的内容
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TargetConfig {
+    #[serde(default = "TargetConfig::current_version")]
+    pub version: u32,
+    #[serde(default)]
+    pub targets: Vec<Target>,
+    /// 性能面板中额外展示的进程
+    #[serde(default)]
+    pub monitor: Vec<ProcessMatcher>,
+    /// 规则里 `trim = true` 时的修剪阈值与频率
+    #[serde(default)]
+    pub trim: TrimPolicy,
+}
+
+impl TargetConfig {
+    /// v2：目标支持 `match`，`monitor` 由关键字改为匹配器
+    /// v3：每个目标带自己的 `actions`
+    pub const VERSION: u32 = 3;
+
+    fn current_version() -> u32 {
+        Self::VERSION
+    }
+
+    pub fn is_target(&self, info: &ProcessInfo) -> bool {
+        self.targets.iter().any(|t| t.matches(info))
+    }
+
+    /// 是否应出现在性能面板里：目标进程本身，或命中任一监视条件
+    pub fn is_monitored(&self, info: &ProcessInfo) -> bool {
+        self.is_target(info) || self.monitor.iter().any(|m| m.matches(info))
+    }
+
+    /// 把进程表中的每个进程分配给至多一条规则
+    ///
+    /// 直接命中优先；`inherit` 规则再把动作传给当前的后代，以及之前记录过、父进程已退出的后代。
+    pub fn resolve(&self, sys: &System, users: &Users, lineage: &Lineage) -> Vec<Assignment> {
+        let tree = ProcessTree::from_system(sys);
+        let remembered = lineage.refresh(&tree);
+        let mut assigned: BTreeMap<u32, Assignment> = BTreeMap::new();
+
+        for (pid, process) in sys.processes() {
+            let info = ProcessInfo::new(sys, users, process);
+            // 多条规则命中同一进程时以靠前的为准
+            if let Some(target) = self.targets.iter().position(|t| t.matches(&info)) {
+                let pid = pid.as_u32();
+                assigned.insert(pid, Assignment { pid, target, inherited: false });
+            }
+        }
+
+        let roots: Vec<Assignment> = assigned.values().filter(|a| self.targets[a.target].inherit).copied().collect();
+        for root in roots {
+            for child in tree.descendants(root.pid) {
+                if let Entry::Vacant(slot) = assigned.entry(child) {
+                    slot.insert(Assignment { pid: child, target: root.target, inherited: true });
+                    lineage.record(&tree, child, &self.targets[root.target].name);
+                }
+            }
+        }
+
+        for (pid, rule) in remembered {
+            let Some(target) = self.targets.iter().position(|t| t.inherit && t.name == rule) else { continue };
+            assigned.entry(pid).or_insert(Assignment { pid, target, inherited: true });
+        }
+        assigned.into_values().collect()
+    }
+
+    /// 只保留按进程名能命中 `names` 中任一进程的规则，用于游戏方案里的伴随进程
+    pub fn subset_for(&self, names: &[String]) -> TargetConfig {
+        let targets = self
+            .targets
+            .iter()
+            .filter(|t| names.iter().any(|name| t.matches(&ProcessInfo { name, ..Default::default() })))
+            .cloned()
+            .collect();
+        TargetConfig { version: self.version, targets, monitor: self.monitor.clone(), trim: self.trim }
+    }
+
+    fn find(&self, name: &str) -> Option<usize> {
+        self.targets.iter().position(|t| t.name.eq_ignore_ascii_case(name))
+    }
+}
+
+impl Default for TargetConfig {
+    fn default() -> Self {
+        Self {
+            version: Self::VERSION,
+            targets: default_targets(),
+            monitor: ["*SGuard*", "*Delta*", "*VALORANT*"]
+                .into_iter()
+                .filter_map(|pattern| ProcessMatcher::by_name(pattern).ok())
+                .collect(),
+            trim: TrimPolicy::default(),
+        }
+    }
+}
+
+/// 旧版五个开关全开时的效果
+fn legacy_actions() -> ActionSet {
+    ActionSet {
+        affinity: Some(Affinity::Last(1)),
+        priority: Some(CpuPriority::Idle),
+        io: Some(IoPriority::VeryLow),
+        eco: Some(true),
+        memory: Some(MemoryPriority::VeryLow),
+        trim: true,
+        threads: None,
+    }
+}
+
+/// 内置的 ACE 规则：客户端压得更狠，服务进程不做内存修剪以免拖慢启动
+fn default_targets() -> Vec<Target> {
+    // 单个线程长时间占满半个核心以上时单独压下去
+    let client = ActionSet { threads: Some(ThreadRule { above_percent: 50.0, affinity: None }), ..legacy_actions() };
+    let service = ActionSet {
+        affinity: Some(Affinity::Last(1)),
+        priority: Some(CpuPriority::Idle),
+        io: Some(IoPriority::Low),
+        eco: Some(true),
+        memory: Some(MemoryPriority::Low),
+        trim: false,
+        threads: None,
+    };
+    // SGuard64 会拉起辅助进程，让它们跟着受限
+    [("SGuard64.exe", client, true), ("SGuardSvc64.exe", service, false)]
+        .into_iter()
+        .filter_map(|(name, actions, inherit)| {
+            Target::new(name).ok().map(|t| t.with_actions(actions).with_inherit(inherit))
+        })
+        .collect()
+}
+
+/// 目标列表的持久化存储；文件不存在时使用内置默认值
+pub struct TargetStore {
+    path: PathBuf,
+    config: Mutex<TargetConfig>,
+    /// 文件存在但无法解析时记下原因，此时拒绝写回，避免覆盖用户的配置
+    load_error: Option<String>,
+}
+
+impl TargetStore {
+    pub const FILE_NAME: &'static str = "targets.toml";
+
+    pub fn open(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let (config, load_error) = match load(&path) {
+            Ok(config) => (config, None),
+            Err(e) => (TargetConfig::default(), Some(e)),
+        };
+        Self { path, config: Mutex::new(config), load_error }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn load_error(&self) -> Option<&str> {
+        self.load_error.as_deref()
+    }
+
+    pub fn config(&self) -> TargetConfig {
+        self.config.lock().unwrap().clone()
+    }
+
+    pub fn targets(&self) -> Vec<Target> {
+        self.config.lock().unwrap().targets.clone()
+    }
+
+    /// 添加或替换同名目标并写回文件
+    pub fn add(&self, target: Target) -> Result<Vec<Target>> {
+        if target.name.trim().is_empty() {
+            return Err("目标名称不能为空".to_string());
+        }
+        if target.matcher.is_empty() {
+            return Err(format!("{}: 至少需要一个匹配条件", target.name));
+        }
+        self.modify(|config| match config.find(&target.name) {
+            Some(index) => config.targets[index] = target,
+            None => config.targets.push(target),
+        })
+    }
+
+    pub fn remove(&self, name: &str) -> Result<Vec<Target>> {
+        self.modify(|config| config.targets.retain(|t| !t.name.eq_ignore_ascii_case(name.trim())))
+    }
+
+    /// 丢弃文件中的修改，恢复内置默认值
+    pub fn reset(&self) -> Result<Vec<Target>> {
+        self.modify(|config| *config = TargetConfig::default())
+    }
+
+    fn modify(&self, f: impl FnOnce(&mut TargetConfig)) -> Result<Vec<Target>> {
+        if let Some(e) = &self.load_error {
+            return Err(format!("配置文件无法解析，未做修改: {}", e));
+        }
+        let mut config = self.config.lock().unwrap();
+        let mut updated = config.clone();
+        f(&mut updated);
+        updated.version = TargetConfig::VERSION;
+        save(&self.path, &updated)?;
+        let (targets, trim) = (updated.targets.clone(), updated.trim);
+        *config = updated;
+        // 先放开锁：修剪器第一次使用时会从默认存储读取配置
+        drop(config);
+        trim::trimmer().set_policy(trim);
+        Ok(targets)
+    }
+}
+
+fn load(path: &Path) -> Result<TargetConfig> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(TargetConfig::default()),
+        Err(e) => return Err(format!("读取目标列表失败 ({}): {}", path.display(), e)),
+    };
+    // 先解析成通用结构，按版本迁移后再转换
+    let mut value: toml::Value = if path.extension().is_some_and(|ext| ext == "json") {
+        serde_json::from_str(&content).map_err(|e| format!("目标列表格式错误 ({}): {}", path.display(), e))?
+    } else {
+        toml::from_str(&content).map_err(|e| format!("目标列表格式错误 ({}): {}", path.display(), e))?
+    };
+    let version = value.get("version").and_then(|v| v.as_integer()).unwrap_or(TargetConfig::VERSION as i64);
+    if version > TargetConfig::VERSION as i64 {
+        return Err(format!("目标列表版本 {} 过新，当前仅支持 {}", version, TargetConfig::VERSION));
+    }
+    if version < 2 {
+        migrate_v1(&mut value);
+    }
+    if version < 3 {
+        migrate_v2(&mut value)?;
+    }
+    value.try_into().map_err(|e| format!("目标列表格式错误 ({}): {}", path.display(), e))
+}
+
+/// v1 的 `monitor` 是进程名关键字，转换为 `*关键字*` 通配符
+fn migrate_v1(value: &mut toml::Value) {
+    let Some(monitor) = value.get_mut("monitor").and_then(|v| v.as_array_mut()) else {
+        return;
+    };
+    for entry in monitor.iter_mut() {
+        if let Some(keyword) = entry.as_str() {
+            let mut table = toml::Table::new();
+            table.insert("name".to_string(), toml::Value::String(format!("*{}*", keyword)));
+            *entry = toml::Value::Table(table);
+        }
+    }
+}
+
+/// v2 之前所有目标共用前端的五个开关，迁移时给没有动作的目标补上当时的完整动作
+fn migrate_v2(value: &mut toml::Value) -> Result<()> {
+    let Some(targets) = value.get_mut("targets").and_then(|v| v.as_array_mut()) else {
+        return Ok(());
+    };
+    let legacy = toml::Value::try_from(legacy_actions()).map_err(|e| format!("迁移目标列表失败: {}", e))?;
+    for target in targets.iter_mut().filter_map(|t| t.as_table_mut()) {
+        target.entry("actions").or_insert_with(|| legacy.clone());
+    }
+    Ok(())
+}
+
+fn save(path: &Path, config: &TargetConfig) -> Result<()> {
+    let content = if path.extension().is_some_and(|ext| ext == "json") {
+        serde_json::to_string_pretty(config).map_err(|e| format!("序列化目标列表失败: {}", e))?
+    } else {
+        toml::to_string_pretty(config).map_err(|e| format!("序列化目标列表失败: {}", e))?
+    };
+    paths::write_atomic(path, &content).map_err(|e| format!("写入目标列表失败 ({}): {}", path.display(), e))
+}
+
+/// 进程内共享的目标列表，默认位于配置目录下的 `targets.toml`
+pub fn store() -> &'static TargetStore {
+    static STORE: OnceLock<TargetStore> = OnceLock::new();
+    STORE.get_or_init(|| {
+        let path = std::env::var_os("PITAYABOX_TARGETS_FILE")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| paths::config_dir().join(TargetStore::FILE_NAME));
+        TargetStore::open(path)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(dir: &Path, name: &str, content: &str) -> PathBuf {
+        let path = dir.join(name);
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    fn named(name: &str) -> ProcessInfo<'_> {
+        ProcessInfo { name, ..Default::default() }
+    }
+
+    #[test]
+    fn migrates_v1_keywords_and_legacy_actions() {
+        let dir = paths::ScratchDir::new("targets");
+        let path = write(
+            &dir,
+            "targets.toml",
+            r#"
+            version = 1
+            monitor = ["Delta"]
+
+            [[targets]]
+            name = "SGuard64.exe"
+            "#,
+        );
+        let config = load(&path).unwrap();
+        assert_eq!(config.monitor, vec![ProcessMatcher::by_name("*Delta*").unwrap()]);
+        assert!(config.is_monitored(&named("DeltaForce.exe")));
+        assert_eq!(config.targets.len(), 1);
+        assert_eq!(config.targets[0].actions, legacy_actions());
+        assert_eq!(config.trim, TrimPolicy::default());
+    }
+
+    #[test]
+    fn migrates_v2_without_touching_explicit_actions() {
+        let dir = paths::ScratchDir::new("targets");
+        let path = write(
+            &dir,
+            "targets.toml",
+            r#"
+            version = 2
+
+            [[targets]]
+            name = "a.exe"
+
+            [[targets]]
+            name = "b.exe"
+            match = { exe = "*/b/*" }
+            actions = { priority = "idle" }
+            "#,
+        );
+        let config = load(&path).unwrap();
+        assert_eq!(config.targets[0].actions, legacy_actions());
+        assert_eq!(config.targets[1].actions, ActionSet { priority: Some(CpuPriority::Idle), ..Default::default() });
+        assert!(config.targets[1].matcher.name.is_none());
+    }
+
+    #[test]
+    fn v3_is_read_as_is() {
+        let dir = paths::ScratchDir::new("targets");
+        let path = write(
+            &dir,
+            "targets.json",
+            r#"{ "version": 3, "targets": [{ "name": "c.exe", "inherit": true }], "trim": { "interval_secs": 5 } }"#,
+        );
+        let config = load(&path).unwrap();
+        assert!(config.targets[0].actions.is_empty());
+        assert!(config.targets[0].inherit);
+        assert_eq!(config.trim.interval_secs, 5);
+        assert_eq!(config.trim.min_resident_mb, TrimPolicy::default().min_resident_mb);
+    }
+
+    #[test]
+    fn rejects_newer_version() {
+        let dir = paths::ScratchDir::new("targets");
+        let path = write(&dir, "targets.toml", "version = 4\n");
+        assert!(load(&path).unwrap_err().contains("过新"));
+    }
+
+    #[test]
+    fn store_refuses_to_overwrite_unreadable_file() {
+        let dir = paths::ScratchDir::new("targets");
+        let path = write(&dir, "targets.toml", "targets = [\n");
+        let store = TargetStore::open(&path);
+        assert!(store.load_error().is_some());
+        assert!(store.remove("SGuard64.exe").is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "targets = [\n");
+    }
+
+    #[test]
+    fn store_saves_current_version() {
+        let dir = paths::ScratchDir::new("targets");
+        let path = dir.join("targets.toml");
+        let store = TargetStore::open(&path);
+        assert_eq!(store.targets(), default_targets());
+        store.add(Target::new("game*.exe").unwrap().with_inherit(true)).unwrap();
+        store.remove("sguardsvc64.exe").unwrap();
+
+        let config = load(&path).unwrap();
+        assert_eq!(config.version, TargetConfig::VERSION);
+        let names: Vec<&str> = config.targets.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, vec!["SGuard64.exe", "game*.exe"]);
+        // 由名称推出的匹配器不写进文件
+        assert!(!fs::read_to_string(&path).unwrap().contains("match"));
+    }
+
+    #[test]
+    fn subset_keeps_rules_matching_names() {
+        let config = TargetConfig::default();
+        let subset = config.subset_for(&["sguardsvc64.exe".to_string()]);
+        assert_eq!(subset.targets.len(), 1);
+        assert_eq!(subset.targets[0].name, "SGuardSvc64.exe");
+        assert!(config.subset_for(&[]).targets.is_empty());
+    }
+}
@@ -0,0 +1,451 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use serde::Serialize;
+
+use crate::cpuset::{CpuSet, MAX_CPUS};
+
+type Result<T> = std::result::Result<T, String>;
+
+/// 一个逻辑处理器在拓扑中的位置
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LogicalCpu {
+    pub id: usize,
+    pub package: u32,
+    /// 物理核心编号，全系统唯一；同一核心的 SMT 线程相同
+    pub core: u32,
+    /// 0 为能效核心，数值越大性能越强；非混合架构全部相同
+    pub efficiency_class: u8,
+    /// 共享的末级缓存编号，AMD 上对应 CCD/CCX
+    pub llc: Option<u32>,
+}
+
+/// 逻辑处理器拓扑，按 `id` 升序排列
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Topology {
+    cpus: Vec<LogicalCpu>,
+}
+
+impl Topology {
+    pub fn new(mut cpus: Vec<LogicalCpu>) -> Self {
+        cpus.sort_by_key(|c| c.id);
+        Self { cpus }
+    }
+
+    /// 没有拓扑信息时的退化模型：每个逻辑处理器都是独立的核心
+    pub fn flat(count: usize) -> Self {
+        Self::new(
+            (0..count)
+                .map(|id| LogicalCpu { id, package: 0, core: id as u32, efficiency_class: 0, llc: None })
+                .collect(),
+        )
+    }
+
+    pub fn cpus(&self) -> &[LogicalCpu] {
+        &self.cpus
+    }
+
+    pub fn len(&self) -> usize {
+        self.cpus.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cpus.is_empty()
+    }
+
+    pub fn get(&self, id: usize) -> Option<&LogicalCpu> {
+        self.cpus.iter().find(|c| c.id == id)
+    }
+
+    pub fn all(&self) -> CpuSet {
+        self.cpus.iter().map(|c| c.id).collect()
+    }
+
+    /// 能效等级不止一种，即大小核混合架构
+    pub fn is_hybrid(&self) -> bool {
+        self.cpus.windows(2).any(|w| w[0].efficiency_class != w[1].efficiency_class)
+    }
+
+    /// 能效等级最低的逻辑处理器；非混合架构返回空
+    pub fn efficiency_cpus(&self) -> CpuSet {
+        if !self.is_hybrid() {
+            return CpuSet::new();
+        }
+        let lowest = self.cpus.iter().map(|c| c.efficiency_class).min().unwrap_or(0);
+        self.cpus.iter().filter(|c| c.efficiency_class == lowest).map(|c| c.id).collect()
+    }
+
+    /// 同一物理核心上的全部逻辑处理器（含自身）
+    pub fn siblings(&self, id: usize) -> Vec<usize> {
+        let Some(cpu) = self.get(id) else { return Vec::new() };
+        self.cpus.iter().filter(|c| c.core == cpu.core).map(|c| c.id).collect()
+    }
+
+    /// 按物理核心分组，组内按 id 升序
+    pub fn cores(&self) -> Vec<Vec<usize>> {
+        let mut cores: BTreeMap<u32, Vec<usize>> = BTreeMap::new();
+        for cpu in &self.cpus {
+            cores.entry(cpu.core).or_default().push(cpu.id);
+        }
+        cores.into_values().collect()
+    }
+
+    /// 按末级缓存分组（AMD 的 CCD），没有缓存信息时返回空
+    pub fn llc_groups(&self) -> Vec<Vec<usize>> {
+        let mut groups: BTreeMap<u32, Vec<usize>> = BTreeMap::new();
+        for cpu in &self.cpus {
+            if let Some(llc) = cpu.llc {
+                groups.entry(llc).or_default().push(cpu.id);
+            }
+        }
+        groups.into_values().collect()
+    }
+
+    /// 当前平台的拓扑；读取失败时退化为 `flat`
+    pub fn detect() -> Self {
+        let detected = Self::detect_native();
+        detected.unwrap_or_else(|e| {
+            eprintln!("读取 CPU 拓扑失败，按独立核心处理: {}", e);
+            Self::flat(std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1))
+        })
+    }
+
+    #[cfg(target_os = "linux")]
+    fn detect_native() -> Result<Self> {
+        Self::from_sysfs(Self::SYSFS_ROOT)
+    }
+
+    #[cfg(windows)]
+    fn detect_native() -> Result<Self> {
+        Self::from_windows()
+    }
+
+    #[cfg(not(any(windows, target_os = "linux")))]
+    fn detect_native() -> Result<Self> {
+        Err("当前平台不支持读取 CPU 拓扑".to_string())
+    }
+}
+
+/// 解析内核的 CPU 列表格式，例如 `0-3,8,10-11`；编号不能超过 `MAX_CPUS`
+pub fn parse_cpu_list(list: &str) -> Result<Vec<usize>> {
+    let mut cpus = Vec::new();
+    for part in list.trim().split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        let parse = |s: &str| {
+            let cpu = s.trim().parse::<usize>().map_err(|_| format!("无法解析 CPU 列表: {}", list))?;
+            if cpu >= MAX_CPUS {
+                return Err(format!("逻辑核心编号 {} 超出上限 {}", cpu, MAX_CPUS - 1));
+            }
+            Ok(cpu)
+        };
+        match part.split_once('-') {
+            Some((start, end)) => {
+                let (start, end) = (parse(start)?, parse(end)?);
+                if start > end {
+                    return Err(format!("CPU 范围颠倒: {}", part));
+                }
+                cpus.extend(start..=end);
+            }
+            None => cpus.push(parse(part)?),
+        }
+    }
+    cpus.sort_unstable();
+    cpus.dedup();
+    Ok(cpus)
+}
+
+// --- Linux：sysfs ---
+
+impl Topology {
+    /// sysfs 挂载点；测试时可以换成同样结构的普通目录
+    pub const SYSFS_ROOT: &'static str = "/sys";
+
+    /// 从 `<root>/devices/system/cpu/cpuN/topology` 读取拓扑
+    ///
+    /// 混合架构优先看 `<root>/devices/cpu_atom/cpus`（Intel），其次看 `cpu_capacity`（ARM）；
+    /// 末级缓存取 `cache/index3/id`。
+    pub fn from_sysfs(root: impl AsRef<Path>) -> Result<Self> {
+        let root = root.as_ref();
+        let cpu_dir = root.join("devices/system/cpu");
+        let entries = fs::read_dir(&cpu_dir).map_err(|e| format!("读取 {} 失败: {}", cpu_dir.display(), e))?;
+
+        let mut dirs: Vec<(usize, PathBuf)> = entries
+            .flatten()
+            .filter_map(|entry| {
+                let name = entry.file_name();
+                let id = name.to_str()?.strip_prefix("cpu")?.parse().ok()?;
+                Some((id, entry.path()))
+            })
+            // 离线的 CPU 没有 topology 目录
+            .filter(|(_, path)| path.join("topology").is_dir())
+            .collect();
+        dirs.sort_by_key(|(id, _)| *id);
+        if dirs.is_empty() {
+            return Err(format!("{} 下没有可用的 CPU", cpu_dir.display()));
+        }
+
+        let atom = read_trimmed(&root.join("devices/cpu_atom/cpus")).map(|s| parse_cpu_list(&s)).transpose()?;
+        let capacities: Vec<Option<u32>> = dirs.iter().map(|(_, p)| read_number(&p.join("cpu_capacity"))).collect();
+        let max_capacity = capacities.iter().flatten().max().copied();
+
+        let mut cpus = Vec::with_capacity(dirs.len());
+        for ((id, path), capacity) in dirs.iter().zip(capacities) {
+            let topology = path.join("topology");
+            let package = read_number(&topology.join("physical_package_id")).unwrap_or(0);
+            // 用同核心线程里最小的 id 作为核心编号，core_id 本身只在封装内唯一
+            let siblings = read_trimmed(&topology.join("core_cpus_list"))
+                .or_else(|| read_trimmed(&topology.join("thread_siblings_list")))
+                .map(|s| parse_cpu_list(&s))
+                .transpose()?
+                .unwrap_or_default();
+            let core = siblings.first().copied().unwrap_or(*id) as u32;
+            let efficiency_class = match (&atom, capacity, max_capacity) {
+                (Some(atom), _, _) => u8::from(!atom.contains(id)),
+                (None, Some(cap), Some(max)) => u8::from(cap >= max),
+                _ => 0,
+            };
+            let llc = read_number(&path.join("cache/index3/id"));
+            cpus.push(LogicalCpu { id: *id, package, core, efficiency_class, llc });
+        }
+        Ok(Self::new(cpus))
+    }
+}
+
+fn read_trimmed(path: &Path) -> Option<String> {
+    fs::read_to_string(path).ok().map(|s| s.trim().to_string()).filter(|s| !s.is_empty())
+}
+
+fn read_number(path: &Path) -> Option<u32> {
+    read_trimmed(path)?.parse().ok()
+}
+
+// --- Windows：GetLogicalProcessorInformationEx ---
+
+#[cfg(windows)]
+impl Topology {
+    pub fn from_windows() -> Result<Self> {
+        use windows::Win32::System::SystemInformation::{
+            GetLogicalProcessorInformationEx, RelationAll, RelationCache, RelationProcessorCore,
+            RelationProcessorPackage, GROUP_AFFINITY, SYSTEM_LOGICAL_PROCESSOR_INFORMATION_EX,
+        };
+
+        // 先取所需长度，再分配缓冲区
+        let mut length = 0u32;
+        unsafe {
+            let _ = GetLogicalProcessorInformationEx(RelationAll, None, &mut length);
+        }
+        if length == 0 {
+            return Err("GetLogicalProcessorInformationEx 未返回数据".to_string());
+        }
+        let mut buffer = vec![0u8; length as usize];
+        unsafe {
+            GetLogicalProcessorInformationEx(
+                RelationAll,
+                Some(buffer.as_mut_ptr() as *mut SYSTEM_LOGICAL_PROCESSOR_INFORMATION_EX),
+                &mut length,
+            )
+            .map_err(|e| format!("读取 CPU 拓扑失败: {}", e))?;
+        }
+
+        // 处理器组内的位序号换算成全局编号：每组最多 64 个
+        let ids = |mask: &GROUP_AFFINITY| -> Vec<usize> {
+            (0..usize::BITS as usize)
+                .filter(|bit| mask.Mask & (1usize << bit) != 0)
+                .map(|bit| mask.Group as usize * 64 + bit)
+                .collect()
+        };
+
+        let mut cpus: BTreeMap<usize, LogicalCpu> = BTreeMap::new();
+        let mut packages: Vec<Vec<usize>> = Vec::new();
+        let mut caches: Vec<Vec<usize>> = Vec::new();
+        let mut core = 0u32;
+        let mut offset = 0usize;
+        while offset < length as usize {
+            let info = unsafe { &*(buffer.as_ptr().add(offset) as *const SYSTEM_LOGICAL_PROCESSOR_INFORMATION_EX) };
+            if info.Size == 0 {
+                break;
+            }
+            unsafe {
+                if info.Relationship == RelationProcessorCore {
+                    let processor = &info.Anonymous.Processor;
+                    let masks = std::slice::from_raw_parts(processor.GroupMask.as_ptr(), processor.GroupCount as usize);
+                    for id in masks.iter().flat_map(ids) {
+                        cpus.insert(id, LogicalCpu { id, package: 0, core, efficiency_class: processor.EfficiencyClass, llc: None });
+                    }
+                    core += 1;
+                } else if info.Relationship == RelationProcessorPackage {
+                    let processor = &info.Anonymous.Processor;
+                    let masks = std::slice::from_raw_parts(processor.GroupMask.as_ptr(), processor.GroupCount as usize);
+                    packages.push(masks.iter().flat_map(ids).collect());
+                } else if info.Relationship == RelationCache && info.Anonymous.Cache.Level == 3 {
+                    caches.push(ids(&info.Anonymous.Cache.Anonymous.GroupMask));
+                }
+            }
+            offset += info.Size as usize;
+        }
+
+        for (index, members) in packages.iter().enumerate() {
+            for id in members {
+                if let Some(cpu) = cpus.get_mut(id) { cpu.package = index as u32; }
+            }
+        }
+        for (index, members) in caches.iter().enumerate() {
+            for id in members {
+                if let Some(cpu) = cpus.get_mut(id) { cpu.llc = Some(index as u32); }
+            }
+        }
+        if cpus.is_empty() {
+            return Err("没有读取到任何逻辑处理器".to_string());
+        }
+        Ok(Self::new(cpus.into_values().collect()))
+    }
+}
+
+/// 进程内缓存的拓扑，只在第一次使用时读取
+pub fn current() -> &'static Topology {
+    static TOPOLOGY: OnceLock<Topology> = OnceLock::new();
+    TOPOLOGY.get_or_init(Topology::detect)
+}
+
+// --- 核心选择 ---
+
+/// 选择核心时需要的运行时信息
+#[derive(Debug, Clone)]
+pub struct PlacementContext<'a> {
+    pub topology: &'a Topology,
+    /// 每个逻辑处理器当前的占用率（0-100），与 `topology.cpus()` 的顺序一致；缺失视为 0
+    pub load: Vec<f32>,
+    /// 游戏进程被限定使用的逻辑处理器；游戏没有被限定时为空
+    pub game_cpus: CpuSet,
+}
+
+impl<'a> PlacementContext<'a> {
+    pub fn new(topology: &'a Topology) -> Self {
+        Self { topology, load: Vec::new(), game_cpus: CpuSet::new() }
+    }
+
+    pub fn with_load(mut self, load: Vec<f32>) -> Self {
+        self.load = load;
+        self
+    }
+
+    pub fn with_game_cpus(mut self, game_cpus: CpuSet) -> Self {
+        self.game_cpus = game_cpus;
+        self
+    }
+
+    pub fn cpu_count(&self) -> usize {
+        self.topology.len()
+    }
+
+    /// Windows 上编号按处理器组排列，可能不连续，所以按位置而不是编号取负载
+    pub fn load_of(&self, id: usize) -> f32 {
+        let index = self.topology.cpus().iter().position(|c| c.id == id);
+        index.and_then(|i| self.load.get(i)).copied().unwrap_or(0.0)
+    }
+
+    /// 按负载从低到高排列的逻辑处理器，负载相同时 id 大的在前（沿用“最后一个核心”的习惯）
+    pub fn by_load(&self) -> Vec<usize> {
+        let mut ids: Vec<usize> = self.topology.cpus().iter().map(|c| c.id).collect();
+        ids.sort_by(|a, b| self.load_of(*a).total_cmp(&self.load_of(*b)).then(b.cmp(a)));
+        ids
+    }
+
+    /// 与游戏不共享物理核心的 SMT 副线程，选负载最低的核心；没有 SMT 或所有核心都被游戏占用时返回 `None`，
+    /// 放到游戏旁边的超线程上反而会和游戏抢同一个核心
+    pub fn smt_sibling(&self) -> Option<usize> {
+        let cores: Vec<Vec<usize>> = self.topology.cores().into_iter().filter(|c| c.len() > 1).collect();
+        let load = |core: &Vec<usize>| core.iter().map(|id| self.load_of(*id)).sum::<f32>();
+        cores
+            .iter()
+            .filter(|core| !core.iter().any(|&id| self.game_cpus.contains(id)))
+            .min_by(|a, b| load(a).total_cmp(&load(b)).then(b[0].cmp(&a[0])))
+            .and_then(|core| core.last().copied())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::paths;
+
+    fn write(root: &Path, relative: &str, content: &str) {
+        let path = root.join(relative);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    /// 两个带 SMT 的性能核心 (0,1)、(2,3)，两个能效核心 4、5，cpu6 离线
+    fn hybrid_fixture() -> paths::ScratchDir {
+        let root = paths::ScratchDir::new("sysfs");
+        let cores = [(0, "0-1", 0), (1, "0-1", 0), (2, "2-3", 0), (3, "2-3", 0), (4, "4", 1), (5, "5", 1)];
+        for (id, siblings, llc) in cores {
+            let dir = format!("devices/system/cpu/cpu{}", id);
+            write(&root, &format!("{}/topology/physical_package_id", dir), "0\n");
+            write(&root, &format!("{}/topology/core_cpus_list", dir), &format!("{}\n", siblings));
+            write(&root, &format!("{}/cache/index3/id", dir), &format!("{}\n", llc));
+        }
+        fs::create_dir_all(root.join("devices/system/cpu/cpu6")).unwrap();
+        fs::create_dir_all(root.join("devices/system/cpu/cpuidle")).unwrap();
+        write(&root, "devices/cpu_atom/cpus", "4-5\n");
+        root
+    }
+
+    #[test]
+    fn parse_cpu_list_accepts_ranges_and_singles() {
+        assert_eq!(parse_cpu_list("0-3,8,10-11\n").unwrap(), vec![0, 1, 2, 3, 8, 10, 11]);
+        assert_eq!(parse_cpu_list(" 3, 1-2 ,1").unwrap(), vec![1, 2, 3]);
+        assert_eq!(parse_cpu_list("").unwrap(), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn parse_cpu_list_rejects_bad_input() {
+        assert!(parse_cpu_list("3-1").is_err());
+        assert!(parse_cpu_list("a-b").is_err());
+        assert!(parse_cpu_list("1,,x").is_err());
+    }
+
+    #[test]
+    fn parse_cpu_list_rejects_ids_beyond_limit() {
+        assert!(parse_cpu_list(&format!("{}", MAX_CPUS - 1)).is_ok());
+        assert!(parse_cpu_list(&format!("{}", MAX_CPUS)).is_err());
+        // 不能因为一个巨大的范围去分配上亿个元素
+        assert!(parse_cpu_list("0-18446744073709551615").is_err());
+        assert!(parse_cpu_list("0-99999999999999999999999").is_err());
+    }
+
+    #[test]
+    fn from_sysfs_reads_fixture_tree() {
+        let topology = Topology::from_sysfs(&*hybrid_fixture()).unwrap();
+        assert_eq!(topology.all().iter().collect::<Vec<_>>(), vec![0, 1, 2, 3, 4, 5]);
+        assert_eq!(topology.cores(), vec![vec![0, 1], vec![2, 3], vec![4], vec![5]]);
+        assert_eq!(topology.siblings(3), vec![2, 3]);
+        assert!(topology.is_hybrid());
+        assert_eq!(topology.efficiency_cpus().iter().collect::<Vec<_>>(), vec![4, 5]);
+        assert_eq!(topology.llc_groups(), vec![vec![0, 1, 2, 3], vec![4, 5]]);
+    }
+
+    #[test]
+    fn from_sysfs_without_cpus_fails() {
+        let root = paths::ScratchDir::new("sysfs-empty");
+        fs::create_dir_all(root.join("devices/system/cpu")).unwrap();
+        assert!(Topology::from_sysfs(&*root).is_err());
+        assert!(Topology::from_sysfs(root.join("missing")).is_err());
+    }
+
+    #[test]
+    fn smt_sibling_avoids_cores_used_by_game() {
+        let topology = Topology::new(
+            [(0, 0), (1, 0), (2, 2), (3, 2)]
+                .into_iter()
+                .map(|(id, core)| LogicalCpu { id, package: 0, core, efficiency_class: 0, llc: None })
+                .collect(),
+        );
+        let ctx = PlacementContext::new(&topology).with_load(vec![0.0, 0.0, 50.0, 50.0]);
+        assert_eq!(ctx.smt_sibling(), Some(1));
+        assert_eq!(ctx.clone().with_game_cpus(CpuSet::from_iter([0])).smt_sibling(), Some(3));
+        assert_eq!(ctx.with_game_cpus(CpuSet::from_iter([1, 2])).smt_sibling(), None);
+        assert_eq!(PlacementContext::new(&Topology::flat(4)).smt_sibling(), None);
+    }
+}
@@ -0,0 +1,591 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::autostart::{Autostart, AutostartBackend};
+use crate::paths;
+use crate::policy::{self, PerfOptions, PolicyStore};
+
+type Result<T> = std::result::Result<T, String>;
+
+/// 事务能改动的持久化存储
+#[derive(Clone, Copy)]
+pub struct Stores<'a> {
+    pub policy: &'a dyn PolicyStore,
+    pub autostart: &'a Autostart,
+}
+
+// --- 单项修改 ---
+
+/// 对一个持久化项的修改，同时记下修改前后的值；`None` 表示不存在
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum Change {
+    /// IFEO `PerfOptions`（非 Windows 为规则文件）
+    Policy { exe_name: String, before: Option<PerfOptions>, after: Option<PerfOptions> },
+    /// 某个自启后端登记的启动命令
+    Autostart { backend: String, before: Option<PathBuf>, after: Option<PathBuf> },
+}
+
+impl Change {
+    pub fn describe(&self) -> String {
+        match self {
+            Change::Policy { exe_name, after: Some(_), .. } => format!("写入 {} 的优先级", exe_name),
+            Change::Policy { exe_name, after: None, .. } => format!("删除 {} 的优先级", exe_name),
+            Change::Autostart { backend, after: Some(_), .. } => format!("登记自启 ({})", backend),
+            Change::Autostart { backend, after: None, .. } => format!("删除自启 ({})", backend),
+        }
+    }
+
+    fn set_policy(stores: &Stores, exe_name: &str, value: &Option<PerfOptions>) -> Result<()> {
+        match value {
+            Some(options) => stores.policy.set(exe_name, options),
+            None => stores.policy.remove(exe_name).map(|_| ()),
+        }
+    }
+
+    fn set_autostart(stores: &Stores, backend: &str, value: &Option<PathBuf>) -> Result<()> {
+        let backend = stores.autostart.find(backend).ok_or_else(|| format!("不支持的自启方式: {}", backend))?;
+        match value {
+            Some(exe) => backend.enable(exe),
+            None => backend.disable().map(|_| ()),
+        }
+    }
+
+    fn apply(&self, stores: &Stores) -> Result<()> {
+        match self {
+            Change::Policy { exe_name, after, .. } => Self::set_policy(stores, exe_name, after),
+            Change::Autostart { backend, after, .. } => Self::set_autostart(stores, backend, after),
+        }
+    }
+
+    /// 写回修改前的值；重复执行结果相同，修改只做了一半时也能用
+    fn undo(&self, stores: &Stores) -> Result<()> {
+        match self {
+            Change::Policy { exe_name, before, .. } => Self::set_policy(stores, exe_name, before),
+            Change::Autostart { backend, before, .. } => Self::set_autostart(stores, backend, before),
+        }
+    }
+
+    /// 当前值是否仍是这次修改写入的值；不是时说明之后又被改过，不能直接撤销
+    fn is_current(&self, stores: &Stores) -> Result<bool> {
+        match self {
+            Change::Policy { exe_name, after, .. } => Ok(stores.policy.get(exe_name)? == *after),
+            Change::Autostart { backend, after, .. } => {
+                let backend = stores.autostart.find(backend).ok_or_else(|| format!("不支持的自启方式: {}", backend))?;
+                Ok(backend.query()? == *after)
+            }
+        }
+    }
+
+    /// 撤销用的反向修改
+    fn inverse(&self) -> Change {
+        match self.clone() {
+            Change::Policy { exe_name, before, after } => Change::Policy { exe_name, before: after, after: before },
+            Change::Autostart { backend, before, after } => Change::Autostart { backend, before: after, after: before },
+        }
+    }
+}
+
+// --- 事务 ---
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum State {
+    /// 正在执行；重启后仍是这个状态说明上次中途崩溃，需要回滚
+    Pending,
+    Committed,
+    RolledBack,
+    /// 已被之后的事务撤销
+    Reverted,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Record {
+    pub id: u64,
+    pub description: String,
+    /// 开始时间（Unix 秒）
+    pub at: u64,
+    pub state: State,
+    pub changes: Vec<Change>,
+    /// 撤销本事务的事务 id
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reverted_by: Option<u64>,
+}
+
+/// 一次事务中的修改：每一项都先把备份写进历史再动手
+pub struct Transaction<'a> {
+    history: &'a History,
+    stores: Stores<'a>,
+    record: Record,
+    /// 预览：只记录修改，不写历史也不动存储
+    dry_run: bool,
+}
+
+impl Transaction<'_> {
+    /// 整体替换该可执行文件的设置，`None` 或空设置表示删除
+    pub fn set_policy(&mut self, exe_name: &str, options: Option<PerfOptions>) -> Result<()> {
+        let before = self.policy(exe_name)?;
+        let after = options.filter(|o| !o.is_empty());
+        self.apply(Change::Policy { exe_name: exe_name.to_string(), before, after })
+    }
+
+    /// 读取后修改再写回，返回写入的设置
+    pub fn update_policy(&mut self, exe_name: &str, f: &dyn Fn(&mut PerfOptions)) -> Result<PerfOptions> {
+        let mut options = self.policy(exe_name)?.unwrap_or_default();
+        f(&mut options);
+        self.set_policy(exe_name, Some(options))?;
+        Ok(options)
+    }
+
+    /// 删除该可执行文件的全部设置，返回之前是否存在
+    pub fn remove_policy(&mut self, exe_name: &str) -> Result<bool> {
+        let existed = self.policy(exe_name)?.is_some();
+        self.set_policy(exe_name, None)?;
+        Ok(existed)
+    }
+
+    /// 在某个后端登记（`Some`）或删除（`None`）自启项，返回之前是否已登记
+    pub fn set_autostart(&mut self, backend: &dyn AutostartBackend, exe: Option<&Path>) -> Result<bool> {
+        let before = self.autostart(backend)?;
+        let existed = before.is_some();
+        self.apply(Change::Autostart { backend: backend.name().to_string(), before, after: exe.map(Path::to_path_buf) })?;
+        Ok(existed)
+    }
+
+    /// 执行预览时生成的修改；当前值已不是预览时看到的值就拒绝，免得覆盖别人的修改
+    pub fn replay(&mut self, change: &Change) -> Result<()> {
+        let unchanged = match change {
+            Change::Policy { exe_name, before, .. } => self.policy(exe_name)? == *before,
+            Change::Autostart { backend, before, .. } => {
+                let backend = self.stores.autostart.find(backend).ok_or_else(|| format!("不支持的自启方式: {}", backend))?;
+                self.autostart(backend)? == *before
+            }
+        };
+        if !unchanged {
+            return Err(format!("“{}”的当前值已变化，请重新生成计划", change.describe()));
+        }
+        self.apply(change.clone())
+    }
+
+    /// 本事务里写过的值优先；预览时存储本身还没有改
+    fn policy(&self, exe_name: &str) -> Result<Option<PerfOptions>> {
+        let written = self.record.changes.iter().rev().find_map(|c| match c {
+            Change::Policy { exe_name: name, after, .. } if name == exe_name => Some(*after),
+            _ => None,
+        });
+        written.map_or_else(|| self.stores.policy.get(exe_name), Ok)
+    }
+
+    fn autostart(&self, backend: &dyn AutostartBackend) -> Result<Option<PathBuf>> {
+        let written = self.record.changes.iter().rev().find_map(|c| match c {
+            Change::Autostart { backend: name, after, .. } if name == backend.name() => Some(after.clone()),
+            _ => None,
+        });
+        written.map_or_else(|| backend.query(), Ok)
+    }
+
+    fn apply(&mut self, change: Change) -> Result<()> {
+        let unchanged = match &change {
+            Change::Policy { before, after, .. } => before == after,
+            // 自启项重新登记可以修复损坏的条目，只跳过“删除不存在的项”
+            Change::Autostart { before: None, after: None, .. } => true,
+            Change::Autostart { .. } => false,
+        };
+        if unchanged {
+            return Ok(());
+        }
+        if self.dry_run {
+            self.record.changes.push(change);
+            return Ok(());
+        }
+        // 写失败时可能已经改了一半，所以先登记再执行，回滚时一并写回原值
+        self.record.changes.push(change.clone());
+        self.history.save_record(&self.record)?;
+        change.apply(&self.stores)
+    }
+}
+
+// --- 历史 ---
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct HistoryFile {
+    #[serde(default)]
+    version: u32,
+    #[serde(default)]
+    records: Vec<Record>,
+}
+
+/// 所有持久化修改的事务历史，写在配置目录下的 `transactions.json`
+pub struct History {
+    path: PathBuf,
+    records: Mutex<Vec<Record>>,
+    /// 同一进程内同一时间只允许一个事务执行，跨进程另有文件锁，见 `exclusive`
+    running: Mutex<()>,
+}
+
+impl History {
+    pub const FILE_NAME: &'static str = "transactions.json";
+    pub const VERSION: u32 = 1;
+    /// 只保留最近的记录，进行中的事务不计入
+    pub const LIMIT: usize = 200;
+
+    /// 读不出来的文件原样保留：`exclusive` 每次都重新读取，在文件修好之前事务都会报错，不会覆盖其中的备份
+    pub fn open(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let records = load(&path).map(|file| file.records).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            Vec::new()
+        });
+        Self { path, records: Mutex::new(records), running: Mutex::new(()) }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// 最新的在前
+    pub fn records(&self) -> Vec<Record> {
+        self.reload();
+        self.records.lock().unwrap().iter().rev().cloned().collect()
+    }
+
+    pub fn get(&self, id: u64) -> Option<Record> {
+        self.reload();
+        self.records.lock().unwrap().iter().find(|r| r.id == id).cloned()
+    }
+
+    /// 重新读取文件，其他进程（如命令行）提交的事务也能看到；读取失败时保留上次的内容
+    fn reload(&self) {
+        match load(&self.path) {
+            Ok(file) => *self.records.lock().unwrap() = file.records,
+            Err(e) => eprintln!("{}", e),
+        }
+    }
+
+    /// 独占事务历史并重新读取文件
+    ///
+    /// 界面和命令行各有一份内存中的历史，每次保存都整体写回；只有在文件锁内从最新内容改起，
+    /// 才不会丢掉对方的记录，也不会把对方正在执行的事务当作崩溃遗留回滚掉。
+    fn exclusive(&self) -> Result<(MutexGuard<'_, ()>, paths::FileLock)> {
+        let running = self.running.lock().unwrap();
+        let lock = paths::lock(&self.path).map_err(|e| format!("锁定事务历史失败 ({}): {}", self.path.display(), e))?;
+        *self.records.lock().unwrap() = load(&self.path)?.records;
+        Ok((running, lock))
+    }
+
+    /// 执行 `f` 中的全部修改：返回 `Ok` 时提交，返回 `Err` 时按相反顺序写回所有原值
+    pub fn run<T>(&self, stores: Stores, description: &str, f: impl FnOnce(&mut Transaction) -> Result<T>) -> Result<T> {
+        let _exclusive = self.exclusive()?;
+        self.run_locked(stores, description, f)
+    }
+
+    /// `run` 的主体，调用方已通过 `exclusive` 独占历史
+    fn run_locked<T>(&self, stores: Stores, description: &str, f: impl FnOnce(&mut Transaction) -> Result<T>) -> Result<T> {
+        let id = self.records.lock().unwrap().iter().map(|r| r.id).max().unwrap_or(0) + 1;
+        let record = Record { id, description: description.to_string(), at: now(), state: State::Pending, changes: Vec::new(), reverted_by: None };
+        let mut tx = Transaction { history: self, stores, record, dry_run: false };
+
+        match f(&mut tx) {
+            Ok(value) => {
+                let mut record = tx.record;
+                if record.changes.is_empty() {
+                    self.discard(id)?;
+                } else {
+                    record.state = State::Committed;
+                    self.save_record(&record)?;
+                }
+                Ok(value)
+            }
+            Err(e) => {
+                let mut record = tx.record;
+                let errors = rollback(&stores, &record.changes);
+                if !errors.is_empty() {
+                    // 留在 Pending 状态，下次启动时再试
+                    return Err(format!("{}；回滚失败: {}", e, errors.join("; ")));
+                }
+                if record.changes.is_empty() {
+                    self.discard(id)?;
+                } else {
+                    record.state = State::RolledBack;
+                    self.save_record(&record)?;
+                }
+                Err(format!("{}（已回滚）", e))
+            }
+        }
+    }
+
+    /// 只演算 `f` 会做的修改，不写入任何东西；返回 `Err` 时同样什么都不留下
+    pub fn preview(&self, stores: Stores, f: impl FnOnce(&mut Transaction) -> Result<()>) -> Result<Vec<Change>> {
+        let record = Record { id: 0, description: String::new(), at: now(), state: State::Pending, changes: Vec::new(), reverted_by: None };
+        let mut tx = Transaction { history: self, stores, record, dry_run: true };
+        f(&mut tx)?;
+        Ok(tx.record.changes)
+    }
+
+    /// 以一个新事务撤销已提交的事务；其中的项被之后的修改覆盖时拒绝撤销
+    pub fn revert(&self, stores: Stores, id: u64) -> Result<Record> {
+        // 从检查到标记为已撤销都不能插入别的事务，否则同一事务可能被撤销两次
+        let _exclusive = self.exclusive()?;
+        let record = self.records.lock().unwrap().iter().find(|r| r.id == id).cloned();
+        let record = record.ok_or_else(|| format!("事务 #{} 不存在", id))?;
+        if record.state != State::Committed {
+            return Err(format!("事务 #{} 未提交或已撤销，无法撤销", id));
+        }
+        for change in &record.changes {
+            if !change.is_current(&stores)? {
+                return Err(format!("事务 #{} 中的“{}”已被之后的修改覆盖，请先撤销之后的事务", id, change.describe()));
+            }
+        }
+        let revert_id = self.run_locked(stores, &format!("撤销 #{} {}", id, record.description), |tx| {
+            for change in record.changes.iter().rev() {
+                tx.apply(change.inverse())?;
+            }
+            Ok(tx.record.id)
+        })?;
+        let mut reverted = record;
+        reverted.state = State::Reverted;
+        reverted.reverted_by = Some(revert_id);
+        self.save_record(&reverted)?;
+        Ok(reverted)
+    }
+
+    /// 回滚上次运行中没有完成的事务，返回失败原因
+    pub fn recover(&self, stores: Stores) -> Vec<String> {
+        let _exclusive = match self.exclusive() {
+            Ok(exclusive) => exclusive,
+            Err(e) => return vec![e],
+        };
+        let pending: Vec<Record> = self.records.lock().unwrap().iter().filter(|r| r.state == State::Pending).cloned().collect();
+        let mut errors = Vec::new();
+        for mut record in pending {
+            let failed = rollback(&stores, &record.changes);
+            if failed.is_empty() {
+                record.state = State::RolledBack;
+                if let Err(e) = self.save_record(&record) { errors.push(e); }
+            } else {
+                errors.push(format!("回滚事务 #{} 失败: {}", record.id, failed.join("; ")));
+            }
+        }
+        errors
+    }
+
+    fn save_record(&self, record: &Record) -> Result<()> {
+        let mut records = self.records.lock().unwrap();
+        let mut updated = records.clone();
+        match updated.iter_mut().find(|r| r.id == record.id) {
+            Some(existing) => *existing = record.clone(),
+            None => updated.push(record.clone()),
+        }
+        let finished = updated.iter().filter(|r| r.state != State::Pending).count();
+        let mut excess = finished.saturating_sub(Self::LIMIT);
+        updated.retain(|r| {
+            let drop = excess > 0 && r.state != State::Pending;
+            if drop { excess -= 1; }
+            !drop
+        });
+        save(&self.path, &updated)?;
+        *records = updated;
+        Ok(())
+    }
+
+    fn discard(&self, id: u64) -> Result<()> {
+        let mut records = self.records.lock().unwrap();
+        let mut updated = records.clone();
+        updated.retain(|r| r.id != id);
+        save(&self.path, &updated)?;
+        *records = updated;
+        Ok(())
+    }
+}
+
+/// 按相反顺序写回原值，返回失败原因
+fn rollback(stores: &Stores, changes: &[Change]) -> Vec<String> {
+    changes.iter().rev().filter_map(|c| c.undo(stores).err().map(|e| format!("{}: {}", c.describe(), e))).collect()
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+fn load(path: &Path) -> Result<HistoryFile> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(HistoryFile::default()),
+        Err(e) => return Err(format!("读取事务历史失败 ({}): {}", path.display(), e)),
+    };
+    let file: HistoryFile =
+        serde_json::from_str(&content).map_err(|e| format!("事务历史格式错误 ({}): {}", path.display(), e))?;
+    if file.version > History::VERSION {
+        return Err(format!("事务历史版本 {} 过新，当前仅支持 {}", file.version, History::VERSION));
+    }
+    Ok(file)
+}
+
+fn save(path: &Path, records: &[Record]) -> Result<()> {
+    let file = HistoryFile { version: History::VERSION, records: records.to_vec() };
+    let content = serde_json::to_string_pretty(&file).map_err(|e| format!("序列化事务历史失败: {}", e))?;
+    paths::write_atomic(path, &content).map_err(|e| format!("写入事务历史失败 ({}): {}", path.display(), e))
+}
+
+// --- 默认实例 ---
+
+/// 进程内共享的事务历史；第一次使用时回滚上次崩溃留下的未完成事务
+pub fn history() -> &'static History {
+    static HISTORY: OnceLock<History> = OnceLock::new();
+    HISTORY.get_or_init(|| {
+        let history = History::open(paths::config_dir().join(History::FILE_NAME));
+        let autostart = Autostart::default();
+        for e in history.recover(Stores { policy: policy::store(), autostart: &autostart }) {
+            eprintln!("{}", e);
+        }
+        history
+    })
+}
+
+/// 在默认存储上执行一个事务
+pub fn run<T>(description: &str, f: impl FnOnce(&mut Transaction) -> Result<T>) -> Result<T> {
+    let autostart = Autostart::default();
+    history().run(Stores { policy: policy::store(), autostart: &autostart }, description, f)
+}
+
+/// 在默认存储上预览一个事务
+pub fn preview(f: impl FnOnce(&mut Transaction) -> Result<()>) -> Result<Vec<Change>> {
+    let autostart = Autostart::default();
+    history().preview(Stores { policy: policy::store(), autostart: &autostart }, f)
+}
+
+/// 在默认存储上撤销一个已提交的事务
+pub fn revert(id: u64) -> Result<Record> {
+    let autostart = Autostart::default();
+    history().revert(Stores { policy: policy::store(), autostart: &autostart }, id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::policy::FilePolicyStore;
+
+    struct Fixture {
+        _dir: paths::ScratchDir,
+        history: History,
+        policy: FilePolicyStore,
+        autostart: Autostart,
+    }
+
+    impl Fixture {
+        fn new() -> Self {
+            let dir = paths::ScratchDir::new("transaction");
+            let history = History::open(dir.join(History::FILE_NAME));
+            let policy = FilePolicyStore::new(dir.join(FilePolicyStore::FILE_NAME));
+            Self { _dir: dir, history, policy, autostart: Autostart::new(Vec::new()) }
+        }
+
+        fn stores(&self) -> Stores<'_> {
+            Stores { policy: &self.policy, autostart: &self.autostart }
+        }
+    }
+
+    fn options(cpu_priority: u32) -> PerfOptions {
+        PerfOptions { cpu_priority: Some(cpu_priority), ..Default::default() }
+    }
+
+    #[test]
+    fn run_commits_changes_and_skips_empty_transactions() {
+        let fx = Fixture::new();
+        fx.history.run(fx.stores(), "写入", |tx| tx.set_policy("game.exe", Some(options(1)))).unwrap();
+        assert_eq!(fx.policy.get("game.exe").unwrap(), Some(options(1)));
+        // 写入相同的值不算修改，不留记录
+        fx.history.run(fx.stores(), "重复写入", |tx| tx.set_policy("game.exe", Some(options(1)))).unwrap();
+
+        let records = History::open(fx.history.path()).records();
+        assert_eq!(records.len(), 1);
+        assert_eq!((records[0].id, records[0].state), (1, State::Committed));
+        assert_eq!(
+            records[0].changes,
+            vec![Change::Policy { exe_name: "game.exe".into(), before: None, after: Some(options(1)) }]
+        );
+    }
+
+    #[test]
+    fn failed_transaction_rolls_back_in_reverse_order() {
+        let fx = Fixture::new();
+        fx.policy.set("game.exe", &options(2)).unwrap();
+        let result: Result<()> = fx.history.run(fx.stores(), "失败", |tx| {
+            tx.set_policy("game.exe", Some(options(1)))?;
+            tx.update_policy("game.exe", &|o| o.io_priority = Some(0))?;
+            tx.set_policy("other.exe", Some(options(1)))?;
+            Err("出错".to_string())
+        });
+        assert_eq!(result.unwrap_err(), "出错（已回滚）");
+        assert_eq!(fx.policy.list().unwrap(), vec![("game.exe".to_string(), options(2))]);
+        let record = fx.history.get(1).unwrap();
+        assert_eq!(record.state, State::RolledBack);
+        assert_eq!(record.changes.len(), 3);
+    }
+
+    #[test]
+    fn replay_rejects_changed_values() {
+        let fx = Fixture::new();
+        let changes = fx.history.preview(fx.stores(), |tx| tx.set_policy("game.exe", Some(options(1))).map(|_| ())).unwrap();
+        assert!(fx.history.records().is_empty() && fx.policy.list().unwrap().is_empty());
+
+        // 预览之后别人写入了新值，计划作废
+        fx.policy.set("game.exe", &options(3)).unwrap();
+        let result = fx.history.run(fx.stores(), "执行计划", |tx| changes.iter().try_for_each(|c| tx.replay(c)));
+        assert!(result.unwrap_err().contains("当前值已变化"));
+        assert_eq!(fx.policy.get("game.exe").unwrap(), Some(options(3)));
+
+        fx.policy.remove("game.exe").unwrap();
+        fx.history.run(fx.stores(), "执行计划", |tx| changes.iter().try_for_each(|c| tx.replay(c))).unwrap();
+        assert_eq!(fx.policy.get("game.exe").unwrap(), Some(options(1)));
+    }
+
+    #[test]
+    fn revert_restores_previous_values_once() {
+        let fx = Fixture::new();
+        fx.policy.set("game.exe", &options(2)).unwrap();
+        fx.history.run(fx.stores(), "写入", |tx| tx.set_policy("game.exe", Some(options(1)))).unwrap();
+
+        let reverted = fx.history.revert(fx.stores(), 1).unwrap();
+        assert_eq!((reverted.state, reverted.reverted_by), (State::Reverted, Some(2)));
+        assert_eq!(fx.policy.get("game.exe").unwrap(), Some(options(2)));
+        assert!(fx.history.revert(fx.stores(), 1).is_err());
+        assert!(fx.history.revert(fx.stores(), 9).is_err());
+
+        // 之后的事务又改过同一项时不能越过它撤销
+        fx.history.run(fx.stores(), "删除", |tx| tx.remove_policy("game.exe")).unwrap();
+        let error = fx.history.revert(fx.stores(), 2).unwrap_err();
+        assert!(error.contains("已被之后的修改覆盖"));
+        assert_eq!(fx.policy.get("game.exe").unwrap(), None);
+    }
+
+    #[test]
+    fn recover_rolls_back_pending_records() {
+        let fx = Fixture::new();
+        let change = Change::Policy { exe_name: "game.exe".into(), before: None, after: Some(options(1)) };
+        let record = Record { id: 1, description: "崩溃".into(), at: 0, state: State::Pending, changes: vec![change], reverted_by: None };
+        save(fx.history.path(), &[record]).unwrap();
+        fx.policy.set("game.exe", &options(1)).unwrap();
+
+        assert!(fx.history.recover(fx.stores()).is_empty());
+        assert_eq!(fx.policy.get("game.exe").unwrap(), None);
+        assert_eq!(fx.history.get(1).unwrap().state, State::RolledBack);
+    }
+
+    #[test]
+    fn unreadable_history_is_left_untouched() {
+        let fx = Fixture::new();
+        let content = r#"{ "version": 99, "records": [] }"#;
+        fs::write(fx.history.path(), content).unwrap();
+        let history = History::open(fx.history.path());
+        assert!(history.run(fx.stores(), "写入", |tx| tx.set_policy("game.exe", Some(options(1)))).is_err());
+        assert!(history.revert(fx.stores(), 1).is_err());
+        assert_eq!(fx.policy.get("game.exe").unwrap(), None);
+        assert_eq!(fs::read_to_string(history.path()).unwrap(), content);
+        assert!(!history.path().with_extension("json.bad").exists());
+    }
+}
@@ -0,0 +1,90 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Mutex, OnceLock};
+
+use sysinfo::System;
+
+/// 一次枚举得到的进程父子关系
+#[derive(Debug, Default)]
+pub struct ProcessTree {
+    start_time: HashMap<u32, u64>,
+    children: HashMap<u32, Vec<u32>>,
+}
+
+impl ProcessTree {
+    /// `(pid, 父 pid, 启动时间)`
+    pub fn new(entries: impl IntoIterator<Item = (u32, Option<u32>, u64)>) -> Self {
+        let entries: Vec<_> = entries.into_iter().collect();
+        let start_time: HashMap<u32, u64> = entries.iter().map(|&(pid, _, start)| (pid, start)).collect();
+        let mut children: HashMap<u32, Vec<u32>> = HashMap::new();
+        for &(pid, parent, start) in &entries {
+            let Some(parent) = parent.filter(|&p| p != pid) else { continue };
+            // 父进程比子进程启动得晚，说明父 PID 已被复用，不是真正的父子关系
+            if start_time.get(&parent).is_some_and(|&p| p <= start) {
+                children.entry(parent).or_default().push(pid);
+            }
+        }
+        Self { start_time, children }
+    }
+
+    pub fn from_system(sys: &System) -> Self {
+        Self::new(sys.processes().iter().map(|(pid, p)| (pid.as_u32(), p.parent().map(|pp| pp.as_u32()), p.start_time())))
+    }
+
+    pub fn contains(&self, pid: u32) -> bool {
+        self.start_time.contains_key(&pid)
+    }
+
+    pub fn start_time(&self, pid: u32) -> Option<u64> {
+        self.start_time.get(&pid).copied()
+    }
+
+    /// 广度优先列出所有后代，不含自身
+    pub fn descendants(&self, pid: u32) -> Vec<u32> {
+        let mut seen = HashSet::from([pid]);
+        let mut queue = VecDeque::from([pid]);
+        let mut out = Vec::new();
+        while let Some(current) = queue.pop_front() {
+            for &child in self.children.get(&current).into_iter().flatten() {
+                if seen.insert(child) {
+                    out.push(child);
+                    queue.push_back(child);
+                }
+            }
+        }
+        out
+    }
+}
+
+/// 跨枚举记住“因继承而受限”的进程
+///
+/// 父进程退出后子进程会被过继给 init/explorer，单看当前的进程树就找不回来了。
+#[derive(Default)]
+pub struct Lineage {
+    /// pid -> (规则名, 启动时间)
+    inherited: Mutex<HashMap<u32, (String, u64)>>,
+}
+
+impl Lineage {
+    /// 用当前进程树刷新记录：丢掉已退出或 PID 被复用的，返回仍然存活的记录
+    pub fn refresh(&self, tree: &ProcessTree) -> HashMap<u32, String> {
+        let mut inherited = self.inherited.lock().unwrap();
+        inherited.retain(|&pid, (_, start)| tree.start_time(pid) == Some(*start));
+        inherited.iter().map(|(&pid, (rule, _))| (pid, rule.clone())).collect()
+    }
+
+    pub fn record(&self, tree: &ProcessTree, pid: u32, rule: &str) {
+        if let Some(start) = tree.start_time(pid) {
+            self.inherited.lock().unwrap().insert(pid, (rule.to_string(), start));
+        }
+    }
+
+    pub fn forget_rule(&self, rule: &str) {
+        self.inherited.lock().unwrap().retain(|_, (r, _)| r != rule);
+    }
+}
+
+/// 进程内共享的继承记录
+pub fn lineage() -> &'static Lineage {
+    static LINEAGE: OnceLock<Lineage> = OnceLock::new();
+    LINEAGE.get_or_init(Lineage::default)
+}
@@ -0,0 +1,186 @@
+use std::collections::BTreeMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::actions::ActionKind;
+use crate::matcher::ProcessName;
+use crate::profiles::Catalog;
+
+/// 前端监听的事件名
+pub const EVENT: &str = "game-watch";
+
+/// 自动检测的开关，由前端的“循环”开关控制
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WatchSettings {
+    pub enabled: bool,
+    /// 限制伴随进程时启用的动作类别
+    pub kinds: Vec<ActionKind>,
+    /// 两次全量扫描之间的间隔（秒）；目标或游戏进程启动时由进程事件提前唤醒
+    pub interval_secs: u64,
+}
+
+impl Default for WatchSettings {
+    fn default() -> Self {
+        Self { enabled: false, kinds: ActionKind::ALL.to_vec(), interval_secs: 30 }
+    }
+}
+
+impl WatchSettings {
+    pub fn interval(&self) -> Duration {
+        Duration::from_secs(self.interval_secs.max(1))
+    }
+}
+
+/// 两次扫描之间某个方案的游戏进程出现或消失
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Transition {
+    Started { profile: String, pids: Vec<u32> },
+    Exited { profile: String },
+}
+
+/// 记住每个方案当前正在运行的游戏进程
+#[derive(Debug, Default)]
+pub struct GameDetector {
+    running: BTreeMap<String, Vec<u32>>,
+}
+
+impl GameDetector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 用 `(pid, 进程名)` 列表刷新状态，返回与上一次相比的变化
+    pub fn update<'a>(&mut self, catalog: &Catalog, processes: impl IntoIterator<Item = (u32, ProcessName<'a>)>) -> Vec<Transition> {
+        let mut current: BTreeMap<String, Vec<u32>> = BTreeMap::new();
+        for (pid, name) in processes {
+            let profile = catalog
+                .profiles
+                .iter()
+                .find(|p| p.games.iter().any(|g| name.is(&g.exe_name)));
+            if let Some(profile) = profile {
+                current.entry(profile.id.clone()).or_default().push(pid);
+            }
+        }
+        for pids in current.values_mut() {
+            pids.sort_unstable();
+        }
+
+        let mut transitions: Vec<Transition> = self
+            .running
+            .keys()
+            .filter(|id| !current.contains_key(*id))
+            .map(|id| Transition::Exited { profile: id.clone() })
+            .collect();
+        transitions.extend(
+            current
+                .iter()
+                .filter(|(id, _)| !self.running.contains_key(*id))
+                .map(|(id, pids)| Transition::Started { profile: id.clone(), pids: pids.clone() }),
+        );
+        self.running = current;
+        transitions
+    }
+
+    /// 全部视为已退出，用于关闭自动检测时撤销
+    pub fn clear(&mut self) -> Vec<Transition> {
+        std::mem::take(&mut self.running).into_keys().map(|profile| Transition::Exited { profile }).collect()
+    }
+
+    pub fn running(&self) -> Vec<String> {
+        self.running.keys().cloned().collect()
+    }
+}
+
+/// 推送给前端的状态变化
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum WatchEvent {
+    GameStarted { profile: String, name: String, pids: Vec<u32> },
+    /// 游戏的持久化优先级被写入策略存储
+    ProfileApplied { profile: String, message: String },
+    CompanionsRestricted { profile: String, message: String },
+    CompanionsReleased { profile: String, message: String },
+    GameExited { profile: String, name: String },
+    /// 被限制的进程自己改回了设置，已重新施加
+    DriftCorrected { pid: u32, name: String, kinds: Vec<ActionKind>, drifts: u32, message: String },
+}
+
+/// `get_watch_status` 的返回值
+#[derive(Debug, Clone, Serialize)]
+pub struct WatchStatus {
+    #[serde(flatten)]
+    pub settings: WatchSettings,
+    /// 正在运行的游戏对应的方案 id
+    pub running: Vec<String>,
+}
+
+/// 后台线程与命令共享的状态
+#[derive(Default)]
+pub struct Watcher {
+    settings: Mutex<WatchSettings>,
+    detector: Mutex<GameDetector>,
+}
+
+impl Watcher {
+    pub fn settings(&self) -> WatchSettings {
+        self.settings.lock().unwrap().clone()
+    }
+
+    pub fn configure(&self, settings: WatchSettings) {
+        *self.settings.lock().unwrap() = settings;
+    }
+
+    pub fn detector(&self) -> std::sync::MutexGuard<'_, GameDetector> {
+        self.detector.lock().unwrap()
+    }
+
+    pub fn status(&self) -> WatchStatus {
+        WatchStatus { settings: self.settings(), running: self.detector().running() }
+    }
+}
+
+/// 进程内共享的检测状态
+pub fn watcher() -> &'static Watcher {
+    static WATCHER: OnceLock<Watcher> = OnceLock::new();
+    WATCHER.get_or_init(Watcher::default)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn catalog() -> Catalog {
+        toml::from_str(
+            r#"
+            [[profiles]]
+            id = "cyberpunk"
+            name = "赛博朋克 2077"
+            games = [{ exe_name = "Cyberpunk2077.exe", cpu_priority = 3, io_priority = 3 }]
+            "#,
+        )
+        .unwrap()
+    }
+
+    fn comm(name: &str) -> ProcessName<'_> {
+        ProcessName { name, ..Default::default() }
+    }
+
+    #[test]
+    fn detects_games_by_truncated_comm() {
+        let catalog = catalog();
+        let mut detector = GameDetector::new();
+        assert!(detector.update(&catalog, [(1, comm("bash"))]).is_empty());
+
+        let transitions = detector.update(&catalog, [(7, comm("Cyberpunk2077.e")), (3, comm("cyberpunk2077.exe"))]);
+        assert_eq!(transitions, vec![Transition::Started { profile: "cyberpunk".into(), pids: vec![3, 7] }]);
+        assert!(detector.update(&catalog, [(7, comm("Cyberpunk2077.e"))]).is_empty());
+        assert_eq!(detector.running(), vec!["cyberpunk".to_string()]);
+
+        assert_eq!(detector.update(&catalog, []), vec![Transition::Exited { profile: "cyberpunk".into() }]);
+        detector.update(&catalog, [(8, comm("Cyberpunk2077.e"))]);
+        assert_eq!(detector.clear(), vec![Transition::Exited { profile: "cyberpunk".into() }]);
+        assert!(detector.running().is_empty());
+    }
+}
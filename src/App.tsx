@@ -45,22 +45,33 @@ const Figure1Switch = styled((props: SwitchProps) => (
   },
 }));
 
-interface ProcessStatus { target_core: number; sguard64_restricted: boolean; message: string; }
+// target_cores 为核心列表，例如 "0-3,8"
+interface ProcessStatus { target_cores: string; sguard64_restricted: boolean; message: string; }
 interface LogEntry { id: number; timestamp: string; message: string; }
 interface SystemInfo { cpu_model: string; os_name: string; os_version: string; cpu_logical_cores: number; }
 interface ProcessPerformance { pid: number; name: string; cpu_usage: number; memory_mb: number; }
+type WatchEvent =
+  | { kind: 'game-started'; profile: string; name: string; pids: number[] }
+  | { kind: 'game-exited'; profile: string; name: string }
+  | { kind: 'profile-applied' | 'companions-restricted' | 'companions-released'; profile: string; message: string }
+  | { kind: 'drift-corrected'; pid: number; name: string; kinds: string[]; drifts: number; message: string };
+// 一键优化的预览，summary 每项一行
+interface RestrictionPlan { id: number; summary: string[]; }
+interface GameProfile { id: string; name: string; games: { exe_name: string }[]; companions: string[]; applied: boolean; }
 
 function App() {
-  const [targetCore, setTargetCore] = useState<number | null>(null);
+  const [targetCores, setTargetCores] = useState<string | null>(null);
   const [logs, setLogs] = useState<LogEntry[]>([]);
   const [loading, setLoading] = useState(false);
   const [darkMode, setDarkMode] = useState(true); 
   const logContainerRef = useRef<HTMLDivElement>(null);
   const [performance, setPerformance] = useState<ProcessPerformance[]>([]);
+  const [profiles, setProfiles] = useState<GameProfile[]>([]);
   
   const [showDisclaimer, setShowDisclaimer] = useState(false);
   const [disclaimerInput, setDisclaimerInput] = useState('');
   const [showExitDialog, setShowExitDialog] = useState(false);
+  const [pendingPlan, setPendingPlan] = useState<RestrictionPlan | null>(null);
 
   const [enableCpuAffinity, setEnableCpuAffinity] = useState(() => getSavedState('sw_cpu', true));
   const [enableProcessPriority, setEnableProcessPriority] = useState(() => getSavedState('sw_priority', true));
@@ -102,22 +113,60 @@ function App() {
     setLogs(prev => [...prev, { id: Date.now() + Math.random(), timestamp: new Date().toLocaleTimeString(), message }].slice(-100));
   }, []);
 
-  const runRegistryCommand = async (command: string, desc: string) => {
+  const runRegistryCommand = async (command: string, desc: string, args?: Record<string, unknown>) => {
     addLog(`指令: ${desc}`);
-    try { const msg = await invoke<string>(command); addLog(msg); } catch (e) { addLog(`❌ 错误: ${e}`); }
+    try { const msg = await invoke<string>(command, args); addLog(msg); } catch (e) { addLog(`❌ 错误: ${e}`); }
   };
 
-  const executeRestriction = useCallback(async (silent = false) => {
-    if (!silent) setLoading(true);
+  const refreshProfiles = useCallback(() => { invoke<GameProfile[]>('list_profiles').then(setProfiles); }, []);
+  const runProfileCommand = async (command: string, profile: GameProfile, desc: string) => {
+    await runRegistryCommand(command, `${profile.name}${desc}`, { id: profile.id });
+    refreshProfiles();
+  };
+
+  // 开关只决定启用哪些类别，具体参数由每条规则自己决定
+  const enabledKinds = useMemo(() => [
+    enableCpuAffinity && 'affinity', enableProcessPriority && 'priority', enableEfficiencyMode && 'eco',
+    enableIoPriority && 'io', enableMemoryPriority && 'memory',
+  ].filter(Boolean), [enableCpuAffinity, enableProcessPriority, enableEfficiencyMode, enableIoPriority, enableMemoryPriority]);
+
+  // 一键优化先生成计划，确认后才按计划执行
+  const previewRestriction = useCallback(async () => {
+    setLoading(true);
+    try { setPendingPlan(await invoke<RestrictionPlan>('plan_restrictions', { enabled: enabledKinds })); }
+    catch (e) { addLog(`生成计划失败: ${e}`); }
+    setLoading(false);
+  }, [addLog, enabledKinds]);
+
+  const applyPendingPlan = async () => {
+    if (!pendingPlan) return;
+    const id = pendingPlan.id;
+    setPendingPlan(null);
+    setLoading(true);
     try {
-      const result = await invoke<ProcessStatus>('restrict_processes', {
-        enableCpuAffinity, enableProcessPriority, enableEfficiencyMode, enableIoPriority, enableMemoryPriority 
-      });
-      if (result.target_core) setTargetCore(result.target_core);
-      if (!silent) addLog(result.message);
-    } catch (e) { if (!silent) addLog(`失败: ${e}`); }
-    if (!silent) setLoading(false);
-  }, [addLog, enableCpuAffinity, enableProcessPriority, enableEfficiencyMode, enableIoPriority, enableMemoryPriority]);
+      const result = await invoke<ProcessStatus>('apply_plan', { id });
+      if (result.target_cores) setTargetCores(result.target_cores);
+      addLog(result.message);
+    } catch (e) { addLog(`失败: ${e}`); }
+    setLoading(false);
+  };
+
+  // 游戏检测在后端运行，这里只同步开关并记录状态变化
+  useEffect(() => {
+    invoke('configure_watcher', { enabled: enableAutoLimit, kinds: enabledKinds }).catch(e => addLog(`自动检测设置错误: ${e}`));
+  }, [addLog, enableAutoLimit, enabledKinds]);
+
+  useEffect(() => {
+    const unlistenPromise = listen<WatchEvent>('game-watch', ({ payload }) => {
+      switch (payload.kind) {
+        case 'game-started': addLog(`检测到 ${payload.name} 启动`); break;
+        case 'game-exited': addLog(`${payload.name} 已退出`); break;
+        default: addLog(payload.message);
+      }
+      if (payload.kind === 'profile-applied') refreshProfiles();
+    });
+    return () => { unlistenPromise.then(f => f()); };
+  }, [addLog, refreshProfiles]);
 
   const toggleAutoStart = async () => {
     try {
@@ -137,15 +186,15 @@ function App() {
     const unlistenPromise = listen('tauri://close-requested', () => setShowExitDialog(true));
     addLog('核心服务已就绪');
     invoke<SystemInfo>('get_system_info').then(info => {
-        if (info.cpu_logical_cores > 0) setTargetCore(info.cpu_logical_cores - 1);
+        if (info.cpu_logical_cores > 0) setTargetCores(String(info.cpu_logical_cores - 1));
     });
     invoke<boolean>('check_autostart').then(setAutoStartEnabled);
+    refreshProfiles();
     const interval = setInterval(async () => {
       setPerformance(await invoke<ProcessPerformance[]>('get_process_performance'));
-      if (enableAutoLimit) executeRestriction(true);
     }, 30000); 
     return () => { unlistenPromise.then(f => f()); clearInterval(interval); };
-  }, [addLog, enableAutoLimit, executeRestriction]);
+  }, [addLog, refreshProfiles]);
 
   useEffect(() => { if (logContainerRef.current) logContainerRef.current.scrollTop = logContainerRef.current.scrollHeight; }, [logs]);
 
@@ -281,6 +330,19 @@ function App() {
         </DialogActions>
       </Dialog>
 
+      <Dialog open={pendingPlan !== null} onClose={() => setPendingPlan(null)} maxWidth="md" fullWidth>
+        <DialogTitle>确认优化计划</DialogTitle>
+        <DialogContent dividers>
+          {pendingPlan?.summary.map((line, i) => (
+            <Typography key={i} variant="body2" sx={{ fontFamily: 'Consolas, monospace', py: 0.3, wordBreak: 'break-all' }}>{line}</Typography>
+          ))}
+        </DialogContent>
+        <DialogActions sx={{ p: 2, gap: 1 }}>
+          <Button onClick={() => setPendingPlan(null)} color="inherit">取消</Button>
+          <Button variant="contained" onClick={applyPendingPlan}>执行</Button>
+        </DialogActions>
+      </Dialog>
+
       <Box sx={{ height: '100vh', display: 'flex', flexDirection: 'column', overflow: 'hidden', bgcolor: 'background.default', color: 'text.primary' }}>
         
         {/* 已移除 CustomTitleBar */}
@@ -300,7 +362,7 @@ function App() {
                 <Typography variant="caption" fontWeight="bold" color="text.secondary" mb={1} display="block">概览</Typography>
                 <Box p={2} borderRadius={2} bgcolor="background.default" mb={2} border={1} borderColor="divider">
                     <Box display="flex" alignItems="center" gap={1} mb={0.5}><MemoryIcon fontSize="small" color="primary"/><Typography variant="caption" color="text.secondary">目标核心</Typography></Box>
-                    <Typography variant="h4" fontWeight="bold" color="primary.main">#{targetCore !== null ? targetCore : '-'}</Typography>
+                    <Typography variant="h4" fontWeight="bold" color="primary.main">#{targetCores ?? '-'}</Typography>
                 </Box>
             </Box>
 
@@ -332,7 +394,10 @@ function App() {
             <Box display="flex" justifyContent="space-between" alignItems="center">
                 <Typography variant="h5" fontWeight="bold" color="text.primary">控制面板</Typography>
                 <Box display="flex" flexDirection="column" alignItems="flex-end">
-                <Button variant="contained" startIcon={<StartIcon />} onClick={() => executeRestriction(false)} disabled={loading} sx={{ px: 3, py:0.8, borderRadius: 2, background: 'linear-gradient(90deg, #409eff 0%, #3a8ee6 100%)', boxShadow: '0 4px 12px rgba(64,158,255,0.3)' }}>一键优化</Button>
+                <Box display="flex" gap={1}>
+                <Button variant="text" color="inherit" onClick={() => runRegistryCommand('restore_all', '还原所有进程')} sx={{ color: 'text.secondary' }}>还原</Button>
+                <Button variant="contained" startIcon={<StartIcon />} onClick={previewRestriction} disabled={loading} sx={{ px: 3, py:0.8, borderRadius: 2, background: 'linear-gradient(90deg, #409eff 0%, #3a8ee6 100%)', boxShadow: '0 4px 12px rgba(64,158,255,0.3)' }}>一键优化</Button>
+                </Box>
                 <Typography variant="caption" color="text.secondary" sx={{mt:0.5, fontSize:'0.75rem', fontWeight:'bold', color: 'error.main'}}>
                     请在进入游戏大厅后点击 (非永久生效)
                 </Typography>
@@ -352,11 +417,11 @@ function App() {
                                 <Typography variant="body2" fontWeight="bold" color="text.primary">自动化托管</Typography>
                                 <Chip size="small" label="SERVICE" sx={{height:16, fontSize:9, fontWeight:'bold', bgcolor:'primary.main', color:'#fff'}} />
                             </Box>
-                            <Typography variant="caption" sx={{color:'text.secondary', fontSize:'0.7rem', mt:0.5}}>开机自启 & 循环扫描</Typography>
+                            <Typography variant="caption" sx={{color:'text.secondary', fontSize:'0.7rem', mt:0.5}}>开机自启 & 游戏自动检测</Typography>
                         </Box>
                         <Box display="flex" gap={2}>
                             <FormControlLabel control={<Figure1Switch size="small" checked={autoStartEnabled} onChange={toggleAutoStart} />} label={<Typography variant="caption" fontWeight="bold" color="text.primary">自启</Typography>} sx={{mr:0}} />
-                            <FormControlLabel control={<Figure1Switch size="small" checked={enableAutoLimit} onChange={(e:any)=>setEnableAutoLimit(e.target.checked)} />} label={<Typography variant="caption" fontWeight="bold" color="text.primary">循环</Typography>} sx={{mr:0}} />
+                            <FormControlLabel control={<Figure1Switch size="small" checked={enableAutoLimit} onChange={(e:any)=>setEnableAutoLimit(e.target.checked)} />} label={<Typography variant="caption" fontWeight="bold" color="text.primary">检测</Typography>} sx={{mr:0}} />
                         </Box>
                     </Box>
                 </CoreCard>
@@ -368,21 +433,21 @@ function App() {
                       <IconButton size="small" sx={{color:'text.secondary', cursor: 'help'}}><InfoIcon fontSize="small" /></IconButton>
                     </Tooltip>
                   }>
-                    <Box mb={2} mt={1}>
-                    <Box display="flex" justifyContent="space-between" mb={1}><Typography variant="body2" fontWeight="bold" color="text.primary">三角洲行动</Typography></Box>
-                    <Box display="flex" gap={1}>
-                        <Button variant="contained" fullWidth size="small" color="secondary" onClick={() => runRegistryCommand('raise_delta_priority', '三角洲优化')} sx={{fontSize:'0.8rem', py:0.5}}>优化</Button>
-                        <Button variant="text" color="inherit" size="small" onClick={() => runRegistryCommand('reset_delta_priority', '恢复')} sx={{fontSize:'0.8rem', color: 'text.secondary'}}>撤销</Button>
+                    {profiles.map((profile, index) => (
+                    <Box key={profile.id}>
+                    {index > 0 && <Divider sx={{ my: 1.5 }} />}
+                    <Box mb={1} mt={index === 0 ? 1 : 0}>
+                    <Box display="flex" justifyContent="space-between" mb={1}>
+                        <Typography variant="body2" fontWeight="bold" color="text.primary">{profile.name}</Typography>
+                        {profile.applied && <Typography variant="caption" color="success.main">已优化</Typography>}
                     </Box>
-                    </Box>
-                    <Divider sx={{ my: 1.5 }} />
-                    <Box mb={1}>
-                    <Box display="flex" justifyContent="space-between" mb={1}><Typography variant="body2" fontWeight="bold" color="text.primary">无畏契约</Typography></Box>
                     <Box display="flex" gap={1}>
-                        <Button variant="contained" fullWidth size="small" color="secondary" onClick={() => runRegistryCommand('modify_valorant_registry_priority', '无畏契约优化')} sx={{fontSize:'0.8rem', py:0.5}}>优化</Button>
-                        <Button variant="text" color="inherit" size="small" onClick={() => runRegistryCommand('reset_valorant_priority', '恢复')} sx={{fontSize:'0.8rem', color: 'text.secondary'}}>撤销</Button>
+                        <Button variant="contained" fullWidth size="small" color="secondary" onClick={() => runProfileCommand('apply_profile', profile, '优化')} sx={{fontSize:'0.8rem', py:0.5}}>优化</Button>
+                        <Button variant="text" color="inherit" size="small" onClick={() => runProfileCommand('reset_profile', profile, '恢复')} sx={{fontSize:'0.8rem', color: 'text.secondary'}}>撤销</Button>
+                    </Box>
                     </Box>
                     </Box>
+                    ))}
                 </ClashCard>
 
                 <ClashCard title="注册表修改 (慎用)" icon={<WarningIcon />} danger>
@@ -58,7 +58,7 @@ async function executeProcessRestriction() {
     addLogEntry('进程限制开始b（￣▽￣）d　');
     
     const result = await invoke('restrict_processes') as {
-      target_core: number;
+      target_cores: string;
       sguard64_found: boolean;
       sguard64_restricted: boolean;
       sguardsvc64_found: boolean;
@@ -67,7 +67,7 @@ async function executeProcessRestriction() {
     };
     
     if (targetCoreEl) {
-      targetCoreEl.textContent = `核心 ${result.target_core}`;
+      targetCoreEl.textContent = `核心 ${result.target_cores}`;
     }
     
     if (sguard64StatusEl) {